@@ -0,0 +1,179 @@
+use crate::system::{
+    text::write_text,
+    variational::TangentVector,
+    vector,
+    System
+};
+const RENORMALIZE_ABOVE: f64 = 1.0E50;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChaosSample {
+    pub epoch: f64,
+    pub megno: f64,
+    pub lyapunov: f64
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChaosIndicator {
+    pub bodies: Vec<usize>,
+    pub history: Vec<ChaosSample>,
+    tangent: usize,
+    start: f64,
+    previous: f64,
+    weighted_rate: f64,
+    megno_sum: f64,
+    log_growth: f64
+}
+impl ChaosIndicator {
+    pub fn new(system: &mut System, bodies: &[usize]) -> Result<ChaosIndicator, String> {
+        let mut bodies = bodies.to_vec();
+        bodies.sort_unstable();
+        bodies.dedup();
+        if bodies.is_empty() {
+            return Err("Failed to seed tangent vector! No bodies were chosen".to_string())
+        }
+        if let Some(x) = bodies.iter().find(|a| **a >= system.size()) {
+            return Err(format!("Failed to find body {} in system!", x))
+        }
+        let mut tangent = TangentVector::zero(system.size());
+        let mut component = 0.0f64;
+        for body in bodies.iter() {
+            for a in tangent.position[*body].iter_mut().chain(tangent.velocity[*body].iter_mut()) {
+                component = (component + 0.7548776662466927).fract();
+                *a = component - 0.5;
+            }
+        }
+        let norm = tangent.norm_squared(&bodies).sqrt();
+        tangent.scale(1.0 / norm);
+        let tangent = system.add_tangent(tangent)?;
+        Ok(ChaosIndicator {
+            bodies,
+            history: Vec::new(),
+            tangent,
+            start: system.epoch(),
+            previous: system.epoch(),
+            weighted_rate: 0.0,
+            megno_sum: 0.0,
+            log_growth: 0.0
+        })
+    }
+    fn tangent_norm(&self, system: &System) -> Result<f64, String> {
+        match system.tangent(self.tangent) {
+            Some(x) => Ok(x.norm_squared(&self.bodies).sqrt()),
+            None => Err("Failed to find tangent vector in system! Were the tangents detached?".to_string())
+        }
+    }
+    pub fn record(&mut self, system: &mut System) -> Result<(), String> {
+        let norm = self.tangent_norm(system)?;
+        let tangent = system.tangent(self.tangent).unwrap();
+        let acceleration = system.linearized_accelerations(tangent);
+        let stretching = self.bodies.iter().map(|a| {
+            vector::dot(&tangent.velocity[*a], &tangent.position[*a]) + vector::dot(&acceleration[*a], &tangent.velocity[*a])
+        }).sum::<f64>();
+        let elapsed = system.epoch() - self.start;
+        let delta_time = system.epoch() - self.previous;
+        self.previous = system.epoch();
+        if norm > 0.0 && delta_time > 0.0 {
+            self.weighted_rate += stretching / norm.powi(2) * elapsed * delta_time;
+            self.megno_sum += 2.0 * self.weighted_rate / elapsed * delta_time;
+        }
+        if norm > RENORMALIZE_ABOVE {
+            self.log_growth += norm.ln();
+            system.tangent_mut(self.tangent).unwrap().scale(1.0 / norm);
+        }
+        Ok(())
+    }
+    pub fn sample(&mut self, system: &System) -> Result<ChaosSample, String> {
+        let sample = ChaosSample {
+            epoch: system.epoch(),
+            megno: self.megno(system),
+            lyapunov: self.lyapunov(system)?
+        };
+        self.history.push(sample);
+        Ok(sample)
+    }
+    pub fn megno(&self, system: &System) -> f64 {
+        let elapsed = system.epoch() - self.start;
+        if elapsed > 0.0 {
+            self.megno_sum / elapsed
+        } else {
+            0.0
+        }
+    }
+    pub fn lyapunov(&self, system: &System) -> Result<f64, String> {
+        let elapsed = system.epoch() - self.start;
+        let norm = self.tangent_norm(system)?;
+        if elapsed > 0.0 && norm > 0.0 {
+            Ok((self.log_growth + norm.ln()) / elapsed)
+        } else {
+            Ok(0.0)
+        }
+    }
+    pub fn save_csv(&self, file_path: &str) -> Result<(), String> {
+        let mut contents = "epoch,megno,lyapunov\n".to_string();
+        for a in self.history.iter() {
+            contents.push_str(&format!("{},{},{}\n", a.epoch, a.megno, a.lyapunov));
+        }
+        write_text(file_path, &contents)
+    }
+}
+pub fn estimate(system: &mut System, bodies: &[usize], duration: f64, cadence: usize) -> Result<ChaosIndicator, String> {
+    if cadence == 0 {
+        return Err("Failed to estimate chaos indicators! Cadence must be at least one step".to_string())
+    }
+    let mut indicator = ChaosIndicator::new(system, bodies)?;
+    system.run_for(duration, |system, step, last| {
+        indicator.record(system)?;
+        if step.is_multiple_of(cadence) || last {
+            indicator.sample(system)?;
+        }
+        Ok(())
+    })?;
+    Ok(indicator)
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use crate::{
+        system::{
+            body::Body,
+            orbital_elements::OrbitalElements
+        },
+        units::GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+    };
+    fn restricted_system(semi_major_axis: f64) -> System {
+        let mass_ratio = 1.0E-3;
+        let mut system = System::new();
+        system.add(Body::new("Primary".to_string(), (1.0 - mass_ratio) / GRAVITATIONAL_MASS_TO_AU3_PER_DAY2, 0.0,
+            [-mass_ratio, 0.0, 0.0], [0.0, -mass_ratio, 0.0]));
+        system.add(Body::new("Secondary".to_string(), mass_ratio / GRAVITATIONAL_MASS_TO_AU3_PER_DAY2, 0.0,
+            [1.0 - mass_ratio, 0.0, 0.0], [0.0, 1.0 - mass_ratio, 0.0]));
+        let elements = OrbitalElements {
+            semi_major_axis,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            mean_anomaly: 2.0
+        };
+        let (position, velocity) = elements.to_state(1.0);
+        system.add(Body::new("Particle".to_string(), 0.0, 0.0, position, velocity));
+        system.set_delta_time(2.0 * PI / 100.0);
+        system
+    }
+    #[test]
+    fn separate_regular_and_chaotic_orbits() -> Result<(), String> {
+        let mut regular = restricted_system(0.6);
+        let regular = estimate(&mut regular, &[2], 2.0 * PI * 300.0, 100)?;
+        let regular = regular.history.last().unwrap();
+        assert!((regular.megno - 2.0).abs() < 0.1, "regular orbit has megno {}", regular.megno);
+        let mut chaotic = restricted_system(1.1);
+        let chaotic = estimate(&mut chaotic, &[2], 2.0 * PI * 300.0, 100)?;
+        let chaotic = chaotic.history.last().unwrap();
+        assert!(chaotic.megno > 10.0, "chaotic orbit has megno {}", chaotic.megno);
+        assert!(chaotic.lyapunov > 5.0 * regular.lyapunov);
+        let mut system = restricted_system(0.6);
+        assert!(ChaosIndicator::new(&mut system, &[]).is_err());
+        assert!(ChaosIndicator::new(&mut system, &[3]).is_err());
+        Ok(())
+    }
+}
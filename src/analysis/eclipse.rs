@@ -0,0 +1,471 @@
+use std::collections::VecDeque;
+use crate::{
+    system::{
+        events::{
+            History,
+            State
+        },
+        text::{
+            csv_field,
+            write_text
+        },
+        vector,
+        System
+    },
+    units::{
+        AU_PER_LIGHT_DAY,
+        AU_TO_KM
+    }
+};
+const ECLIPSE_CONTACTS: [(&str, &str); 3] = [("P1", "P4"), ("U1", "U4"), ("U2", "U3")];
+const ALIGNMENT_CONTACTS: [(&str, &str); 2] = [("C1", "C4"), ("C2", "C3")];
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watch {
+    Eclipse {
+        source: usize,
+        occulter: usize,
+        target: usize
+    },
+    Alignment {
+        observer: usize,
+        first: usize,
+        second: usize
+    }
+}
+impl Watch {
+    fn bodies(&self) -> [usize; 3] {
+        match *self {
+            Watch::Eclipse { source, occulter, target } => [source, occulter, target],
+            Watch::Alignment { observer, first, second } => [observer, first, second]
+        }
+    }
+    fn kind(&self) -> EventKind {
+        match self {
+            Watch::Eclipse { .. } => EventKind::Eclipse,
+            Watch::Alignment { .. } => EventKind::Transit
+        }
+    }
+    fn contacts(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Watch::Eclipse { .. } => &ECLIPSE_CONTACTS,
+            Watch::Alignment { .. } => &ALIGNMENT_CONTACTS
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Eclipse,
+    Transit,
+    Occultation
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    pub name: &'static str,
+    pub epoch: f64
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub bodies: [usize; 3],
+    pub contacts: Vec<Contact>,
+    pub maximum: f64,
+    pub magnitude: f64
+}
+impl Event {
+    pub fn contact(&self, name: &str) -> Option<f64> {
+        self.contacts.iter().find(|a| a.name == name).map(|a| a.epoch)
+    }
+    pub fn describe(&self, system: &System) -> String {
+        let name = |a: usize| system.object_from_index(a).unwrap().read().unwrap().name().to_string();
+        let reached = |a: &str| self.contact(a).is_some();
+        match self.kind {
+            EventKind::Eclipse => format!("{} eclipse of {} by {}",
+                if reached("U2") || reached("U3") { "Total" } else if reached("U1") || reached("U4") { "Partial" } else { "Penumbral" },
+                name(self.bodies[2]), name(self.bodies[1])),
+            EventKind::Transit => format!("{} of {} across {} from {}",
+                if reached("C2") || reached("C3") { "Transit" } else { "Grazing transit" },
+                name(self.bodies[1]), name(self.bodies[2]), name(self.bodies[0])),
+            EventKind::Occultation => format!("{} of {} by {} from {}",
+                if reached("C2") || reached("C3") { "Total occultation" } else { "Partial occultation" },
+                name(self.bodies[2]), name(self.bodies[1]), name(self.bodies[0]))
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+struct Geometry {
+    separation: f64,
+    threshold: Vec<f64>,
+    magnitude: f64,
+    front: usize,
+    delay: f64
+}
+impl Geometry {
+    fn values(&self) -> Vec<f64> {
+        self.threshold.iter().map(|a| self.separation - a).collect()
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+struct Tracker {
+    watch: Watch,
+    previous: Vec<f64>,
+    recent: VecDeque<(f64, f64)>,
+    open: Option<Event>
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventPredictor {
+    pub light_time: bool,
+    pub subdivisions: usize,
+    pub events: Vec<Event>,
+    radius: Vec<f64>,
+    observer: Option<usize>,
+    history: History,
+    checked: f64,
+    tracker: Vec<Tracker>
+}
+impl EventPredictor {
+    pub fn new(system: &System, watch: &[Watch], observer: Option<usize>, light_time: bool) -> Result<EventPredictor, String> {
+        for a in watch.iter() {
+            let bodies = a.bodies();
+            if let Some(x) = bodies.iter().chain(observer.iter()).find(|b| **b >= system.size()) {
+                return Err(format!("Failed to find body {} in system!", x))
+            }
+            if bodies[0] == bodies[1] || bodies[0] == bodies[2] || bodies[1] == bodies[2] {
+                return Err(format!("Failed to watch for events! Bodies {:?} must differ", bodies))
+            }
+        }
+        let radius: Vec<f64> = (0..system.size()).map(|a| system.object_from_index(a).unwrap().read().unwrap().radius()).collect();
+        if let Some(x) = watch.iter().flat_map(|a| a.bodies().to_vec()).find(|a| radius[*a] <= 0.0) {
+            return Err(format!("Failed to watch for events! {} has no radius",
+                system.object_from_index(x).unwrap().read().unwrap().name()))
+        }
+        let mut predictor = EventPredictor {
+            light_time,
+            subdivisions: 8,
+            events: Vec::new(),
+            radius,
+            observer,
+            history: {
+                let mut history = History::new();
+                history.push(State::capture(system));
+                history
+            },
+            checked: system.epoch(),
+            tracker: Vec::new()
+        };
+        for a in watch.iter() {
+            let geometry = predictor.geometry(a, system.epoch());
+            let previous = geometry.values();
+            let open = if previous[0] < 0.0 {
+                Some(Event {
+                    kind: a.kind(),
+                    bodies: a.bodies(),
+                    contacts: Vec::new(),
+                    maximum: system.epoch() + geometry.delay,
+                    magnitude: geometry.magnitude
+                })
+            } else {
+                None
+            };
+            predictor.tracker.push(Tracker {
+                watch: *a,
+                previous,
+                recent: VecDeque::from(vec![(system.epoch(), geometry.separation)]),
+                open
+            });
+        }
+        Ok(predictor)
+    }
+    fn position(&self, body: usize, epoch: f64) -> [f64; 3] {
+        self.history.position(body, epoch)
+    }
+    fn apparent(&self, body: usize, viewpoint: &[f64; 3], epoch: f64) -> [f64; 3] {
+        if self.light_time {
+            self.history.light_time_state(body, viewpoint, epoch).0
+        } else {
+            self.position(body, epoch)
+        }
+    }
+    fn geometry(&self, watch: &Watch, epoch: f64) -> Geometry {
+        match *watch {
+            Watch::Eclipse { source, occulter, target } => {
+                let target_position = self.position(target, epoch);
+                let source_position = vector::scale(&self.apparent(source, &target_position, epoch), AU_TO_KM);
+                let occulter_position = vector::scale(&self.apparent(occulter, &target_position, epoch), AU_TO_KM);
+                let delay = match self.observer {
+                    Some(x) if self.light_time && x != target =>
+                        vector::distance(&target_position, &self.position(x, epoch)) / AU_PER_LIGHT_DAY,
+                    _ => 0.0
+                };
+                let target_position = vector::scale(&target_position, AU_TO_KM);
+                let axis = vector::subtract(&occulter_position, &source_position);
+                let distance = vector::norm(&axis);
+                let axis = vector::scale(&axis, 1.0 / distance);
+                let offset = vector::subtract(&target_position, &occulter_position);
+                let along = vector::dot(&offset, &axis);
+                let front = occulter;
+                let (source, occulter, target) = (self.radius[source], self.radius[occulter], self.radius[target]);
+                let penumbra = occulter + along * (source + occulter) / distance;
+                let umbra = occulter - along * (source - occulter) / distance;
+                let separation = if along > 0.0 {
+                    vector::norm(&vector::subtract(&offset, &vector::scale(&axis, along)))
+                } else {
+                    f64::INFINITY
+                };
+                Geometry {
+                    separation,
+                    threshold: vec![penumbra + target, umbra + target, umbra - target],
+                    magnitude: (umbra + target - separation) / (2.0 * target),
+                    front,
+                    delay
+                }
+            },
+            Watch::Alignment { observer, first, second } => {
+                let observer_position = self.position(observer, epoch);
+                let first_position = vector::subtract(&self.apparent(first, &observer_position, epoch), &observer_position);
+                let second_position = vector::subtract(&self.apparent(second, &observer_position, epoch), &observer_position);
+                let angular_radius = |a: &[f64; 3], b: usize| (self.radius[b] / (vector::norm(a) * AU_TO_KM)).min(1.0).asin();
+                let (first_radius, second_radius) = (angular_radius(&first_position, first), angular_radius(&second_position, second));
+                let separation = vector::norm(&vector::cross(&first_position, &second_position))
+                    .atan2(vector::dot(&first_position, &second_position));
+                let (front, back_radius) = if vector::norm(&first_position) < vector::norm(&second_position) {
+                    (first, second_radius)
+                } else {
+                    (second, first_radius)
+                };
+                Geometry {
+                    separation,
+                    threshold: vec![first_radius + second_radius, (first_radius - second_radius).abs()],
+                    magnitude: (first_radius + second_radius - separation) / (2.0 * back_radius),
+                    front,
+                    delay: 0.0
+                }
+            }
+        }
+    }
+    fn refine_contact(&self, watch: &Watch, contact: usize, mut before: f64, mut after: f64) -> f64 {
+        let inside = self.geometry(watch, after).values()[contact] < 0.0;
+        while after - before > 1.0E-8 {
+            let middle = (before + after) / 2.0;
+            if (self.geometry(watch, middle).values()[contact] < 0.0) == inside {
+                after = middle
+            } else {
+                before = middle
+            }
+        }
+        (before + after) / 2.0
+    }
+    fn refine_maximum(&self, watch: &Watch, mut left: f64, mut right: f64) -> (f64, Geometry) {
+        let ratio = (5.0f64.sqrt() - 1.0) / 2.0;
+        while right - left > 1.0E-8 {
+            let a = right - ratio * (right - left);
+            let b = left + ratio * (right - left);
+            if self.geometry(watch, a).separation < self.geometry(watch, b).separation {
+                right = b
+            } else {
+                left = a
+            }
+        }
+        let epoch = (left + right) / 2.0;
+        (epoch, self.geometry(watch, epoch))
+    }
+    fn close(&mut self, mut event: Event) {
+        if event.kind != EventKind::Eclipse {
+            let watch = Watch::Alignment {
+                observer: event.bodies[0],
+                first: event.bodies[1],
+                second: event.bodies[2]
+            };
+            let front = self.geometry(&watch, event.maximum).front;
+            if front != event.bodies[1] {
+                event.bodies.swap(1, 2);
+            }
+            let (front, back) = (event.bodies[1], event.bodies[2]);
+            let size = |a: usize| self.radius[a] / vector::distance(&self.position(a, event.maximum),
+                &self.position(event.bodies[0], event.maximum));
+            event.kind = if size(front) < size(back) { EventKind::Transit } else { EventKind::Occultation };
+        }
+        self.events.push(event)
+    }
+    fn advance_tracker(&mut self, index: usize, epoch: f64) {
+        let watch = self.tracker[index].watch;
+        let geometry = self.geometry(&watch, epoch);
+        let values = geometry.values();
+        let previous_epoch = self.tracker[index].recent.back().unwrap().0;
+        let mut crossing = Vec::new();
+        for (i, (before, after)) in self.tracker[index].previous.iter().zip(values.iter()).enumerate() {
+            if (*before < 0.0) != (*after < 0.0) {
+                crossing.push((self.refine_contact(&watch, i, previous_epoch, epoch), i, *after < 0.0));
+            }
+        }
+        crossing.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let open = self.tracker[index].open.is_some();
+        let recent = &mut self.tracker[index].recent;
+        recent.push_back((epoch, geometry.separation));
+        if recent.len() > 3 {
+            recent.pop_front();
+        }
+        if open && recent.len() == 3 && recent[1].1 < recent[0].1 && recent[1].1 <= recent[2].1 {
+            let (left, right) = (recent[0].0, recent[2].0);
+            let (maximum, geometry) = self.refine_maximum(&watch, left, right);
+            let event = self.tracker[index].open.as_mut().unwrap();
+            event.maximum = maximum + geometry.delay;
+            event.magnitude = geometry.magnitude;
+        }
+        for (contact_epoch, i, entering) in crossing {
+            let delay = self.geometry(&watch, contact_epoch).delay;
+            let contact = Contact {
+                name: if entering { watch.contacts()[i].0 } else { watch.contacts()[i].1 },
+                epoch: contact_epoch + delay
+            };
+            if i == 0 && entering {
+                let geometry = self.geometry(&watch, contact_epoch);
+                self.tracker[index].open = Some(Event {
+                    kind: watch.kind(),
+                    bodies: watch.bodies(),
+                    contacts: vec![contact],
+                    maximum: contact.epoch,
+                    magnitude: geometry.magnitude
+                });
+            } else if let Some(x) = self.tracker[index].open.as_mut() {
+                x.contacts.push(contact);
+                if i == 0 {
+                    let event = self.tracker[index].open.take().unwrap();
+                    self.close(event);
+                }
+            }
+        }
+        self.tracker[index].previous = values;
+    }
+    pub fn record(&mut self, system: &System) {
+        self.history.push(State::capture(system));
+        let start = self.checked;
+        let span = (system.epoch() - start) / self.subdivisions.max(1) as f64;
+        for step in 1..=self.subdivisions.max(1) {
+            let epoch = start + span * step as f64;
+            (0..self.tracker.len()).for_each(|a| self.advance_tracker(a, epoch));
+        }
+        self.checked = system.epoch();
+        let last = self.history.latest().unwrap();
+        let reach = self.tracker.iter()
+            .flat_map(|a| {
+                let bodies = a.watch.bodies();
+                vec![(bodies[0], bodies[1]), (bodies[0], bodies[2]), (bodies[1], bodies[2])]
+            })
+            .map(|(a, b)| vector::distance(&last.position[a], &last.position[b]) / AU_PER_LIGHT_DAY)
+            .fold(0.0, f64::max);
+        self.history.forget_before(self.checked - 2.0 * reach - span * 2.0);
+    }
+    pub fn finish(mut self) -> Vec<Event> {
+        for i in 0..self.tracker.len() {
+            if let Some(x) = self.tracker[i].open.take() {
+                self.close(x);
+            }
+        }
+        self.events.sort_by(|a, b| a.maximum.partial_cmp(&b.maximum).unwrap());
+        self.events
+    }
+}
+pub fn predict(system: &mut System, watch: &[Watch], observer: Option<usize>, duration: f64, light_time: bool,
+    subdivisions: usize) -> Result<Vec<Event>, String> {
+    let mut predictor = EventPredictor::new(system, watch, observer, light_time)?;
+    predictor.subdivisions = subdivisions;
+    system.run_for(duration, |system, _, _| {
+        predictor.record(system);
+        Ok(())
+    })?;
+    Ok(predictor.finish())
+}
+pub fn save_csv(events: &[Event], system: &System, file_path: &str) -> Result<(), String> {
+    let mut contents = "event,description,contact,epoch,magnitude\n".to_string();
+    for (i, a) in events.iter().enumerate() {
+        let description = csv_field(&a.describe(system));
+        contents.push_str(&format!("{},{},max,{},{}\n", i, description, a.maximum, a.magnitude));
+        for b in a.contacts.iter() {
+            contents.push_str(&format!("{},{},{},{},\n", i, description, csv_field(&b.name), b.epoch));
+        }
+    }
+    write_text(file_path, &contents)
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::body::Body;
+    use float_eq::assert_float_eq;
+    fn solve<F: Fn(f64) -> f64>(function: F, mut before: f64, mut after: f64) -> f64 {
+        let inside = function(after) < 0.0;
+        while after - before > 1.0E-12 {
+            let middle = (before + after) / 2.0;
+            if (function(middle) < 0.0) == inside { after = middle } else { before = middle }
+        }
+        (before + after) / 2.0
+    }
+    #[test]
+    fn predict_transits_and_occultations() -> Result<(), String> {
+        let mut contact = [0.0; 2];
+        for light_time in [false, true].iter() {
+            let mut system = System::new();
+            system.add(Body::new("Observer".to_string(), 0.0, 1.0, [0.0; 3], [0.0; 3]));
+            system.add(Body::new("Star".to_string(), 0.0, 695700.0, [1.0, 0.0, 0.0], [0.0; 3]));
+            system.add(Body::new("Planet".to_string(), 0.0, 6051.8, [0.5, -0.01, 0.0], [0.0, 0.01, 0.0]));
+            system.add(Body::new("Background".to_string(), 0.0, 100.0, [2.0, 0.0, 0.0], [0.0; 3]));
+            system.set_delta_time(0.05);
+            let watch = [
+                Watch::Alignment { observer: 0, first: 1, second: 2 },
+                Watch::Alignment { observer: 0, first: 3, second: 2 }
+            ];
+            let events = predict(&mut system, &watch, None, 2.0, *light_time, 8)?;
+            assert_eq!(events.len(), 2);
+            let transit = events.iter().find(|a| a.kind == EventKind::Transit).unwrap();
+            let occultation = events.iter().find(|a| a.kind == EventKind::Occultation).unwrap();
+            assert_eq!(transit.bodies, [0, 2, 1]);
+            assert_eq!(occultation.bodies, [0, 2, 3]);
+            assert_eq!(transit.contacts.iter().map(|a| a.name).collect::<Vec<_>>(), vec!["C1", "C2", "C3", "C4"]);
+            assert_eq!(transit.describe(&system), "Transit of Planet across Star from Observer");
+            assert_eq!(occultation.describe(&system), "Total occultation of Background by Planet from Observer");
+            let star = (695700.0 / AU_TO_KM).asin();
+            let first = solve(|t| {
+                let y = -0.01 + 0.01 * t;
+                (y.abs() / 0.5).atan() - star - (6051.8 / (AU_TO_KM * (0.25 + y * y).sqrt())).asin()
+            }, 0.0, 1.0);
+            contact[*light_time as usize] = transit.contact("C1").unwrap();
+            if !light_time {
+                assert_float_eq!(transit.contact("C1").unwrap(), first, abs <= 1.0E-6);
+                assert_float_eq!(transit.maximum, 1.0, abs <= 1.0E-6);
+                assert_float_eq!(transit.contact("C4").unwrap(), 2.0 - first, abs <= 1.0E-6);
+            }
+        }
+        let delay = 0.5 / AU_PER_LIGHT_DAY;
+        assert_float_eq!(contact[1] - contact[0], delay, abs <= 1.0E-6);
+        Ok(())
+    }
+    #[test]
+    fn predict_eclipse_contacts() -> Result<(), String> {
+        let mut system = System::new();
+        system.add(Body::new("Star".to_string(), 0.0, 695700.0, [0.0; 3], [0.0; 3]));
+        system.add(Body::new("Planet".to_string(), 0.0, 70000.0, [1.0, 0.0, 0.0], [0.0; 3]));
+        system.add(Body::new("Moon".to_string(), 0.0, 1800.0, [1.01, -0.001, 0.0], [0.0, 0.001, 0.0]));
+        system.add(Body::new("Far".to_string(), 0.0, 1800.0, [1.01, 0.01, 0.0], [0.0; 3]));
+        system.set_delta_time(0.05);
+        let watch = [
+            Watch::Eclipse { source: 0, occulter: 1, target: 2 },
+            Watch::Eclipse { source: 0, occulter: 1, target: 3 }
+        ];
+        let events = predict(&mut system, &watch, None, 2.0, true, 8)?;
+        assert_eq!(events.len(), 1);
+        let eclipse = &events[0];
+        assert_eq!(eclipse.describe(&system), "Total eclipse of Moon by Planet");
+        let along = 0.01 * AU_TO_KM;
+        let distance = AU_TO_KM;
+        let penumbra = 70000.0 + along * (695700.0 + 70000.0) / distance;
+        let umbra = 70000.0 - along * (695700.0 - 70000.0) / distance;
+        let crossing = |a: f64| 1.0 - a / (0.001 * AU_TO_KM);
+        for (name, radius) in [("P1", penumbra + 1800.0), ("U1", umbra + 1800.0), ("U2", umbra - 1800.0)].iter() {
+            assert_float_eq!(eclipse.contact(name).unwrap(), crossing(*radius), abs <= 1.0E-6);
+        }
+        assert_float_eq!(eclipse.contact("P4").unwrap(), 2.0 - crossing(penumbra + 1800.0), abs <= 1.0E-6);
+        assert_float_eq!(eclipse.maximum, 1.0, abs <= 1.0E-6);
+        assert_float_eq!(eclipse.magnitude, (umbra + 1800.0) / 3600.0, abs <= 1.0E-6);
+        assert!(EventPredictor::new(&system, &[Watch::Eclipse { source: 0, occulter: 0, target: 2 }], None, true).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use std::f64::consts::PI;
+use crate::{
+    system::{
+        events::{
+            History,
+            State
+        },
+        reference_frame::ReferenceFrame,
+        text::write_text,
+        vector,
+        System
+    },
+    timescale::{
+        Epoch,
+        TimeScale
+    },
+    units::{
+        AU_PER_DAY_TO_KM_PER_S,
+        AU_PER_LIGHT_DAY
+    }
+};
+const SEPARATOR: &str = "*******************************************************************************";
+fn angle_between(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    vector::norm(&vector::cross(a, b)).atan2(vector::dot(a, b))
+}
+fn sexagesimal(value: f64, precision: u32) -> (bool, i64, i64, f64) {
+    let scale = 10i64.pow(precision);
+    let total = (value.abs() * 3600.0 * scale as f64).round() as i64;
+    let fraction = total % (60 * scale);
+    (value < 0.0, total / (3600 * scale), total / (60 * scale) % 60, fraction as f64 / scale as f64)
+}
+pub fn format_right_ascension(right_ascension: f64) -> String {
+    let (_, hours, minutes, seconds) = sexagesimal(right_ascension.rem_euclid(2.0 * PI) * 12.0 / PI, 2);
+    format!("{:02} {:02} {:05.2}", hours % 24, minutes, seconds)
+}
+pub fn format_declination(declination: f64) -> String {
+    let (negative, degrees, minutes, seconds) = sexagesimal(declination.to_degrees(), 1);
+    format!("{}{:02} {:02} {:04.1}", if negative { '-' } else { '+' }, degrees, minutes, seconds)
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObserverRow {
+    pub epoch: f64,
+    pub right_ascension: f64,
+    pub declination: f64,
+    pub range: f64,
+    pub range_rate: f64,
+    pub light_time: f64,
+    pub elongation: Option<f64>,
+    pub trailing: Option<bool>,
+    pub phase_angle: Option<f64>
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObserverEphemeris {
+    pub observer: String,
+    pub target: String,
+    pub sun: String,
+    pub aberration: bool,
+    pub row: Vec<ObserverRow>
+}
+fn observe(
+    history: &History,
+    frame: &ReferenceFrame,
+    observer: usize,
+    target: usize,
+    sun: usize,
+    epoch: f64,
+    aberration: bool
+) -> ObserverRow {
+    let rotate = |to: &ReferenceFrame, a: &[f64; 3]| frame.convert(to, epoch, a, &[0.0; 3]).0;
+    let (observer_position, observer_velocity) = history.state_of(observer, epoch);
+    let (target_position, target_velocity, light_time) = history.light_time_state(target, &observer_position, epoch);
+    let line_of_sight = vector::subtract(&target_position, &observer_position);
+    let range = vector::norm(&line_of_sight);
+    let range_rate = vector::dot(&line_of_sight, &vector::subtract(&target_velocity, &observer_velocity)) / range *
+        AU_PER_DAY_TO_KM_PER_S;
+    let direction = if aberration {
+        let unit = vector::scale(&line_of_sight, 1.0 / range);
+        let beta = vector::scale(&observer_velocity, 1.0 / AU_PER_LIGHT_DAY);
+        vector::subtract(&vector::add(&unit, &beta), &vector::scale(&unit, vector::dot(&unit, &beta)))
+    } else {
+        line_of_sight
+    };
+    let equatorial = rotate(&ReferenceFrame::Icrf, &direction);
+    let (elongation, trailing) = if sun != observer {
+        let sun_position = history.light_time_state(sun, &observer_position, epoch).0;
+        let sun_direction = vector::subtract(&sun_position, &observer_position);
+        let (target_direction, sun_direction) = (rotate(&ReferenceFrame::EclipticJ2000, &line_of_sight),
+            rotate(&ReferenceFrame::EclipticJ2000, &sun_direction));
+        let longitude = target_direction[1].atan2(target_direction[0]) - sun_direction[1].atan2(sun_direction[0]);
+        (Some(angle_between(&sun_direction, &target_direction)), Some(longitude.sin() > 0.0))
+    } else {
+        (None, None)
+    };
+    let phase_angle = if sun != target {
+        let sun_position = history.light_time_state(sun, &target_position, epoch - light_time).0;
+        Some(angle_between(&vector::subtract(&sun_position, &target_position), &vector::scale(&line_of_sight, -1.0)))
+    } else {
+        None
+    };
+    ObserverRow {
+        epoch,
+        right_ascension: equatorial[1].atan2(equatorial[0]).rem_euclid(2.0 * PI),
+        declination: (equatorial[2] / vector::norm(&equatorial)).asin(),
+        range,
+        range_rate,
+        light_time,
+        elongation,
+        trailing,
+        phase_angle
+    }
+}
+fn reach(state: &State, bodies: &[usize], delta_time: f64) -> f64 {
+    let distance = bodies.iter()
+        .flat_map(|a| bodies.iter().map(move |b| (*a, *b)))
+        .map(|(a, b)| vector::distance(&state.position[a], &state.position[b]))
+        .fold(0.0, f64::max);
+    2.0 * distance / AU_PER_LIGHT_DAY + 2.0 * delta_time.abs()
+}
+impl ObserverEphemeris {
+    pub fn generate(
+        system: &mut System,
+        observer: usize,
+        target: usize,
+        sun: usize,
+        duration: f64,
+        every: f64,
+        aberration: bool
+    ) -> Result<ObserverEphemeris, String> {
+        if let Some(x) = [observer, target, sun].iter().find(|a| **a >= system.size()) {
+            return Err(format!("Failed to find body {} in system!", x))
+        }
+        if observer == target {
+            return Err("Failed to generate ephemeris! Observer and target are the same body".to_string())
+        }
+        if every <= 0.0 || duration < 0.0 {
+            return Err("Failed to generate ephemeris! Duration must not be negative and the interval must be positive".to_string())
+        }
+        let name = |a: usize| system.object_from_index(a).unwrap().read().unwrap().name().to_string();
+        let mut ephemeris = ObserverEphemeris {
+            observer: name(observer),
+            target: name(target),
+            sun: name(sun),
+            aberration,
+            row: Vec::new()
+        };
+        let frame = system.frame()?;
+        let bodies = [observer, target, sun];
+        let current = State::capture(system);
+        let mut past = system.snapshot();
+        past.set_delta_time(-system.delta_time());
+        let mut earlier = Vec::new();
+        while past.epoch() > current.epoch - reach(&current, &bodies, system.delta_time()) {
+            past.step();
+            earlier.push(State::capture(&past));
+        }
+        let mut history = History::new();
+        earlier.into_iter().rev().for_each(|a| history.push(a));
+        history.push(current);
+        let start = system.epoch();
+        for i in 0..=(duration / every + 1.0E-9).floor() as usize {
+            let epoch = start + every * i as f64;
+            while system.epoch() < epoch {
+                system.step();
+                history.push(State::capture(system));
+            }
+            ephemeris.row.push(observe(&history, &frame, observer, target, sun, epoch, aberration));
+            let latest = history.latest().unwrap();
+            history.forget_before(epoch - reach(latest, &bodies, system.delta_time()));
+        }
+        Ok(ephemeris)
+    }
+    pub fn table(&self, scale: TimeScale) -> String {
+        let angle = |a: Option<f64>| match a {
+            Some(x) => format!("{:8.4}", x.to_degrees()),
+            None => format!("{:>8}", "n.a.")
+        };
+        let mut contents = format!("{}\nTarget body name: {}\nCenter body name: {}\nSun body name   : {}\n", SEPARATOR,
+            self.target, self.observer, self.sun);
+        contents.push_str(&format!("Output          : {} RA/DEC in ICRF, corrected for light time{}\n",
+            if self.aberration { "apparent" } else { "astrometric" },
+            if self.aberration { " and stellar aberration" } else { "" }));
+        contents.push_str(&format!("{}\n Date__({})__HR:MN:SS{}     R.A.___(ICRF)___DEC          delta          deldot     S-O-T /r     S-T-O\n{}\n$$SOE\n",
+            SEPARATOR, scale.name(), "_".repeat(3 - scale.name().len()), SEPARATOR));
+        for a in self.row.iter() {
+            contents.push_str(&format!(" {}     {} {}  {:16.13} {:11.7}  {} /{}  {}\n",
+                Epoch::from_julian_date(a.epoch, TimeScale::Tdb).to(scale).format("%Y-%b-%d", 0),
+                format_right_ascension(a.right_ascension), format_declination(a.declination),
+                a.range, a.range_rate, angle(a.elongation),
+                match a.trailing { Some(true) => "T", Some(false) => "L", None => " " },
+                angle(a.phase_angle)));
+        }
+        contents.push_str(&format!("$$EOE\n{}\n", SEPARATOR));
+        contents
+    }
+    pub fn save_csv(&self, file_path: &str) -> Result<(), String> {
+        let optional = |a: Option<f64>| a.map(|x| x.to_degrees().to_string()).unwrap_or_default();
+        let mut contents = "epoch,right_ascension,declination,range,range_rate,light_time,elongation,trailing,phase_angle\n".to_string();
+        for a in self.row.iter() {
+            contents.push_str(&format!("{},{},{},{},{},{},{},{},{}\n", a.epoch, a.right_ascension.to_degrees(),
+                a.declination.to_degrees(), a.range, a.range_rate, a.light_time * 1440.0, optional(a.elongation),
+                a.trailing.map(|x| x.to_string()).unwrap_or_default(), optional(a.phase_angle)));
+        }
+        write_text(file_path, &contents)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        system::{
+            body::Body,
+            reference_frame::OBLIQUITY_J2000
+        },
+        units::AU_TO_KM
+    };
+    use float_eq::assert_float_eq;
+    fn straight_lines() -> System {
+        let radial = 10.0 * 86400.0 / AU_TO_KM;
+        let mut system = System::new();
+        system.add(Body::new("Observer".to_string(), 0.0, 1.0, [0.0; 3], [0.0; 3]));
+        system.add(Body::new("Sun".to_string(), 0.0, 695700.0, [1.0, 0.0, 0.0], [0.0; 3]));
+        system.add(Body::new("Receding".to_string(), 0.0, 1.0, [0.0, 2.0, 0.0], [0.0, radial, 0.0]));
+        system.add(Body::new("Crossing".to_string(), 0.0, 1.0, [0.0, 1.0, 0.0], [0.01, 0.0, 0.0]));
+        system.set_delta_time(0.05);
+        system
+    }
+    #[test]
+    fn observe_straight_line_targets() -> Result<(), String> {
+        let radial = 10.0 * 86400.0 / AU_TO_KM;
+        let mut system = straight_lines();
+        let receding = ObserverEphemeris::generate(&mut system, 0, 2, 1, 2.0, 0.5, false)?;
+        assert_eq!(receding.row.len(), 5);
+        for a in receding.row.iter() {
+            let range = (2.0 + radial * a.epoch) / (1.0 + radial / AU_PER_LIGHT_DAY);
+            assert_float_eq!(a.range, range, abs <= 1.0E-12);
+            assert_float_eq!(a.light_time, range / AU_PER_LIGHT_DAY, abs <= 1.0E-12);
+            assert_float_eq!(a.range_rate, 10.0, abs <= 1.0E-9);
+            assert_float_eq!(a.right_ascension, PI / 2.0, abs <= 1.0E-12);
+            assert_float_eq!(a.declination, OBLIQUITY_J2000, abs <= 1.0E-12);
+            assert_float_eq!(a.elongation.unwrap(), PI / 2.0, abs <= 1.0E-12);
+            assert_eq!(a.trailing, Some(true));
+            assert_float_eq!(a.phase_angle.unwrap(), (1.0 / range).atan(), abs <= 1.0E-12);
+        }
+        let mut system = straight_lines();
+        let crossing = ObserverEphemeris::generate(&mut system, 0, 3, 1, 2.0, 0.5, false)?;
+        for a in crossing.row.iter() {
+            let x = 0.01 * (a.epoch - a.light_time);
+            assert_float_eq!(a.light_time, (1.0 + x * x).sqrt() / AU_PER_LIGHT_DAY, abs <= 1.0E-12);
+            let expected = ReferenceFrame::EclipticJ2000.convert(&ReferenceFrame::Icrf, a.epoch, &[x, 1.0, 0.0], &[0.0; 3]).0;
+            assert_float_eq!(a.right_ascension, expected[1].atan2(expected[0]), abs <= 1.0E-12);
+            assert_float_eq!(a.declination, (expected[2] / vector::norm(&expected)).asin(), abs <= 1.0E-12);
+        }
+        let table = receding.table(TimeScale::Tdb);
+        assert!(table.contains("$$SOE\n") && table.contains("$$EOE\n"));
+        assert!(table.contains("06 00 00.00 +23 26 21.4"));
+        assert!(table.contains(" /T "));
+        assert!(receding.table(TimeScale::Tt).contains(" Date__(TT)__HR:MN:SS_ "));
+        assert!(ObserverEphemeris::generate(&mut straight_lines(), 0, 0, 1, 1.0, 0.5, false).is_err());
+        Ok(())
+    }
+    #[test]
+    fn format_sexagesimal() {
+        assert_eq!(format_right_ascension(PI / 2.0), "06 00 00.00");
+        assert_eq!(format_right_ascension(2.0 * PI - 1.0E-9), "00 00 00.00");
+        assert_eq!(format_declination(-(10.5f64.to_radians())), "-10 30 00.0");
+        assert_eq!(format_declination(OBLIQUITY_J2000), "+23 26 21.4");
+    }
+}
@@ -0,0 +1,452 @@
+use crate::{
+    analysis::porkchop::{
+        self,
+        Leg,
+        Transfer
+    },
+    system::{
+        body::Body,
+        events::History,
+        text::{
+            csv_field,
+            write_text
+        },
+        vector,
+        System
+    },
+    units::{
+        AU_PER_DAY_TO_KM_PER_S,
+        AU_TO_KM,
+        DAY_TO_S,
+        GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+    }
+};
+const VELOCITY_STEP: f64 = 1.0E-7;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncounterKind {
+    Departure,
+    Flyby,
+    Arrival
+}
+impl EncounterKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EncounterKind::Departure => "departure",
+            EncounterKind::Flyby => "flyby",
+            EncounterKind::Arrival => "arrival"
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub central: usize,
+    pub encounter: Vec<(usize, f64)>,
+    pub departure_altitude: f64,
+    pub arrival_altitude: f64,
+    pub max_revolutions: u32,
+    pub prograde: bool
+}
+impl Plan {
+    pub fn new(central: usize, encounter: Vec<(usize, f64)>) -> Plan {
+        Plan {
+            central,
+            encounter,
+            departure_altitude: 200.0,
+            arrival_altitude: 500.0,
+            max_revolutions: 0,
+            prograde: true
+        }
+    }
+    fn check(&self, system: &System) -> Result<(), String> {
+        if self.encounter.len() < 2 {
+            return Err("Failed to design mission! It needs at least a departure and an arrival".to_string())
+        }
+        if let Some(x) = self.encounter.iter().map(|a| a.0).chain(Some(self.central)).find(|a| *a >= system.size()) {
+            return Err(format!("Failed to find body {} in system!", x))
+        }
+        if self.encounter.iter().any(|a| a.0 == self.central) {
+            return Err("Failed to design mission! The central body cannot be encountered".to_string())
+        }
+        if self.encounter.windows(2).any(|a| a[1].1 <= a[0].1) {
+            return Err("Failed to design mission! Encounters must be in order of their epochs".to_string())
+        }
+        if self.departure_altitude < 0.0 || self.arrival_altitude < 0.0 {
+            return Err("Failed to design mission! Altitudes must not be negative".to_string())
+        }
+        Ok(())
+    }
+}
+pub fn sphere_of_influence(distance: f64, gravitational_mass: f64, central_mass: f64) -> f64 {
+    distance * (gravitational_mass / central_mass).powf(0.4)
+}
+pub fn periapsis_burn(v_infinity: f64, gravitational_parameter: f64, radius: f64) -> f64 {
+    (v_infinity * v_infinity + 2.0 * gravitational_parameter / radius).sqrt() - (gravitational_parameter / radius).sqrt()
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Flyby {
+    pub turn_angle: f64,
+    pub periapsis_radius: f64,
+    pub delta_v: f64
+}
+pub fn flyby(incoming: &[f64; 3], outgoing: &[f64; 3], gravitational_parameter: f64) -> Flyby {
+    let (speed_in, speed_out) = (vector::norm(incoming), vector::norm(outgoing));
+    let turn_angle = vector::norm(&vector::cross(incoming, outgoing)).atan2(vector::dot(incoming, outgoing));
+    let speed = (speed_in + speed_out) / 2.0;
+    let periapsis_radius = gravitational_parameter / (speed * speed) * (1.0 / (turn_angle / 2.0).sin() - 1.0);
+    let delta_v = if periapsis_radius.is_finite() {
+        let escape = 2.0 * gravitational_parameter / periapsis_radius;
+        ((speed_out * speed_out + escape).sqrt() - (speed_in * speed_in + escape).sqrt()).abs()
+    } else {
+        (speed_out - speed_in).abs()
+    };
+    Flyby {
+        turn_angle,
+        periapsis_radius,
+        delta_v
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Encounter {
+    pub body: usize,
+    pub name: String,
+    pub epoch: f64,
+    pub kind: EncounterKind,
+    pub gravitational_parameter: f64,
+    pub radius: f64,
+    pub sphere_of_influence: f64,
+    pub v_infinity_in: Option<[f64; 3]>,
+    pub v_infinity_out: Option<[f64; 3]>,
+    pub periapsis_radius: f64,
+    pub turn_angle: f64,
+    pub delta_v: f64
+}
+impl Encounter {
+    pub fn altitude(&self) -> f64 {
+        self.periapsis_radius - self.radius
+    }
+    pub fn is_feasible(&self) -> bool {
+        self.periapsis_radius >= self.radius
+    }
+    fn burn(&self, v_infinity_in: Option<&[f64; 3]>, v_infinity_out: Option<&[f64; 3]>) -> f64 {
+        match (v_infinity_in, v_infinity_out) {
+            (Some(x), Some(y)) => flyby(x, y, self.gravitational_parameter).delta_v,
+            (Some(x), None) | (None, Some(x)) => periapsis_burn(vector::norm(x), self.gravitational_parameter, self.periapsis_radius),
+            (None, None) => 0.0
+        }
+    }
+    fn aim_point(&self) -> [f64; 3] {
+        let incoming = self.v_infinity_in.unwrap();
+        let (speed, unit) = (vector::norm(&incoming), vector::normalize(&incoming));
+        let side = match &self.v_infinity_out {
+            Some(x) => vector::subtract(&vector::scale(&unit, vector::dot(x, &unit)), x),
+            None => vector::cross(&[0.0, 0.0, 1.0], &unit)
+        };
+        let side = if vector::norm(&side) > 0.0 { vector::normalize(&side) } else { vector::normalize(&vector::cross(&unit, &[1.0, 0.0, 0.0])) };
+        let impact = self.periapsis_radius * (1.0 + 2.0 * self.gravitational_parameter / (self.periapsis_radius * speed * speed)).sqrt();
+        vector::add(&vector::scale(&unit, -self.sphere_of_influence), &vector::scale(&side, impact))
+    }
+}
+fn central_body(system: &System, plan: &Plan) -> Result<Body, String> {
+    let central = system.object_from_index(plan.central).unwrap().read().unwrap().clone();
+    if central.mass() <= 0.0 {
+        return Err(format!("Failed to design mission! Central body {} has no mass", central.name()))
+    }
+    Ok(central)
+}
+pub fn transfer(system: &System, history: &History, plan: &Plan, from: (usize, f64), to: (usize, f64)) -> Result<Transfer, String> {
+    let gravitational_parameter = central_body(system, plan)?.mass() * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+    let name = |a: usize| system.object_from_index(a).unwrap().read().unwrap().name().to_string();
+    let route = Leg {
+        max_revolutions: plan.max_revolutions,
+        prograde: plan.prograde,
+        ..Leg::new(from.0, to.0, plan.central)
+    };
+    let best = porkchop::transfers(history, &route, gravitational_parameter, from.1, to.1)
+        .map_err(|x| format!("Failed to find a transfer from {} to {}!\n{}", name(from.0), name(to.0), x))?
+        .into_iter()
+        .min_by(|x, y| (x.departure_v_infinity + x.arrival_v_infinity)
+            .partial_cmp(&(y.departure_v_infinity + y.arrival_v_infinity)).unwrap());
+    match best {
+        Some(x) => Ok(x),
+        None => Err(format!("Failed to find a transfer from {} to {}!", name(from.0), name(to.0)))
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissionDesign {
+    pub central: String,
+    pub encounter: Vec<Encounter>,
+    pub leg: Vec<Transfer>
+}
+impl MissionDesign {
+    pub fn generate(system: &System, plan: &Plan) -> Result<MissionDesign, String> {
+        plan.check(system)?;
+        let history = History::propagate(system, plan.encounter[0].1, plan.encounter[plan.encounter.len() - 1].1);
+        MissionDesign::from_history(system, &history, plan)
+    }
+    pub fn from_history(system: &System, history: &History, plan: &Plan) -> Result<MissionDesign, String> {
+        plan.check(system)?;
+        let leg = plan.encounter.windows(2)
+            .map(|a| transfer(system, history, plan, a[0], a[1]))
+            .collect::<Result<Vec<_>, String>>()?;
+        MissionDesign::from_transfers(system, history, plan, leg)
+    }
+    pub fn from_transfers(system: &System, history: &History, plan: &Plan, leg: Vec<Transfer>) -> Result<MissionDesign, String> {
+        plan.check(system)?;
+        if leg.len() + 1 != plan.encounter.len() {
+            return Err(format!("Failed to design mission! {} encounters need {} transfers, not {}", plan.encounter.len(),
+                plan.encounter.len() - 1, leg.len()))
+        }
+        let body = |a: usize| system.object_from_index(a).unwrap().read().unwrap().clone();
+        let central = central_body(system, plan)?;
+        let last = plan.encounter.len() - 1;
+        let mut encounter = Vec::new();
+        for (i, (index, epoch)) in plan.encounter.iter().enumerate() {
+            let object = body(*index);
+            let (position, velocity) = history.state_of(*index, *epoch);
+            let (central_position, central_velocity) = history.state_of(plan.central, *epoch);
+            let velocity = vector::subtract(&velocity, &central_velocity);
+            let v_infinity = |a: &[f64; 3]| vector::scale(&vector::subtract(a, &velocity), AU_PER_DAY_TO_KM_PER_S);
+            let v_infinity_in = if i > 0 { Some(v_infinity(&leg[i - 1].arrival_velocity)) } else { None };
+            let v_infinity_out = if i < last { Some(v_infinity(&leg[i].departure_velocity)) } else { None };
+            let (kind, periapsis_radius, turn_angle) = match (&v_infinity_in, &v_infinity_out) {
+                (Some(x), Some(y)) => {
+                    let pass = flyby(x, y, object.mass());
+                    (EncounterKind::Flyby, pass.periapsis_radius, pass.turn_angle)
+                },
+                (None, _) => (EncounterKind::Departure, object.radius() + plan.departure_altitude, 0.0),
+                (_, None) => (EncounterKind::Arrival, object.radius() + plan.arrival_altitude, 0.0)
+            };
+            let distance = vector::distance(&position, &central_position) * AU_TO_KM;
+            let mut encounter_at = Encounter {
+                body: *index,
+                name: object.name().to_string(),
+                epoch: *epoch,
+                kind,
+                gravitational_parameter: object.mass(),
+                radius: object.radius(),
+                sphere_of_influence: sphere_of_influence(distance, object.mass(), central.mass()),
+                v_infinity_in,
+                v_infinity_out,
+                periapsis_radius,
+                turn_angle,
+                delta_v: 0.0
+            };
+            encounter_at.delta_v = encounter_at.burn(v_infinity_in.as_ref(), v_infinity_out.as_ref());
+            encounter.push(encounter_at);
+        }
+        Ok(MissionDesign {
+            central: central.name().to_string(),
+            encounter,
+            leg
+        })
+    }
+    pub fn total_delta_v(&self) -> f64 {
+        self.encounter.iter().map(|a| a.delta_v).sum()
+    }
+    pub fn is_feasible(&self) -> bool {
+        self.encounter.iter().all(|a| a.is_feasible())
+    }
+    pub fn time_of_flight(&self) -> f64 {
+        self.encounter[self.encounter.len() - 1].epoch - self.encounter[0].epoch
+    }
+    pub fn sequence(&self) -> String {
+        self.encounter.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(" - ")
+    }
+    pub fn save_csv(&self, refinement: Option<&Refinement>, file_path: &str) -> Result<(), String> {
+        let speed = |a: &Option<[f64; 3]>| a.map(|x| vector::norm(&x).to_string()).unwrap_or_default();
+        let mut contents = "body,kind,epoch,v_infinity_in,v_infinity_out,periapsis_altitude,turn_angle,delta_v,refined_delta_v\n".to_string();
+        for (i, a) in self.encounter.iter().enumerate() {
+            contents.push_str(&format!("{},{},{},{},{},{},{},{},{}\n", csv_field(&a.name), a.kind.name(), a.epoch, speed(&a.v_infinity_in),
+                speed(&a.v_infinity_out), a.altitude(), a.turn_angle.to_degrees(), a.delta_v,
+                refinement.map(|x| x.delta_v[i].to_string()).unwrap_or_default()));
+        }
+        write_text(file_path, &contents)
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefinedLeg {
+    pub from: String,
+    pub to: String,
+    pub start: f64,
+    pub stop: f64,
+    pub position: [f64; 3],
+    pub velocity: [f64; 3],
+    pub correction: f64,
+    pub v_infinity_out: [f64; 3],
+    pub v_infinity_in: [f64; 3],
+    pub miss: f64,
+    pub iterations: usize
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Refinement {
+    pub leg: Vec<RefinedLeg>,
+    pub delta_v: Vec<f64>
+}
+impl Refinement {
+    pub fn total_delta_v(&self) -> f64 {
+        self.delta_v.iter().sum()
+    }
+}
+fn propagate_to(system: &mut System, epoch: f64) {
+    let step = system.delta_time().abs();
+    while (epoch - system.epoch()).abs() > 1.0E-9 {
+        let remaining = epoch - system.epoch();
+        system.step_by(remaining.signum() * step.min(remaining.abs()));
+    }
+}
+fn state_of(system: &System, body: usize) -> ([f64; 3], [f64; 3]) {
+    let object = system.object_from_index(body).unwrap();
+    let object = object.read().unwrap();
+    (object.position(), object.velocity())
+}
+fn solve_linear(column: &[[f64; 3]; 3], right: &[f64; 3]) -> Option<[f64; 3]> {
+    let determinant = |a: &[f64; 3], b: &[f64; 3], c: &[f64; 3]| vector::dot(a, &vector::cross(b, c));
+    let full = determinant(&column[0], &column[1], &column[2]);
+    if full == 0.0 || !full.is_finite() {
+        return None
+    }
+    Some([
+        determinant(right, &column[1], &column[2]) / full,
+        determinant(&column[0], right, &column[2]) / full,
+        determinant(&column[0], &column[1], right) / full
+    ])
+}
+fn refine_leg(system: &System, from: &Encounter, to: &Encounter, tolerance: f64, max_iterations: usize) -> Result<RefinedLeg, String> {
+    let outgoing = from.v_infinity_out.unwrap();
+    let incoming = to.v_infinity_in.unwrap();
+    let start = from.epoch + from.sphere_of_influence / vector::norm(&outgoing) / DAY_TO_S;
+    let stop = to.epoch - to.sphere_of_influence / vector::norm(&incoming) / DAY_TO_S;
+    if stop <= start {
+        return Err(format!("Failed to refine the leg from {} to {}! It leaves one sphere of influence after entering the next",
+            from.name, to.name))
+    }
+    let mut base = system.snapshot();
+    propagate_to(&mut base, start);
+    let (body_position, body_velocity) = state_of(&base, from.body);
+    let position = vector::add(&body_position, &vector::scale(&vector::normalize(&outgoing), from.sphere_of_influence / AU_TO_KM));
+    let guess = vector::add(&body_velocity, &vector::scale(&outgoing, 1.0 / AU_PER_DAY_TO_KM_PER_S));
+    let aim = vector::scale(&to.aim_point(), 1.0 / AU_TO_KM);
+    let shoot = |velocity: &[f64; 3]| {
+        let mut trial = base.snapshot();
+        trial.add(Body::new("Probe".to_string(), 0.0, 0.0, position, *velocity));
+        propagate_to(&mut trial, stop);
+        let (probe_position, probe_velocity) = state_of(&trial, trial.size() - 1);
+        let (target_position, target_velocity) = state_of(&trial, to.body);
+        (
+            vector::subtract(&vector::subtract(&probe_position, &target_position), &aim),
+            vector::subtract(&probe_velocity, &target_velocity)
+        )
+    };
+    let mut velocity = guess;
+    for iterations in 0..=max_iterations {
+        let (miss, arrival) = shoot(&velocity);
+        if vector::norm(&miss) * AU_TO_KM <= tolerance {
+            return Ok(RefinedLeg {
+                from: from.name.clone(),
+                to: to.name.clone(),
+                start,
+                stop,
+                position,
+                velocity,
+                correction: vector::distance(&velocity, &guess) * AU_PER_DAY_TO_KM_PER_S,
+                v_infinity_out: vector::scale(&vector::subtract(&velocity, &body_velocity), AU_PER_DAY_TO_KM_PER_S),
+                v_infinity_in: vector::scale(&arrival, AU_PER_DAY_TO_KM_PER_S),
+                miss: vector::norm(&miss) * AU_TO_KM,
+                iterations
+            })
+        }
+        if iterations == max_iterations {
+            return Err(format!("Failed to refine the leg from {} to {} in {} iterations! It still misses by {:.3} km",
+                from.name, to.name, max_iterations, vector::norm(&miss) * AU_TO_KM))
+        }
+        let mut column = [[0.0; 3]; 3];
+        for (k, a) in column.iter_mut().enumerate() {
+            let mut trial = velocity;
+            trial[k] += VELOCITY_STEP;
+            *a = vector::scale(&vector::subtract(&shoot(&trial).0, &miss), 1.0 / VELOCITY_STEP);
+        }
+        match solve_linear(&column, &vector::scale(&miss, -1.0)) {
+            Some(x) => velocity = vector::add(&velocity, &x),
+            None => return Err(format!("Failed to refine the leg from {} to {}! The arrival does not depend on the departure velocity",
+                from.name, to.name))
+        }
+    }
+    unreachable!()
+}
+pub fn refine(system: &System, design: &MissionDesign, tolerance: f64, max_iterations: usize) -> Result<Refinement, String> {
+    if tolerance <= 0.0 {
+        return Err(format!("Failed to refine mission! Tolerance {} must be positive", tolerance))
+    }
+    let leg = design.encounter.windows(2)
+        .map(|a| refine_leg(system, &a[0], &a[1], tolerance, max_iterations))
+        .collect::<Result<Vec<_>, String>>()?;
+    let delta_v = design.encounter.iter().enumerate().map(|(i, a)| {
+        let incoming = if i > 0 { Some(&leg[i - 1].v_infinity_in) } else { None };
+        a.burn(incoming, leg.get(i).map(|x| &x.v_infinity_out))
+    }).collect();
+    Ok(Refinement {
+        leg,
+        delta_v
+    })
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use crate::system::fixture::{
+        circular_planets,
+        SUN
+    };
+    use float_eq::assert_float_eq;
+    fn planets() -> System {
+        let mut system = circular_planets(&[("Earth", 398600.4, 6371.0, 1.0, 0.0), ("Mars", 42828.4, 3389.5, 1.524, 0.8)]);
+        system.set_delta_time(0.25);
+        system
+    }
+    #[test]
+    fn bend_with_a_flyby() {
+        let incoming = [5.0, 0.0, 0.0];
+        let turn = 40.0f64.to_radians();
+        let outgoing = [5.0 * turn.cos(), 5.0 * turn.sin(), 0.0];
+        let pass = flyby(&incoming, &outgoing, 398600.4);
+        assert_float_eq!(pass.turn_angle, turn, abs <= 1.0E-12);
+        let eccentricity = 1.0 / (turn / 2.0).sin();
+        assert_float_eq!(pass.periapsis_radius, 398600.4 / 25.0 * (eccentricity - 1.0), r2nd <= 1.0E-12);
+        assert_float_eq!(pass.delta_v, 0.0, abs <= 1.0E-12);
+        assert!(flyby(&incoming, &[6.0, 0.1, 0.0], 398600.4).delta_v > 0.0);
+        assert_float_eq!(periapsis_burn(0.0, 398600.4, 6571.0), (2.0f64.sqrt() - 1.0) * (398600.4f64 / 6571.0).sqrt(),
+            r2nd <= 1.0E-12);
+        assert_float_eq!(sphere_of_influence(AU_TO_KM, 398600.4, SUN), 924600.0, abs <= 1000.0);
+    }
+    #[test]
+    fn design_and_refine_a_transfer() -> Result<(), String> {
+        let system = planets();
+        let mu = SUN * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+        let (inner, outer) = (mu.sqrt(), (mu / 1.524f64.powi(3)).sqrt());
+        let hohmann = PI * ((1.0 + 1.524) / 2.0f64).powf(1.5) / mu.sqrt();
+        let departure = (0.8 - (PI - outer * hohmann)) / (inner - outer);
+        let plan = Plan::new(0, vec!((1, departure), (2, departure + hohmann - 20.0)));
+        let design = MissionDesign::generate(&system, &plan)?;
+        assert_eq!(design.encounter[0].kind, EncounterKind::Departure);
+        assert_eq!(design.encounter[1].kind, EncounterKind::Arrival);
+        assert_float_eq!(design.encounter[0].delta_v, 3.6, abs <= 0.2);
+        assert_float_eq!(design.encounter[1].delta_v, 2.1, abs <= 0.3);
+        assert!(design.is_feasible());
+        let refinement = refine(&system, &design, 1.0, 8)?;
+        let leg = &refinement.leg[0];
+        assert!(leg.miss <= 1.0 && leg.iterations > 0);
+        assert!(leg.correction > 0.0 && leg.correction < 0.2, "correction {}", leg.correction);
+        assert_float_eq!(refinement.total_delta_v(), design.total_delta_v(), abs <= 0.3);
+        let mut check = system.snapshot();
+        propagate_to(&mut check, leg.start);
+        check.add(Body::new("Probe".to_string(), 0.0, 0.0, leg.position, leg.velocity));
+        propagate_to(&mut check, leg.stop);
+        let (probe, _) = state_of(&check, 3);
+        let (mars, _) = state_of(&check, 2);
+        let aim = vector::scale(&design.encounter[1].aim_point(), 1.0 / AU_TO_KM);
+        assert!(vector::distance(&vector::subtract(&probe, &mars), &aim) * AU_TO_KM <= 1.0);
+        assert!(MissionDesign::generate(&system, &Plan::new(0, vec!((1, 10.0), (2, 5.0)))).is_err());
+        assert!(MissionDesign::generate(&system, &Plan::new(1, vec!((1, 0.0), (2, 200.0)))).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+pub mod chaos;
+pub mod eclipse;
+pub mod ephemeris;
+pub mod mission;
+pub mod porkchop;
+pub mod resonance;
+pub mod sequence;
@@ -0,0 +1,366 @@
+use crate::{
+    system::{
+        events::History,
+        lambert::{
+            self,
+            Branch
+        },
+        text::write_text,
+        vector,
+        System
+    },
+    timescale::{
+        Epoch,
+        TimeScale
+    },
+    units::{
+        AU_PER_DAY_TO_KM_PER_S,
+        GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+    }
+};
+const WIDTH: f64 = 820.0;
+const HEIGHT: f64 = 640.0;
+const LEFT: f64 = 90.0;
+const RIGHT: f64 = 150.0;
+const TOP: f64 = 50.0;
+const BOTTOM: f64 = 70.0;
+const PALETTE: [&str; 8] = ["#3b4cc0", "#5977e3", "#7b9ff9", "#9ebeff", "#f7b89c", "#f49a7b", "#e7745b", "#b40426"];
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transfer {
+    pub departure: f64,
+    pub arrival: f64,
+    pub revolutions: u32,
+    pub branch: Branch,
+    pub departure_velocity: [f64; 3],
+    pub arrival_velocity: [f64; 3],
+    pub c3: f64,
+    pub departure_v_infinity: f64,
+    pub arrival_v_infinity: f64
+}
+impl Transfer {
+    pub fn time_of_flight(&self) -> f64 {
+        self.arrival - self.departure
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Leg {
+    pub from: usize,
+    pub to: usize,
+    pub central: usize,
+    pub max_revolutions: u32,
+    pub prograde: bool
+}
+impl Leg {
+    pub fn new(from: usize, to: usize, central: usize) -> Leg {
+        Leg {
+            from,
+            to,
+            central,
+            max_revolutions: 0,
+            prograde: true
+        }
+    }
+}
+pub fn transfers(
+    history: &History,
+    leg: &Leg,
+    gravitational_parameter: f64,
+    departure: f64,
+    arrival: f64
+) -> Result<Vec<Transfer>, String> {
+    let relative = |body: usize, epoch: f64| {
+        let (position, velocity) = history.state_of(body, epoch);
+        let (central_position, central_velocity) = history.state_of(leg.central, epoch);
+        (vector::subtract(&position, &central_position), vector::subtract(&velocity, &central_velocity))
+    };
+    let (departure_position, departure_velocity) = relative(leg.from, departure);
+    let (arrival_position, arrival_velocity) = relative(leg.to, arrival);
+    let solution = lambert::solve(&departure_position, &arrival_position, arrival - departure, gravitational_parameter,
+        leg.prograde, leg.max_revolutions)?;
+    Ok(solution.into_iter().map(|a| {
+        let departure_v_infinity = vector::norm(&vector::subtract(&a.departure_velocity, &departure_velocity)) *
+            AU_PER_DAY_TO_KM_PER_S;
+        Transfer {
+            departure,
+            arrival,
+            revolutions: a.revolutions,
+            branch: a.branch,
+            departure_velocity: a.departure_velocity,
+            arrival_velocity: a.arrival_velocity,
+            c3: departure_v_infinity * departure_v_infinity,
+            departure_v_infinity,
+            arrival_v_infinity: vector::norm(&vector::subtract(&a.arrival_velocity, &arrival_velocity)) * AU_PER_DAY_TO_KM_PER_S
+        }
+    }).collect())
+}
+pub fn sweep(start: f64, days: f64, every: f64) -> Result<Vec<f64>, String> {
+    if every <= 0.0 || days < 0.0 {
+        return Err(format!("Failed to sweep {} days every {} days! Days must not be negative and the interval must be positive",
+            days, every))
+    }
+    Ok((0..=(days / every + 1.0E-9).floor() as usize).map(|a| start + every * a as f64).collect())
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Porkchop {
+    pub from: String,
+    pub to: String,
+    pub central: String,
+    pub departure: Vec<f64>,
+    pub arrival: Vec<f64>,
+    pub transfer: Vec<Vec<Option<Transfer>>>
+}
+impl Porkchop {
+    pub fn generate(system: &System, leg: &Leg, departure: &[f64], arrival: &[f64]) -> Result<Porkchop, String> {
+        let (from, to, central) = (leg.from, leg.to, leg.central);
+        if let Some(x) = [from, to, central].iter().find(|a| **a >= system.size()) {
+            return Err(format!("Failed to find body {} in system!", x))
+        }
+        if from == to || from == central || to == central {
+            return Err("Failed to generate porkchop plot! Departure, arrival and central bodies must differ".to_string())
+        }
+        if departure.is_empty() || arrival.is_empty() {
+            return Err("Failed to generate porkchop plot! It needs at least one departure and one arrival epoch".to_string())
+        }
+        let name = |a: usize| system.object_from_index(a).unwrap().read().unwrap().name().to_string();
+        let gravitational_parameter = system.object_from_index(central).unwrap().read().unwrap().mass() *
+            GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+        if gravitational_parameter <= 0.0 {
+            return Err(format!("Failed to generate porkchop plot! Central body {} has no mass", name(central)))
+        }
+        let start = departure.iter().chain(arrival.iter()).cloned().fold(f64::MAX, f64::min);
+        let stop = departure.iter().chain(arrival.iter()).cloned().fold(f64::MIN, f64::max);
+        let history = History::propagate(system, start, stop);
+        let mut transfer = Vec::new();
+        for a in departure.iter() {
+            let mut row = Vec::new();
+            for b in arrival.iter() {
+                let best = if b > a {
+                    transfers(&history, leg, gravitational_parameter, *a, *b)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .min_by(|x, y| (x.departure_v_infinity + x.arrival_v_infinity)
+                            .partial_cmp(&(y.departure_v_infinity + y.arrival_v_infinity)).unwrap())
+                } else {
+                    None
+                };
+                row.push(best);
+            }
+            transfer.push(row);
+        }
+        Ok(Porkchop {
+            from: name(from),
+            to: name(to),
+            central: name(central),
+            departure: departure.to_vec(),
+            arrival: arrival.to_vec(),
+            transfer
+        })
+    }
+    pub fn best(&self) -> Option<&Transfer> {
+        self.transfer.iter()
+            .flat_map(|a| a.iter().flatten())
+            .min_by(|a, b| a.c3.partial_cmp(&b.c3).unwrap())
+    }
+    fn grid<F>(&self, value: F) -> Vec<Vec<f64>>
+    where
+        F: Fn(&Transfer) -> f64
+    {
+        self.transfer.iter().map(|a| a.iter().map(|b| b.as_ref().map(&value).unwrap_or(f64::NAN)).collect()).collect()
+    }
+    pub fn save_csv(&self, file_path: &str) -> Result<(), String> {
+        let mut contents = "departure,arrival,time_of_flight,c3,departure_v_infinity,arrival_v_infinity,revolutions,branch\n".to_string();
+        for (i, a) in self.departure.iter().enumerate() {
+            for (j, b) in self.arrival.iter().enumerate().filter(|(_, b)| *b > a) {
+                contents.push_str(&match &self.transfer[i][j] {
+                    Some(x) => format!("{},{},{},{},{},{},{},{}\n", a, b, b - a, x.c3, x.departure_v_infinity,
+                        x.arrival_v_infinity, x.revolutions, x.branch.name()),
+                    None => format!("{},{},{},,,,,\n", a, b, b - a)
+                });
+            }
+        }
+        write_text(file_path, &contents)
+    }
+    pub fn to_svg(&self) -> Result<String, String> {
+        if self.departure.len() < 2 || self.arrival.len() < 2 {
+            return Err("Failed to draw porkchop plot! It needs at least two departure and two arrival epochs".to_string())
+        }
+        let (width, height) = (WIDTH - LEFT - RIGHT, HEIGHT - TOP - BOTTOM);
+        let (first_departure, last_departure) = (self.departure[0], self.departure[self.departure.len() - 1]);
+        let (first_arrival, last_arrival) = (self.arrival[0], self.arrival[self.arrival.len() - 1]);
+        let x = |a: f64| LEFT + (a - first_departure) / (last_departure - first_departure) * width;
+        let y = |a: f64| TOP + height - (a - first_arrival) / (last_arrival - first_arrival) * height;
+        let point = |a: &[f64; 2]| (LEFT + a[0] / (self.departure.len() - 1) as f64 * width,
+            TOP + height - a[1] / (self.arrival.len() - 1) as f64 * height);
+        let path = |segment: &[([f64; 2], [f64; 2])]| segment.iter().map(|(a, b)| {
+            let (a, b) = (point(a), point(b));
+            format!("M{:.1} {:.1}L{:.1} {:.1}", a.0, a.1, b.0, b.1)
+        }).collect::<String>();
+        let date = |a: f64| Epoch::from_julian_date(a, TimeScale::Tdb).calendar().0.format("%F");
+        let mut contents = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"11\">\n",
+            WIDTH, HEIGHT);
+        contents.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", WIDTH, HEIGHT));
+        contents.push_str(&format!("<text x=\"{}\" y=\"24\" font-size=\"14\" text-anchor=\"middle\">{} to {} around {}</text>\n",
+            LEFT + width / 2.0, self.from, self.to, self.central));
+        contents.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"black\"/>\n",
+            LEFT, TOP, width, height));
+        for i in 0..=5 {
+            let (departure, arrival) = (first_departure + (last_departure - first_departure) * i as f64 / 5.0,
+                first_arrival + (last_arrival - first_arrival) * i as f64 / 5.0);
+            contents.push_str(&format!("<line x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\" stroke=\"black\"/><text x=\"{0:.1}\" y=\"{3}\" text-anchor=\"middle\">{4}</text>\n",
+                x(departure), TOP + height, TOP + height + 5.0, TOP + height + 18.0, date(departure)));
+            contents.push_str(&format!("<line x1=\"{0}\" y1=\"{1:.1}\" x2=\"{2}\" y2=\"{1:.1}\" stroke=\"black\"/><text x=\"{3}\" y=\"{1:.1}\" text-anchor=\"end\" dy=\"4\">{4}</text>\n",
+                LEFT - 5.0, y(arrival), LEFT, LEFT - 8.0, date(arrival)));
+        }
+        contents.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">Departure (TDB)</text>\n",
+            LEFT + width / 2.0, HEIGHT - 20.0));
+        contents.push_str(&format!("<text x=\"20\" y=\"{0}\" text-anchor=\"middle\" transform=\"rotate(-90 20 {0})\">Arrival (TDB)</text>\n",
+            TOP + height / 2.0));
+        let time_of_flight = self.grid(|a| a.time_of_flight());
+        for level in levels(&time_of_flight, 6, 1.0) {
+            let segment = contour(&time_of_flight, level);
+            if let Some((a, _)) = segment.first() {
+                let (label_x, label_y) = point(a);
+                contents.push_str(&format!("<path d=\"{}\" fill=\"none\" stroke=\"#999999\" stroke-dasharray=\"4 3\"/><text x=\"{:.1}\" y=\"{:.1}\" fill=\"#666666\">{} d</text>\n",
+                    path(&segment), label_x + 3.0, label_y - 3.0, level));
+            }
+        }
+        let v_infinity = self.grid(|a| a.arrival_v_infinity);
+        for level in levels(&v_infinity, 8, 0.25) {
+            contents.push_str(&format!("<path d=\"{}\" fill=\"none\" stroke=\"#555555\" stroke-width=\"0.6\"/>\n",
+                path(&contour(&v_infinity, level))));
+        }
+        let c3 = self.grid(|a| a.c3);
+        let c3_levels = levels(&c3, 8, 0.25);
+        for (i, level) in c3_levels.iter().enumerate() {
+            let colour = PALETTE[i * (PALETTE.len() - 1) / (c3_levels.len() - 1).max(1)];
+            contents.push_str(&format!("<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\"/>\n",
+                path(&contour(&c3, *level)), colour));
+            let legend = TOP + 20.0 + 18.0 * i as f64;
+            contents.push_str(&format!("<line x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" stroke=\"{3}\" stroke-width=\"3\"/><text x=\"{4}\" y=\"{1}\" dy=\"4\">{5}</text>\n",
+                WIDTH - RIGHT + 15.0, legend, WIDTH - RIGHT + 40.0, colour, WIDTH - RIGHT + 46.0, level));
+        }
+        contents.push_str(&format!("<text x=\"{}\" y=\"{}\">C3 (km²/s²)</text>\n", WIDTH - RIGHT + 15.0, TOP + 4.0));
+        let key = TOP + 40.0 + 18.0 * c3_levels.len() as f64;
+        contents.push_str(&format!("<text x=\"{0}\" y=\"{1}\">arrival v∞ (km/s)</text><line x1=\"{0}\" y1=\"{2}\" x2=\"{3}\" y2=\"{2}\" stroke=\"#555555\"/>\n",
+            WIDTH - RIGHT + 15.0, key, key + 10.0, WIDTH - RIGHT + 40.0));
+        contents.push_str(&format!("<text x=\"{0}\" y=\"{1}\">time of flight</text><line x1=\"{0}\" y1=\"{2}\" x2=\"{3}\" y2=\"{2}\" stroke=\"#999999\" stroke-dasharray=\"4 3\"/>\n",
+            WIDTH - RIGHT + 15.0, key + 30.0, key + 40.0, WIDTH - RIGHT + 40.0));
+        if let Some(best) = self.best() {
+            contents.push_str(&format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"4\" fill=\"black\"/>\n",
+                x(best.departure), y(best.arrival)));
+        }
+        contents.push_str("</svg>\n");
+        Ok(contents)
+    }
+    pub fn save_svg(&self, file_path: &str) -> Result<(), String> {
+        let contents = self.to_svg()?;
+        write_text(file_path, &contents)
+    }
+}
+fn nice_step(range: f64, count: usize) -> f64 {
+    let raw = range / count as f64;
+    let magnitude = 10.0f64.powf(raw.log10().floor());
+    magnitude * match raw / magnitude {
+        a if a <= 1.0 => 1.0,
+        a if a <= 2.0 => 2.0,
+        a if a <= 5.0 => 5.0,
+        _ => 10.0
+    }
+}
+pub fn levels(grid: &[Vec<f64>], count: usize, quantile: f64) -> Vec<f64> {
+    let mut finite = grid.iter().flatten().cloned().filter(|a| a.is_finite()).collect::<Vec<_>>();
+    finite.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let (low, high) = match (finite.first(), finite.get(((finite.len().max(1) - 1) as f64 * quantile) as usize)) {
+        (Some(x), Some(y)) if y > x => (*x, *y),
+        _ => return Vec::new()
+    };
+    let step = nice_step(high - low, count);
+    let first = (low / step).ceil() * step;
+    (0..).map(|a| first + step * a as f64).take_while(|a| *a <= high).collect()
+}
+pub fn contour(grid: &[Vec<f64>], level: f64) -> Vec<([f64; 2], [f64; 2])> {
+    let mut segment = Vec::new();
+    for i in 0..grid.len().saturating_sub(1) {
+        for j in 0..grid[i].len().saturating_sub(1) {
+            let corner = [[i, j], [i + 1, j], [i + 1, j + 1], [i, j + 1]];
+            let value = corner.map(|a| grid[a[0]][a[1]]);
+            if value.iter().any(|a| !a.is_finite()) {
+                continue
+            }
+            let crossing = (0..4).map(|k| {
+                let (a, b) = (k, (k + 1) % 4);
+                if (value[a] < level) == (value[b] < level) {
+                    return None
+                }
+                let t = (level - value[a]) / (value[b] - value[a]);
+                Some([
+                    corner[a][0] as f64 + t * (corner[b][0] as f64 - corner[a][0] as f64),
+                    corner[a][1] as f64 + t * (corner[b][1] as f64 - corner[a][1] as f64)
+                ])
+            }).collect::<Vec<_>>();
+            match crossing.iter().flatten().cloned().collect::<Vec<_>>()[..] {
+                [a, b] => segment.push((a, b)),
+                [a, b, c, d] => {
+                    if (value.iter().sum::<f64>() / 4.0 < level) == (value[0] < level) {
+                        segment.push((a, b));
+                        segment.push((c, d));
+                    } else {
+                        segment.push((d, a));
+                        segment.push((b, c));
+                    }
+                },
+                _ => ()
+            }
+        }
+    }
+    segment
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use crate::system::fixture::circular_planets;
+    use float_eq::assert_float_eq;
+    fn two_planets() -> System {
+        circular_planets(&[("Inner", 0.0, 0.0, 1.0, 0.0), ("Outer", 0.0, 0.0, 1.524, 0.8)])
+    }
+    #[test]
+    fn find_hohmann_transfer() -> Result<(), String> {
+        let system = two_planets();
+        let mu = 132712440041.93938 * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+        let (inner, outer) = (mu.sqrt(), (mu / 1.524f64.powi(3)).sqrt());
+        let hohmann = PI * ((1.0 + 1.524) / 2.0f64).powf(1.5) / mu.sqrt();
+        let departure = (0.8 - (PI - outer * hohmann)) / (inner - outer);
+        let porkchop = Porkchop::generate(&system, &Leg::new(1, 2, 0), &sweep(departure - 20.0, 40.0, 2.0)?,
+            &sweep(departure + hohmann - 40.0, 80.0, 4.0)?)?;
+        let best = porkchop.best().unwrap();
+        let v_inner = (mu / 1.0).sqrt() * AU_PER_DAY_TO_KM_PER_S;
+        let v_transfer = (mu * (2.0 - 2.0 / 2.524)).sqrt() * AU_PER_DAY_TO_KM_PER_S;
+        assert_float_eq!(best.departure_v_infinity, v_transfer - v_inner, abs <= 0.05);
+        assert_float_eq!(best.time_of_flight(), hohmann, abs <= 6.0);
+        assert!(porkchop.transfer[0][0].is_some());
+        let svg = porkchop.to_svg()?;
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains("Inner to Outer around Sun"));
+        assert!(svg.matches("stroke-width=\"1.5\"").count() > 2);
+        assert!(Porkchop::generate(&system, &Leg::new(1, 1, 0), &[0.0], &[10.0]).is_err());
+        Ok(())
+    }
+    #[test]
+    fn trace_contours() {
+        let grid = (0..5).map(|a| (0..5).map(|b| (a * a + b * b) as f64).collect()).collect::<Vec<Vec<f64>>>();
+        let segment = contour(&grid, 10.0);
+        assert!(!segment.is_empty());
+        for (a, b) in segment.iter() {
+            assert_float_eq!(a[0] * a[0] + a[1] * a[1], 10.0, abs <= 1.5);
+            assert_float_eq!(b[0] * b[0] + b[1] * b[1], 10.0, abs <= 1.5);
+        }
+        assert_eq!(levels(&grid, 4, 0.25), vec!(0.0, 2.0, 4.0));
+        assert_eq!(levels(&grid, 4, 1.0), vec!(0.0, 10.0, 20.0, 30.0));
+        let mut holes = grid.clone();
+        holes[2][2] = f64::NAN;
+        assert!(contour(&holes, 10.0).len() < segment.len());
+        assert!(sweep(0.0, 10.0, 0.0).is_err());
+        assert_eq!(sweep(2.0, 10.0, 5.0).unwrap(), vec!(2.0, 7.0, 12.0));
+    }
+}
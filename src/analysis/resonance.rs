@@ -0,0 +1,280 @@
+use std::f64::consts::PI;
+use crate::{
+    system::{
+        text::write_text,
+        vector,
+        System
+    },
+    units::GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+};
+pub fn primary_of(system: &System, body: usize) -> Option<usize> {
+    let object = system.object_from_index(body)?;
+    let (position, mass) = {
+        let object = object.read().unwrap();
+        (object.position(), object.mass())
+    };
+    (0..system.size())
+        .filter(|a| *a != body)
+        .filter_map(|a| {
+            let other = system.object_from_index(a).unwrap();
+            let other = other.read().unwrap();
+            let distance = vector::distance(&position, &other.position());
+            if other.mass() > mass && distance > 0.0 {
+                Some((a, other.mass() / distance.powi(2)))
+            } else {
+                None
+            }
+        })
+        .fold(None, |a: Option<(usize, f64)>, b| match a {
+            Some(x) if x.1 >= b.1 => Some(x),
+            _ => Some(b)
+        })
+        .map(|a| a.0)
+}
+pub fn parse_ratio(ratio: &str) -> Result<(u32, u32), String> {
+    let mut parts = ratio.split(':').map(|a| a.trim().parse::<u32>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(p)), Some(Ok(q)), None) if p >= q && q > 0 => Ok((p, q)),
+        _ => Err(format!("Failed to parse ratio {}! Expected p:q with p >= q > 0, such as 3:2", ratio))
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pericentre {
+    Inner,
+    Outer
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResonantAngle {
+    pub central: usize,
+    pub inner: usize,
+    pub outer: usize,
+    pub p: u32,
+    pub q: u32,
+    pub pericentre: Pericentre
+}
+impl ResonantAngle {
+    pub fn new(central: usize, inner: usize, outer: usize, p: u32, q: u32, pericentre: Pericentre) -> Result<ResonantAngle, String> {
+        if q == 0 || p < q {
+            return Err(format!("Failed to build resonant angle! Ratio {}:{} needs p >= q > 0", p, q))
+        }
+        if central == inner || central == outer || inner == outer {
+            return Err("Failed to build resonant angle! Central, inner and outer bodies must differ".to_string())
+        }
+        Ok(ResonantAngle {
+            central,
+            inner,
+            outer,
+            p,
+            q,
+            pericentre
+        })
+    }
+    pub fn angle(&self, system: &System) -> Result<f64, String> {
+        let inner = system.osculating_elements(self.inner, self.central)?;
+        let outer = system.osculating_elements(self.outer, self.central)?;
+        let pericentre = match self.pericentre {
+            Pericentre::Inner => inner.longitude_of_periapsis(),
+            Pericentre::Outer => outer.longitude_of_periapsis()
+        };
+        Ok((self.p as f64 * outer.mean_longitude() - self.q as f64 * inner.mean_longitude() -
+            (self.p - self.q) as f64 * pericentre).rem_euclid(2.0 * PI))
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Behaviour {
+    Librating {
+        centre: f64,
+        amplitude: f64
+    },
+    Circulating {
+        rate: f64
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResonanceTracker {
+    pub angle: ResonantAngle,
+    pub epoch: Vec<f64>,
+    pub value: Vec<f64>,
+    unwrapped: Vec<f64>
+}
+impl ResonanceTracker {
+    pub fn new(angle: ResonantAngle) -> ResonanceTracker {
+        ResonanceTracker {
+            angle,
+            epoch: Vec::new(),
+            value: Vec::new(),
+            unwrapped: Vec::new()
+        }
+    }
+    pub fn record(&mut self, system: &System) -> Result<(), String> {
+        let value = self.angle.angle(system)?;
+        let unwrapped = match self.unwrapped.last() {
+            Some(x) => x + ((value - x + PI).rem_euclid(2.0 * PI) - PI),
+            None => value
+        };
+        self.epoch.push(system.epoch());
+        self.value.push(value);
+        self.unwrapped.push(unwrapped);
+        Ok(())
+    }
+    pub fn classify(&self) -> Option<Behaviour> {
+        if self.unwrapped.len() < 2 {
+            return None
+        }
+        let minimum = self.unwrapped.iter().cloned().fold(f64::MAX, f64::min);
+        let maximum = self.unwrapped.iter().cloned().fold(f64::MIN, f64::max);
+        if maximum - minimum < 2.0 * PI {
+            Some(Behaviour::Librating {
+                centre: ((maximum + minimum) / 2.0).rem_euclid(2.0 * PI),
+                amplitude: (maximum - minimum) / 2.0
+            })
+        } else {
+            let duration = self.epoch[self.epoch.len() - 1] - self.epoch[0];
+            Some(Behaviour::Circulating {
+                rate: (self.unwrapped[self.unwrapped.len() - 1] - self.unwrapped[0]) / duration
+            })
+        }
+    }
+    pub fn save_csv(&self, file_path: &str) -> Result<(), String> {
+        let mut contents = "epoch,angle,unwrapped\n".to_string();
+        for i in 0..self.epoch.len() {
+            contents.push_str(&format!("{},{},{}\n", self.epoch[i], self.value[i], self.unwrapped[i]));
+        }
+        write_text(file_path, &contents)
+    }
+}
+pub fn track(system: &mut System, angle: ResonantAngle, duration: f64, cadence: usize) -> Result<ResonanceTracker, String> {
+    if cadence == 0 {
+        return Err("Failed to track resonant angle! Cadence must be at least one step".to_string())
+    }
+    let mut tracker = ResonanceTracker::new(angle);
+    tracker.record(system)?;
+    system.run_for(duration, |system, step, last| {
+        if step.is_multiple_of(cadence) || last {
+            tracker.record(system)?;
+        }
+        Ok(())
+    })?;
+    Ok(tracker)
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Commensurability {
+    pub inner: usize,
+    pub outer: usize,
+    pub p: u32,
+    pub q: u32,
+    pub period_ratio: f64,
+    pub offset: f64
+}
+pub fn scan(system: &System, central: usize, max_coefficient: u32, tolerance: f64) -> Result<Vec<Commensurability>, String> {
+    let mut period = Vec::new();
+    for body in (0..system.size()).filter(|a| *a != central) {
+        if primary_of(system, body) != Some(central) {
+            continue
+        }
+        let elements = system.osculating_elements(body, central)?;
+        if elements.eccentricity < 1.0 {
+            let central_mass = system.object_from_index(central).unwrap().read().unwrap().mass();
+            let body_mass = system.object_from_index(body).unwrap().read().unwrap().mass();
+            let gravitational_parameter = (central_mass + body_mass) * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+            period.push((body, elements.period(gravitational_parameter)));
+        }
+    }
+    period.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    let mut found = Vec::new();
+    for (i, inner) in period.iter().enumerate() {
+        for outer in period.iter().skip(i + 1) {
+            let period_ratio = outer.1 / inner.1;
+            let best = (1..=max_coefficient)
+                .flat_map(|q| (q..=max_coefficient).map(move |p| (p, q)))
+                .filter(|(p, q)| greatest_common_divisor(*p, *q) == 1)
+                .map(|(p, q)| (p, q, period_ratio / (p as f64 / q as f64) - 1.0))
+                .filter(|a| a.2.abs() <= tolerance)
+                .min_by(|a, b| (a.0 + a.1).cmp(&(b.0 + b.1)).then(a.2.abs().partial_cmp(&b.2.abs()).unwrap()));
+            if let Some((p, q, offset)) = best {
+                found.push(Commensurability {
+                    inner: inner.0,
+                    outer: outer.0,
+                    p,
+                    q,
+                    period_ratio,
+                    offset
+                });
+            }
+        }
+    }
+    Ok(found)
+}
+fn greatest_common_divisor(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { greatest_common_divisor(b, a % b) }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::{
+        body::Body,
+        integrator::Integrator,
+        orbital_elements::OrbitalElements
+    };
+    fn add_particle(system: &mut System, name: &str, semi_major_axis: f64, mean_anomaly: f64) {
+        let elements = OrbitalElements {
+            semi_major_axis,
+            eccentricity: 0.05,
+            inclination: 0.0,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 0.4,
+            mean_anomaly
+        };
+        let (position, velocity) = elements.to_state(1.0);
+        system.add(Body::new(name.to_string(), 0.0, 0.0, position, velocity));
+    }
+    fn resonant_system() -> System {
+        let mut system = System::new();
+        system.add(Body::new("Primary".to_string(), 1.0 / GRAVITATIONAL_MASS_TO_AU3_PER_DAY2, 0.0, [0.0; 3], [0.0; 3]));
+        add_particle(&mut system, "Inner", 1.0, 0.0);
+        add_particle(&mut system, "Outer", 2.0f64.powf(2.0 / 3.0), 1.0);
+        add_particle(&mut system, "Drifting", 2.1f64.powf(2.0 / 3.0), 2.0);
+        system
+    }
+    #[test]
+    fn classify_resonant_angles() -> Result<(), String> {
+        let mut system = resonant_system();
+        system.set_integrator(Integrator::Yoshida4);
+        system.set_delta_time(0.05);
+        let (p, q) = parse_ratio("2:1")?;
+        let resonant = ResonantAngle::new(0, 1, 2, p, q, Pericentre::Inner)?;
+        let drifting = ResonantAngle::new(0, 1, 3, p, q, Pericentre::Inner)?;
+        let mut first = ResonanceTracker::new(resonant);
+        let mut second = ResonanceTracker::new(drifting);
+        for _ in 0..200 {
+            first.record(&system)?;
+            second.record(&system)?;
+            (0..20).for_each(|_| system.step());
+        }
+        match first.classify() {
+            Some(Behaviour::Librating { amplitude, .. }) => assert!(amplitude < 0.01, "amplitude {}", amplitude),
+            x => return Err(format!("Expected libration, found {:?}", x))
+        }
+        match second.classify() {
+            Some(Behaviour::Circulating { .. }) => (),
+            x => return Err(format!("Expected circulation, found {:?}", x))
+        }
+        assert!(parse_ratio("1:2").is_err());
+        assert!(ResonantAngle::new(0, 1, 1, 2, 1, Pericentre::Outer).is_err());
+        Ok(())
+    }
+    #[test]
+    fn scan_period_ratios() -> Result<(), String> {
+        let system = resonant_system();
+        let found = scan(&system, 0, 4, 0.06)?;
+        assert_eq!(found.len(), 3);
+        assert_eq!((found[0].inner, found[0].outer, found[0].p, found[0].q), (1, 2, 2, 1));
+        assert!(found[0].offset.abs() < 1.0E-9);
+        assert_eq!((found[1].inner, found[1].outer, found[1].p, found[1].q), (1, 3, 2, 1));
+        assert_eq!((found[2].inner, found[2].outer, found[2].p, found[2].q), (2, 3, 1, 1));
+        assert_eq!(scan(&system, 0, 4, 0.01)?.len(), 1);
+        assert_eq!(primary_of(&system, 1), Some(0));
+        assert_eq!(primary_of(&system, 0), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use std::f64::consts::PI;
+use crate::{
+    analysis::{
+        mission::{
+            self,
+            MissionDesign,
+            Plan
+        },
+        porkchop::Transfer
+    },
+    system::{
+        events::History,
+        vector,
+        System
+    },
+    units::GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+};
+#[derive(Clone, Debug, PartialEq)]
+pub struct Search {
+    pub central: usize,
+    pub departure: usize,
+    pub arrival: usize,
+    pub flyby: Vec<usize>,
+    pub max_flybys: usize,
+    pub time_of_flight: Vec<f64>,
+    pub departure_altitude: f64,
+    pub arrival_altitude: f64,
+    pub min_flyby_altitude: f64,
+    pub max_launch_v_infinity: f64,
+    pub max_delta_v: f64,
+    pub max_days: f64,
+    pub beam: usize
+}
+#[derive(Clone, Debug)]
+struct Partial {
+    encounter: Vec<(usize, f64)>,
+    leg: Vec<Transfer>,
+    cost: f64
+}
+impl Search {
+    pub fn new(central: usize, departure: usize, arrival: usize, flyby: Vec<usize>) -> Search {
+        Search {
+            central,
+            departure,
+            arrival,
+            flyby,
+            max_flybys: 2,
+            time_of_flight: vec!(0.6, 0.8, 1.0, 1.2, 1.4),
+            departure_altitude: 200.0,
+            arrival_altitude: 500.0,
+            min_flyby_altitude: 300.0,
+            max_launch_v_infinity: f64::INFINITY,
+            max_delta_v: f64::INFINITY,
+            max_days: f64::INFINITY,
+            beam: 50
+        }
+    }
+    fn check(&self, system: &System) -> Result<(), String> {
+        let body = [self.central, self.departure, self.arrival];
+        if let Some(x) = body.iter().chain(self.flyby.iter()).find(|a| **a >= system.size()) {
+            return Err(format!("Failed to find body {} in system!", x))
+        }
+        if self.flyby.iter().chain(body[1..].iter()).any(|a| *a == self.central) {
+            return Err("Failed to search flyby sequences! The central body cannot be encountered".to_string())
+        }
+        if self.time_of_flight.is_empty() || self.time_of_flight.iter().any(|a| *a <= 0.0) {
+            return Err("Failed to search flyby sequences! Times of flight must be positive fractions of a transfer".to_string())
+        }
+        if self.beam == 0 {
+            return Err("Failed to search flyby sequences! The beam must keep at least one branch".to_string())
+        }
+        if self.min_flyby_altitude < 0.0 {
+            return Err("Failed to search flyby sequences! The lowest flyby altitude must not be negative".to_string())
+        }
+        Ok(())
+    }
+    fn plan(&self, encounter: Vec<(usize, f64)>) -> Plan {
+        Plan {
+            departure_altitude: self.departure_altitude,
+            arrival_altitude: self.arrival_altitude,
+            ..Plan::new(self.central, encounter)
+        }
+    }
+    pub fn reference_time(&self, system: &System, from: usize, to: usize) -> f64 {
+        let object = |a: usize| system.object_from_index(a).unwrap().read().unwrap().clone();
+        let central = object(self.central);
+        let gravitational_parameter = central.mass() * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+        let distance = |a: usize| vector::distance(&object(a).position(), &central.position());
+        let (inner, outer) = (distance(from), distance(to));
+        if from == to {
+            2.0 * PI * (inner.powi(3) / gravitational_parameter).sqrt()
+        } else {
+            PI * ((inner + outer) / 2.0).powi(3).sqrt() / gravitational_parameter.sqrt()
+        }
+    }
+    pub fn horizon(&self, system: &System) -> f64 {
+        let body = self.flyby.iter().chain(Some(&self.departure)).chain(Some(&self.arrival)).copied().collect::<Vec<_>>();
+        let longest = body.iter().flat_map(|a| body.iter().map(move |b| (*a, *b)))
+            .map(|(a, b)| self.reference_time(system, a, b))
+            .fold(0.0, f64::max);
+        let fraction = self.time_of_flight.iter().copied().fold(0.0, f64::max);
+        (longest * fraction * (self.max_flybys + 1) as f64).min(self.max_days)
+    }
+    pub fn run(&self, system: &System, launch: &[f64]) -> Result<Vec<MissionDesign>, String> {
+        self.check(system)?;
+        if launch.is_empty() {
+            return Ok(Vec::new())
+        }
+        let first = launch.iter().copied().fold(f64::INFINITY, f64::min);
+        let last = launch.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let history = History::propagate(system, first, last + self.horizon(system));
+        self.run_with_history(system, &history, launch)
+    }
+    pub fn run_with_history(&self, system: &System, history: &History, launch: &[f64]) -> Result<Vec<MissionDesign>, String> {
+        self.check(system)?;
+        let mut frontier = launch.iter().map(|a| Partial {
+            encounter: vec!((self.departure, *a)),
+            leg: Vec::new(),
+            cost: 0.0
+        }).collect::<Vec<_>>();
+        let mut found = Vec::new();
+        for depth in 0..=self.max_flybys {
+            let mut next = Vec::new();
+            let mut target = vec!((self.arrival, true));
+            if depth < self.max_flybys {
+                target.extend(self.flyby.iter().map(|a| (*a, false)));
+            }
+            for partial in frontier.iter() {
+                let (from, epoch) = partial.encounter[partial.encounter.len() - 1];
+                for (to, arrival) in target.iter().copied() {
+                    let reference = self.reference_time(system, from, to);
+                    for fraction in self.time_of_flight.iter() {
+                        let stop = epoch + fraction * reference;
+                        if stop - partial.encounter[0].1 > self.max_days {
+                            continue
+                        }
+                        let mut encounter = partial.encounter.clone();
+                        encounter.push((to, stop));
+                        let plan = self.plan(encounter);
+                        let mut leg = partial.leg.clone();
+                        match mission::transfer(system, history, &plan, (from, epoch), (to, stop)) {
+                            Ok(x) => leg.push(x),
+                            Err(_) => continue
+                        }
+                        let design = MissionDesign::from_transfers(system, history, &plan, leg.clone())?;
+                        let count = design.encounter.len();
+                        if leg[0].departure_v_infinity > self.max_launch_v_infinity ||
+                            design.encounter[1..count - 1].iter().any(|a| a.altitude() < self.min_flyby_altitude)
+                        {
+                            continue
+                        }
+                        let cost = design.total_delta_v() - design.encounter[count - 1].delta_v;
+                        if arrival {
+                            if design.total_delta_v() <= self.max_delta_v {
+                                found.push(design);
+                            }
+                        } else if cost <= self.max_delta_v {
+                            next.push(Partial {
+                                encounter: plan.encounter,
+                                leg,
+                                cost
+                            });
+                        }
+                    }
+                }
+            }
+            next.sort_by(|a, b| a.cost.partial_cmp(&b.cost).unwrap());
+            next.truncate(self.beam);
+            frontier = next;
+        }
+        found.sort_by(|a, b| a.total_delta_v().partial_cmp(&b.total_delta_v()).unwrap());
+        Ok(found)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use crate::system::{
+        fixture::circular_planets,
+        frame::Frame,
+        recorder::{
+            Trajectory,
+            TrajectoryFormat,
+            TrajectoryRecorder
+        }
+    };
+    fn planets() -> System {
+        circular_planets(&[("Venus", 324859.0, 6051.8, 0.723, 1.0), ("Earth", 398600.4, 6371.0, 1.0, 0.0), ("Mars", 42828.4, 3389.5, 1.524, 0.8)])
+    }
+    #[test]
+    fn search_flyby_sequences() -> Result<(), String> {
+        let system = planets();
+        let search = Search {
+            max_flybys: 1,
+            min_flyby_altitude: 0.0,
+            ..Search::new(0, 2, 3, vec!(1, 2))
+        };
+        let launch = (0..8).map(|a| a as f64 * 30.0).collect::<Vec<_>>();
+        let found = search.run(&system, &launch)?;
+        assert!(found.iter().any(|a| a.encounter.len() == 2));
+        assert!(found.iter().any(|a| a.encounter.len() == 3));
+        assert!(found.windows(2).all(|a| a[0].total_delta_v() <= a[1].total_delta_v()));
+        for design in found.iter() {
+            assert!(design.encounter.len() <= 3 && design.is_feasible());
+            assert_eq!((design.encounter[0].body, design.encounter[design.encounter.len() - 1].body), (2, 3));
+            assert!(design.encounter[1..design.encounter.len() - 1].iter().all(|a| a.altitude() >= 0.0));
+        }
+        let direct = Search {
+            max_flybys: 0,
+            max_delta_v: found[0].total_delta_v() + 1.0,
+            ..search.clone()
+        };
+        let limited = direct.run(&system, &launch)?;
+        assert!(!limited.is_empty() && limited.iter().all(|a| a.encounter.len() == 2 && a.total_delta_v() <= direct.max_delta_v));
+        assert!(Search::new(0, 0, 3, vec!()).run(&system, &launch).is_err());
+        Ok(())
+    }
+    #[test]
+    fn search_cached_trajectory() -> Result<(), String> {
+        let mut system = planets();
+        let search = Search {
+            max_flybys: 0,
+            ..Search::new(0, 2, 3, vec!())
+        };
+        let recorder = TrajectoryRecorder::create("sequence_test.bin", TrajectoryFormat::Binary, &system, &[], Frame::Inertial, 1)?;
+        let live = search.run(&system, &[0.0, 20.0])?;
+        system.attach_recorder(recorder);
+        while system.epoch() < 20.0 + search.horizon(&system) {
+            system.step();
+        }
+        for recorder in system.detach_recorders() {
+            recorder.finish()?;
+        }
+        let trajectory = Trajectory::load_binary("sequence_test.bin");
+        fs::remove_file("sequence_test.bin").unwrap();
+        let history = History::from_trajectory(&trajectory?, &planets())?;
+        let cached = search.run_with_history(&planets(), &history, &[0.0, 20.0])?;
+        assert_eq!(live.len(), cached.len());
+        for (a, b) in live.iter().zip(cached.iter()) {
+            assert_eq!(a.sequence(), b.sequence());
+            assert!((a.total_delta_v() - b.total_delta_v()).abs() < 1.0E-9);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+use clap::Parser;
+use nbodysim::cli::{
+    self,
+    IntegrationArguments,
+    RunArguments,
+    SourceArguments
+};
+use std::process;
+#[derive(Parser, Debug)]
+#[clap(name = "headless_simulation", about = "Integrates a system without opening a window")]
+struct Arguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(flatten)]
+    run: RunArguments
+}
+fn main() {
+    let arguments = Arguments::parse();
+    process::exit(cli::execute_run(&arguments.source, &arguments.integration, &arguments.run))
+}
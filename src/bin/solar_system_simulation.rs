@@ -1,3 +1,8 @@
+use clap::{
+    Args,
+    Parser,
+    Subcommand
+};
 use kiss3d::{
     event::{
         Action,
@@ -9,95 +14,477 @@ use kiss3d::{
     window::Window
 };
 use nbodysim::{
-    system::System,
-    graphics::Graphics
+    analysis::{
+        chaos,
+        eclipse::{
+            self,
+            Watch
+        },
+        ephemeris::ObserverEphemeris,
+        mission::{
+            self,
+            MissionDesign,
+            Plan
+        },
+        porkchop::{
+            self,
+            Leg,
+            Porkchop
+        },
+        resonance::{
+            self,
+            Behaviour,
+            Pericentre,
+            ResonantAngle
+        },
+        sequence::Search
+    },
+    cli::{
+        self,
+        IntegrationArguments,
+        RunArguments,
+        SourceArguments,
+        EXIT_INPUT,
+        EXIT_RUN
+    },
+    graphics::Graphics,
+    system::{
+        events,
+        frame::Frame,
+        recorder::{
+            self,
+            Trajectory
+        },
+        scenario::Scenario,
+        vector,
+        System
+    },
+    timescale::{
+        Epoch,
+        TimeScale
+    },
+    validation::{
+        self,
+        EpochReport,
+        ReferenceEphemeris,
+        Tolerance,
+        Tolerances
+    }
 };
-use std::env;
-fn main() {
-    let mut flags: Vec<Option<String>> = vec!(None; 4);
-    let args: Vec<String> = env::args().collect();
-    let flag = "get".to_string();
-    let mut list = Vec::new();
-    let mut i: usize = 0;
-    for arg in args {
-        if flags[0] == Some(flag.clone()) {
-            flags[0] = Some(arg)
-        } else if flags[1] == Some(flag.clone()) {
-            flags[1] = Some(arg)
-        } else if flags[2] == Some(flag.clone()) {
-            flags[2] = Some(arg);
-            flags[3] = Some(flag.clone())
-        } else if flags[3] == Some(flag.clone()) {
-            flags[3] = Some(arg.clone());
-            i = match arg.parse::<usize>() {
-                Ok(x) => x.clone(),
-                Err(x) => {
-                    println!("Failed to parse {}!\n{}", arg, x);
-                    return
-                }
-            }
-        } else if i > 0 {
-            i = i -1;
-            list.push(arg);
-        } else {
-            match arg.as_str() {
-                "-L" => flags[0] = Some(flag.clone()),
-                "-S" => flags[1] = Some(flag.clone()),
-                "-F" => flags[2] = Some(flag.clone()),
-                _ => ()
-            }
+use std::process;
+const DEFAULT_SYSTEM: &str = "a_few_satellites_more_1969_07_16.json";
+const DEFAULT_SCENARIO: &str = include_str!("a_few_satellites_more_1969_07_16.toml");
+#[derive(Parser, Debug)]
+#[clap(name = "solar_system_simulation", about = "Simulates bodies of the solar system using data from JPL HORIZONS")]
+struct Arguments {
+    #[clap(subcommand)]
+    command: Option<Command>
+}
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[clap(about = "Open a window and watch the system evolve (default)")]
+    View(ViewArguments),
+    #[clap(about = "Fetch a system from HORIZONS and save it as JSON")]
+    Fetch(FetchArguments),
+    #[clap(about = "Integrate a system without opening a window")]
+    Run(RunCommand),
+    #[clap(about = "Export a saved system, checkpoint or binary trajectory as CSV")]
+    Export(ExportArguments),
+    #[clap(about = "Compare the bodies of two saved systems")]
+    Compare(CompareArguments),
+    #[clap(about = "Fetch reference ephemerides from HORIZONS for validation")]
+    Reference(ReferenceArguments),
+    #[clap(about = "Integrate from a reference ephemeris and check the error against it")]
+    Validate(ValidateArguments),
+    #[clap(about = "Scan for near commensurabilities or track a resonant angle")]
+    Resonance(ResonanceArguments),
+    #[clap(about = "Estimate MEGNO and the maximal Lyapunov exponent from tangent vectors")]
+    Chaos(ChaosArguments),
+    #[clap(about = "Predict eclipses, transits and occultations with their contact times")]
+    Eclipses(EclipseArguments),
+    #[clap(about = "Find apsides, node crossings, conjunctions and oppositions")]
+    Events(EventArguments),
+    #[clap(about = "Generate an observer table with RA/Dec, range, range-rate, elongation and phase angle")]
+    Ephemeris(EphemerisArguments),
+    #[clap(about = "Sweep departure and arrival epochs for Lambert transfers and draw a porkchop plot")]
+    Porkchop(PorkchopArguments),
+    #[clap(about = "Design a patched-conic mission through flybys and refine its legs in the n-body system")]
+    Mission(MissionArguments),
+    #[clap(about = "Search gravity-assist flyby sequences over a launch window")]
+    Sequences(SequenceArguments)
+}
+#[derive(Args, Debug, Default)]
+struct ViewArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(short = 'S', long, value_parser, value_name = "FILE", help = "Save the system as JSON when the window closes")]
+    save: Option<String>,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 40.0, help = "Length of the trails drawn behind bodies")]
+    trail_days: f64,
+    #[clap(long, value_parser, value_name = "BODY", help = "Body the camera follows at start, by name, HORIZONS id or index")]
+    focus: Option<String>,
+    #[clap(long, value_parser, value_name = "FRAME",
+        help = "Draw trails in a frame: inertial, body:BODY or synodic:PRIMARY,SECONDARY")]
+    frame: Option<String>,
+    #[clap(long, value_parser, value_names = &["PRIMARY", "SECONDARY"], number_of_values = 2, action = clap::ArgAction::Append,
+        help = "Mark the lagrange points of a pair of bodies, by name, HORIZONS id or index")]
+    lagrange: Vec<String>
+}
+#[derive(Args, Debug)]
+struct FetchArguments {
+    #[clap(value_parser, value_name = "EPOCH", help = "Epoch to fetch the system at, TDB unless a UTC, TAI or TT scale follows")]
+    date: String,
+    #[clap(short = 'T', long, value_parser, value_name = "TARGET", multiple_values = true, help = "HORIZONS targets to fetch")]
+    targets: Vec<String>,
+    #[clap(long, value_parser, value_name = "FILE", help = "Read HORIZONS targets to fetch from a file")]
+    targets_file: Option<String>,
+    #[clap(short, long, value_parser, value_name = "FILE", help = "File to save the system to")]
+    output: String
+}
+#[derive(Args, Debug)]
+struct RunCommand {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(flatten)]
+    run: RunArguments
+}
+#[derive(Args, Debug)]
+struct ExportArguments {
+    #[clap(value_parser, value_name = "INPUT", help = "Saved system, checkpoint or binary trajectory")]
+    input: String,
+    #[clap(value_parser, value_name = "OUTPUT", help = "CSV file to write")]
+    output: String,
+    #[clap(long, value_parser, value_name = "FRAME",
+        help = "Export a system in a frame: inertial, body:BODY or synodic:PRIMARY,SECONDARY")]
+    frame: Option<String>
+}
+#[derive(Args, Debug)]
+struct CompareArguments {
+    #[clap(value_parser, value_name = "REFERENCE", help = "Saved system or checkpoint to compare against")]
+    reference: String,
+    #[clap(value_parser, value_name = "TEST", help = "Saved system or checkpoint to compare")]
+    test: String,
+    #[clap(long, value_parser, value_name = "KM", help = "Fail when any body is further than this from the reference")]
+    tolerance: Option<f64>
+}
+#[derive(Args, Debug)]
+struct ReferenceArguments {
+    #[clap(value_parser, value_name = "EPOCH", help = "Epoch of the first snapshot")]
+    date: String,
+    #[clap(long, value_parser, value_name = "DAYS", help = "Days from the first snapshot to the last")]
+    days: i64,
+    #[clap(long, value_parser, value_name = "DAYS", help = "Days between snapshots, only the ends if omitted")]
+    every: Option<i64>,
+    #[clap(short = 'T', long, value_parser, value_name = "TARGET", multiple_values = true, help = "HORIZONS targets to fetch")]
+    targets: Vec<String>,
+    #[clap(long, value_parser, value_name = "FILE", help = "Read HORIZONS targets to fetch from a file")]
+    targets_file: Option<String>,
+    #[clap(short, long, value_parser, value_name = "FILE", help = "File to save the reference to")]
+    output: String
+}
+#[derive(Args, Debug)]
+struct ValidateArguments {
+    #[clap(value_parser, value_name = "REFERENCE", help = "Reference ephemeris to validate against")]
+    reference: String,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(long, value_parser, value_name = "KM", help = "Fail when any body is further than this from the reference")]
+    position_tolerance: Option<f64>,
+    #[clap(long, value_parser, value_name = "M/S", help = "Fail when any velocity is further than this from the reference")]
+    velocity_tolerance: Option<f64>,
+    #[clap(long, value_parser, value_name = "FRACTION",
+        help = "Fail when any position error is larger than this fraction of the distance to the first body")]
+    relative_tolerance: Option<f64>,
+    #[clap(long, value_parser, value_name = "FILE", help = "Read default and per body tolerances from a TOML file")]
+    tolerances: Option<String>,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the accuracy report as JSON")]
+    report: Option<String>
+}
+#[derive(Args, Debug)]
+struct ResonanceArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "0", help = "Body the orbits are measured around")]
+    central: String,
+    #[clap(long, value_parser, value_names = &["INNER", "OUTER"], number_of_values = 2, requires = "ratio",
+        help = "Track the resonant angle of two bodies instead of scanning")]
+    pair: Vec<String>,
+    #[clap(long, value_parser, value_name = "P:Q", help = "Ratio of the outer period to the inner period, such as 3:2")]
+    ratio: Option<String>,
+    #[clap(long, value_parser, action, help = "Use the longitude of periapsis of the outer body instead of the inner body")]
+    outer_pericentre: bool,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 3652.5, help = "Days to track the angle for")]
+    days: f64,
+    #[clap(long, value_parser, value_name = "STEPS", default_value_t = 24, help = "Steps between samples of the angle")]
+    cadence: usize,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the sampled angle as CSV")]
+    csv: Option<String>,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 5, help = "Largest coefficient of a scanned ratio")]
+    max_coefficient: u32,
+    #[clap(long, value_parser, value_name = "FRACTION", default_value_t = 0.01,
+        help = "Largest relative distance of a period ratio from a scanned ratio")]
+    tolerance: f64
+}
+#[derive(Args, Debug)]
+struct ChaosArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(long, value_parser, value_name = "BODY", multiple_values = true,
+        help = "Bodies the tangent vector is seeded and measured on, every body when left out")]
+    bodies: Vec<String>,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 36525.0, help = "Days to integrate for")]
+    days: f64,
+    #[clap(long, value_parser, value_name = "STEPS", default_value_t = 240, help = "Steps between samples of the indicators")]
+    cadence: usize,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the sampled indicators as CSV")]
+    csv: Option<String>
+}
+#[derive(Args, Debug)]
+struct EclipseArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "Earth", help = "Body the events are seen from")]
+    observer: String,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "Sun", help = "Body whose light casts the shadows")]
+    light: String,
+    #[clap(long, value_parser, value_names = &["OCCULTER", "TARGET"], number_of_values = 2, action = clap::ArgAction::Append,
+        help = "Watch for TARGET passing through the shadow of OCCULTER")]
+    eclipse: Vec<String>,
+    #[clap(long, value_parser, value_names = &["FIRST", "SECOND"], number_of_values = 2, action = clap::ArgAction::Append,
+        help = "Watch for transits and occultations of two bodies seen from the observer")]
+    transit: Vec<String>,
+    #[clap(long, value_parser, value_name = "BODY", action = clap::ArgAction::Append,
+        help = "Watch for eclipses, transits and occultations of every satellite of BODY")]
+    satellites_of: Vec<String>,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 365.25, help = "Days to search")]
+    days: f64,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 8, help = "Points each step is searched at")]
+    subdivisions: usize,
+    #[clap(long, value_parser, action, help = "Ignore the light time between the bodies and the observer")]
+    geometric: bool,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the events and their contacts as CSV")]
+    csv: Option<String>,
+    #[clap(long, value_parser, value_name = "SCALE", default_value = "TDB", help = "Time scale of printed epochs: UTC, TAI, TT or TDB")]
+    time_scale: String
+}
+#[derive(Args, Debug)]
+struct EventArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(long, value_parser, value_name = "BODY", action = clap::ArgAction::Append, help = "Find periapsis and apoapsis passages of BODY")]
+    apsides: Vec<String>,
+    #[clap(long, value_parser, value_name = "BODY", action = clap::ArgAction::Append, help = "Find the node crossings of BODY")]
+    nodes: Vec<String>,
+    #[clap(long, value_parser, value_name = "BODY",
+        help = "Body apsides and nodes are measured around, the primary of each body when left out")]
+    central: Option<String>,
+    #[clap(long, value_parser, value_names = &["FIRST", "SECOND"], number_of_values = 2, action = clap::ArgAction::Append,
+        help = "Find conjunctions and oppositions of two bodies seen from the observer")]
+    conjunctions: Vec<String>,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "Earth", help = "Body conjunctions are seen from")]
+    observer: String,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 365.25, help = "Days to search")]
+    days: f64,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 4, help = "Points each step is searched at")]
+    subdivisions: usize,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the events as CSV")]
+    csv: Option<String>,
+    #[clap(long, value_parser, value_name = "SCALE", default_value = "TDB", help = "Time scale of printed epochs: UTC, TAI, TT or TDB")]
+    time_scale: String
+}
+#[derive(Args, Debug)]
+struct EphemerisArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(value_parser, value_name = "TARGET", help = "Body to observe")]
+    target: String,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "Earth", help = "Body observing the target")]
+    observer: String,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "Sun", help = "Body elongation and phase angle are measured to")]
+    sun: String,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 30.0, help = "Days to tabulate")]
+    days: f64,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 1.0, help = "Days between rows")]
+    every: f64,
+    #[clap(long, value_parser, action, help = "Correct the direction for stellar aberration")]
+    aberration: bool,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the table as CSV")]
+    csv: Option<String>,
+    #[clap(long, value_parser, value_name = "SCALE", default_value = "TDB", help = "Time scale of printed epochs: UTC, TAI, TT or TDB")]
+    time_scale: String
+}
+#[derive(Args, Debug)]
+struct PorkchopArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(value_parser, value_name = "FROM", help = "Body the transfer departs from")]
+    from: String,
+    #[clap(value_parser, value_name = "TO", help = "Body the transfer arrives at")]
+    to: String,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "Sun", help = "Body the transfer orbits")]
+    central: String,
+    #[clap(long, value_parser, value_name = "EPOCH", help = "First departure, the epoch of the system when left out")]
+    depart: Option<String>,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 180.0, help = "Days of departures to sweep")]
+    depart_days: f64,
+    #[clap(long, value_parser, value_name = "EPOCH", help = "First arrival, the first departure when left out")]
+    arrive: Option<String>,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 360.0, help = "Days of arrivals to sweep")]
+    arrive_days: f64,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 5.0, help = "Days between epochs of the grid")]
+    every: f64,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 0, help = "Most complete revolutions of a transfer")]
+    revolutions: u32,
+    #[clap(long, value_parser, action, help = "Search retrograde transfers instead of prograde ones")]
+    retrograde: bool,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the grid as CSV")]
+    csv: Option<String>,
+    #[clap(long, value_parser, value_name = "FILE", help = "Draw C3, arrival v-infinity and time of flight as an SVG contour plot")]
+    svg: Option<String>,
+    #[clap(long, value_parser, value_name = "SCALE", default_value = "TDB", help = "Time scale of printed epochs: UTC, TAI, TT or TDB")]
+    time_scale: String
+}
+#[derive(Args, Debug)]
+struct MissionArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(long, value_parser, value_names = &["BODY", "EPOCH"], number_of_values = 2, action = clap::ArgAction::Append, required = true,
+        help = "Encounter BODY at EPOCH, from the departure through every flyby to the arrival")]
+    encounter: Vec<String>,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "Sun", help = "Body the heliocentric legs orbit")]
+    central: String,
+    #[clap(long, value_parser, value_name = "KM", default_value_t = 200.0, help = "Altitude of the parking orbit to depart from")]
+    departure_altitude: f64,
+    #[clap(long, value_parser, value_name = "KM", default_value_t = 500.0, help = "Altitude of the circular orbit to capture into")]
+    arrival_altitude: f64,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 0, help = "Most complete revolutions of a leg")]
+    revolutions: u32,
+    #[clap(long, value_parser, action, help = "Fly retrograde legs instead of prograde ones")]
+    retrograde: bool,
+    #[clap(long, value_parser, value_name = "KM", default_value_t = 1000.0, help = "Largest miss at the next sphere of influence")]
+    tolerance: f64,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 10, help = "Most corrections of a leg")]
+    iterations: usize,
+    #[clap(long, value_parser, action, help = "Only design the patched conics without refining them")]
+    patched_only: bool,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the encounters as CSV")]
+    csv: Option<String>,
+    #[clap(long, value_parser, value_name = "SCALE", default_value = "TDB", help = "Time scale of printed epochs: UTC, TAI, TT or TDB")]
+    time_scale: String
+}
+#[derive(Args, Debug)]
+struct SequenceArguments {
+    #[clap(flatten)]
+    source: SourceArguments,
+    #[clap(flatten)]
+    integration: IntegrationArguments,
+    #[clap(value_parser, value_name = "FROM", help = "Body to launch from")]
+    from: String,
+    #[clap(value_parser, value_name = "TO", help = "Body to arrive at")]
+    to: String,
+    #[clap(long, value_parser, value_name = "BODY", multiple_values = true, help = "Bodies that may be flown by")]
+    flyby: Vec<String>,
+    #[clap(long, value_parser, value_name = "BODY", default_value = "Sun", help = "Body the heliocentric legs orbit")]
+    central: String,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 2, help = "Most flybys of a sequence")]
+    depth: usize,
+    #[clap(long, value_parser, value_name = "EPOCH", help = "First launch, the epoch of the system when left out")]
+    launch: Option<String>,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 365.0, help = "Days of launches to search")]
+    launch_days: f64,
+    #[clap(long, value_parser, value_name = "DAYS", default_value_t = 10.0, help = "Days between launches")]
+    every: f64,
+    #[clap(long, value_parser, value_name = "FRACTION", multiple_values = true,
+        default_values = &["0.6", "0.8", "1.0", "1.2", "1.4"],
+        help = "Times of flight of a leg as fractions of its Hohmann transfer, or of the orbital period when returning to a body")]
+    time_of_flight: Vec<f64>,
+    #[clap(long, value_parser, value_name = "KM", default_value_t = 200.0, help = "Altitude of the parking orbit to depart from")]
+    departure_altitude: f64,
+    #[clap(long, value_parser, value_name = "KM", default_value_t = 500.0, help = "Altitude of the circular orbit to capture into")]
+    arrival_altitude: f64,
+    #[clap(long, value_parser, value_name = "KM", default_value_t = 300.0, help = "Prune flybys passing lower than this above the surface")]
+    min_altitude: f64,
+    #[clap(long, value_parser, value_name = "KM/S", help = "Prune launches with a higher v-infinity")]
+    max_launch_v_infinity: Option<f64>,
+    #[clap(long, value_parser, value_name = "KM/S", help = "Prune sequences needing more Δv")]
+    max_delta_v: Option<f64>,
+    #[clap(long, value_parser, value_name = "DAYS", help = "Prune sequences flying longer")]
+    max_days: Option<f64>,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 50, help = "Partial sequences kept at every depth, the cheapest first")]
+    beam: usize,
+    #[clap(long, value_parser, value_name = "FILE",
+        help = "Take the ephemerides from an inertial trajectory recorded of every body instead of integrating the system")]
+    trajectory: Option<String>,
+    #[clap(long, value_parser, value_name = "N", default_value_t = 10, help = "Sequences to print")]
+    top: usize,
+    #[clap(long, value_parser, value_name = "SCALE", default_value = "TDB", help = "Time scale of printed epochs: UTC, TAI, TT or TDB")]
+    time_scale: String
+}
+fn default_system() -> Result<System, String> {
+    match System::load_json(DEFAULT_SYSTEM) {
+        Err(x) => {
+            println!("Failed to load {}, fetching from HORIZONS...\n{}", DEFAULT_SYSTEM, x);
+            let system = Scenario::from_toml(DEFAULT_SCENARIO)?.build()?;
+            system.save_json(DEFAULT_SYSTEM)?;
+            Ok(system)
         }
+        Ok(x) => Ok(x)
     }
-    let delta_time = 1.0/24.0;
-    let mut system = if flags[2] != None {
-        let data = match &flags[2] {
-            Some(x) => x.as_str(),
-            _ => ""
-        };
-        let date = match time::Date::parse(data, "%F") {
-            Ok(x) => x,
-            Err(x) => {
-                println!("Failed to parse date {}!\n{}", data, x);
-                return
-            }
-        };
-        match System::fetch_from_horizons(list.as_slice(), &date) {
-            Ok(x) => x,
-            Err(x) => {
-                println!("Failed to fetch system data!\n{}", x.clone());
-                return
-            }
-        }
+}
+fn view(arguments: &ViewArguments) -> Result<(), String> {
+    let mut system = if arguments.source.is_empty() {
+        default_system()?
     } else {
-        let name = match &flags[0] {
-            Some(x) => x,
-            _ => "a_few_satellites_more_1969_07_16.json"
-        };
-        match System::load_json(&name) {
-            Err(x) => {
-                println!("Failed to load {}, fetching from HORIZONS...\n{}",name, x);
-                let list = vec!(r"10", r"199", r"299", r"399", r"499", r"599", r"699", r"799", r"899", r"999",
-                    r"301", r"401", r"402",
-                    r"A801 AA", r"A807 FA", r"A802 FA", r"A849 GA", r"A854 RA", r"A910 TC", r"A903 KB", r"A904 HE", r"A851 OA", r"A804 RA", r"A852 FA", r"A858 CA",
-                    r"501", r"502", r"503", r"504", r"505", r"506", r"507", r"508", r"509", r"510", r"511", r"512", r"513", r"514", r"515", r"516",
-                    r"601", r"602", r"603", r"604", r"605", r"606", r"607", r"608", r"609", r"610", r"611", r"612", r"613", r"614", r"615", r"616", r"617", r"618",
-                    r"701", r"702", r"703", r"704", r"705",
-                    r"801", r"802", r"803", r"804", r"805", r"806", r"807", r"808", r"809", r"810", r"811", r"812", r"813", r"814",
-                    r"901", r"902", r"903", r"904", r"905"
-                );
-                let date = time::Date::try_from_ymd(1969,07,16).unwrap();
-                let system = System::fetch_from_horizons(&list, &date).unwrap();
-                system.save_json(&name).unwrap();
-                system
-            }
-            Ok(x) => x
-        }
+        arguments.source.build_system()?
     };
+    arguments.integration.apply(&mut system)?;
+    if arguments.trail_days < 0.0 {
+        return Err(format!("Failed to set trail length {}! It must not be negative", arguments.trail_days))
+    }
+    let focus = match &arguments.focus {
+        Some(x) => Some(system.find_body(x)?),
+        None => None
+    };
+    let frame = match &arguments.frame {
+        Some(x) => Frame::parse(&system, x)?,
+        None => Frame::Inertial
+    };
+    frame.transform(&system)?;
+    let lagrange = arguments.lagrange.iter()
+        .map(|a| system.find_body(a))
+        .collect::<Result<Vec<usize>, String>>()?;
+    let delta_time = system.delta_time();
     let mut window = Window::new("Kiss3d: solar system n-body simulator");
     window.set_light(Light::StickToCamera);
     let eye = Point3::new(0.0f32, 0.0, -1.0);
     let at =  Point3::origin();
-    let mut graphics = Graphics::new(&system, eye, at, (40 as f64 / delta_time) as usize, &mut window.add_group());
+    let trail_size = ((arguments.trail_days / delta_time) as usize).max(1);
+    let mut graphics = Graphics::new(&system, eye, at, trail_size, &mut window.add_group());
+    graphics.set_frame(&system, frame)?;
+    if let Some(x) = focus {
+        graphics.focus(x);
+    }
+    for pair in lagrange.chunks(2) {
+        graphics.show_lagrange_points(&system, pair[0], pair[1])?;
+    }
     let camera = graphics.camera();
     camera.set_dist(1.0);
     camera.set_dist_step(0.5);
@@ -105,39 +492,424 @@ fn main() {
     camera.set_max_dist(7.0E2);
     while window.render_with_camera(graphics.camera()) {
         for event in window.events().iter() {
-            match event.value {
-                WindowEvent::Key(button, Action::Press, _) => {
-                    match button {
-                        Key::Key1 => graphics.focus(1),
-                        Key::Key2 => graphics.focus(2),
-                        Key::Key3 => graphics.focus(3),
-                        Key::Key4 => graphics.focus(4),
-                        Key::Key5 => graphics.focus(5),
-                        Key::Key6 => graphics.focus(6),
-                        Key::Key7 => graphics.focus(7),
-                        Key::Key8 => graphics.focus(8),
-                        Key::Key9 => graphics.focus(9),
-                        Key::Key0 => graphics.focus(0),
-                        Key::Equals => graphics.focus_next(),
-                        Key::Minus => graphics.focus_last(),
-                        Key::Back => graphics.unfocus(),
-                        _ => ()
-                    }
-                },
-                _ => ()
+            if let WindowEvent::Key(button, Action::Press, _) = event.value {
+                let focus = |graphics: &mut Graphics, index: usize| if index < system.size() {
+                    graphics.focus(index)
+                };
+                match button {
+                    Key::Key1 => focus(&mut graphics, 1),
+                    Key::Key2 => focus(&mut graphics, 2),
+                    Key::Key3 => focus(&mut graphics, 3),
+                    Key::Key4 => focus(&mut graphics, 4),
+                    Key::Key5 => focus(&mut graphics, 5),
+                    Key::Key6 => focus(&mut graphics, 6),
+                    Key::Key7 => focus(&mut graphics, 7),
+                    Key::Key8 => focus(&mut graphics, 8),
+                    Key::Key9 => focus(&mut graphics, 9),
+                    Key::Key0 => focus(&mut graphics, 0),
+                    Key::Equals => graphics.focus_next(),
+                    Key::Minus => graphics.focus_last(),
+                    Key::Back => graphics.unfocus(),
+                    _ => ()
+                }
             }
         }
-        system.kick_drift_kick_step(delta_time);
-        graphics.update(&system);
+        system.step();
+        graphics.update(&system)?;
         graphics.draw(&mut window);
     };
-    match &flags[1] {
-        Some(x) => match system.save_json(&x) {
-            Ok(_) => (),
-            Err(y) => {
-                println!("Failed to save {}!\n{}", x, y)
+    match &arguments.save {
+        Some(x) => system.save_json(x),
+        None => Ok(())
+    }
+}
+fn fetch(arguments: &FetchArguments) -> Result<(), String> {
+    let source = SourceArguments {
+        fetch: Some(arguments.date.clone()),
+        targets: arguments.targets.clone(),
+        targets_file: arguments.targets_file.clone(),
+        ..SourceArguments::default()
+    };
+    source.build_system()?.save_json(&arguments.output)
+}
+fn export(arguments: &ExportArguments) -> Result<(), String> {
+    let trajectory = if &cli::file_magic(&arguments.input)? == recorder::MAGIC {
+        if arguments.frame.is_some() {
+            return Err(format!("Failed to export {}! Trajectories keep the frame they were recorded in, pass --frame to run",
+                arguments.input))
+        }
+        Trajectory::load_binary(&arguments.input)?
+    } else {
+        let system = cli::load_system_file(&arguments.input)?;
+        let frame = match &arguments.frame {
+            Some(x) => Frame::parse(&system, x)?,
+            None => Frame::Inertial
+        };
+        Trajectory::from_system(&system, frame)?
+    };
+    trajectory.save_csv(&arguments.output)
+}
+fn print_errors(report: &EpochReport) {
+    println!("{:<32} {:>18} {:>18}", "body", "position (km)", "velocity (m/s)");
+    for body in report.body.iter() {
+        println!("{:<32} {:>18.3} {:>18.6}{}", body.name, body.position, body.velocity,
+            if body.passed { "" } else { "  exceeds tolerance" });
+    }
+    for name in report.missing.iter() {
+        println!("{:<32} missing", name);
+    }
+}
+fn compare(arguments: &CompareArguments) -> Result<bool, String> {
+    let reference = cli::load_system_file(&arguments.reference)?;
+    let test = cli::load_system_file(&arguments.test)?;
+    if reference.epoch() != test.epoch() {
+        println!("Comparing systems at different epochs {} and {}", reference.epoch(), test.epoch());
+    }
+    let tolerances = Tolerances::new(Tolerance {
+        position: arguments.tolerance,
+        ..Tolerance::default()
+    });
+    let report = validation::compare(&reference, &test, &tolerances);
+    print_errors(&report);
+    Ok(report.passed())
+}
+fn reference(arguments: &ReferenceArguments) -> Result<(), String> {
+    let source = SourceArguments {
+        targets: arguments.targets.clone(),
+        targets_file: arguments.targets_file.clone(),
+        ..SourceArguments::default()
+    };
+    let list = source.target_list()?;
+    if list.is_empty() {
+        return Err("Failed to fetch reference! Pass --targets or --targets-file".to_string())
+    }
+    if arguments.days <= 0 {
+        return Err(format!("Failed to fetch reference! Days {} must be positive", arguments.days))
+    }
+    let every = match arguments.every {
+        Some(x) if x <= 0 => return Err(format!("Failed to fetch reference! Every {} must be positive", x)),
+        Some(x) => x,
+        None => arguments.days
+    };
+    let start = cli::parse_epoch(&arguments.date)?;
+    let mut dates: Vec<Epoch> = (0..arguments.days).step_by(every as usize)
+        .map(|a| start.add_days(a as f64))
+        .collect();
+    dates.push(start.add_days(arguments.days as f64));
+    ReferenceEphemeris::fetch_from_horizons(&list, &dates)?.save_json(&arguments.output)
+}
+fn validate(arguments: &ValidateArguments) -> Result<bool, String> {
+    let reference = ReferenceEphemeris::load_json(&arguments.reference)?;
+    let mut tolerances = match &arguments.tolerances {
+        Some(x) => Tolerances::load_toml(x)?,
+        None => Tolerances::default()
+    };
+    if arguments.position_tolerance.is_some() {
+        tolerances.default.position = arguments.position_tolerance;
+    }
+    if arguments.velocity_tolerance.is_some() {
+        tolerances.default.velocity = arguments.velocity_tolerance;
+    }
+    if arguments.relative_tolerance.is_some() {
+        tolerances.default.relative = arguments.relative_tolerance;
+    }
+    let mut settings = System::new();
+    arguments.integration.apply(&mut settings)?;
+    let report = validation::validate(&reference, settings.integrator(), settings.delta_time(), &tolerances)?;
+    for epoch in report.epoch.iter() {
+        println!("Epoch {}", epoch.epoch);
+        print_errors(epoch);
+    }
+    println!("Largest errors {:.3} km and {:.6} m/s in {:.1}s", report.max_position, report.max_velocity, report.wall_time);
+    if let Some(x) = &arguments.report {
+        report.save_json(x)?;
+    }
+    Ok(report.passed)
+}
+fn resonance(arguments: &ResonanceArguments) -> Result<(), String> {
+    let mut system = arguments.source.build_system()?;
+    arguments.integration.apply(&mut system)?;
+    let central = system.find_body(&arguments.central)?;
+    let name = |a: usize| system.object_from_index(a).unwrap().read().unwrap().name().to_string();
+    if arguments.pair.is_empty() {
+        println!("{:<32} {:<32} {:>6} {:>12} {:>10}", "inner", "outer", "ratio", "periods", "offset");
+        for a in resonance::scan(&system, central, arguments.max_coefficient, arguments.tolerance)? {
+            println!("{:<32} {:<32} {:>6} {:>12.6} {:>+10.5}", name(a.inner), name(a.outer), format!("{}:{}", a.p, a.q),
+                a.period_ratio, a.offset);
+        }
+        return Ok(())
+    }
+    let (p, q) = resonance::parse_ratio(arguments.ratio.as_deref().unwrap_or_default())?;
+    let pericentre = if arguments.outer_pericentre { Pericentre::Outer } else { Pericentre::Inner };
+    let inner = system.find_body(&arguments.pair[0])?;
+    let outer = system.find_body(&arguments.pair[1])?;
+    let angle = ResonantAngle::new(central, inner, outer, p, q, pericentre)?;
+    let (inner, outer) = (name(inner), name(outer));
+    let tracker = resonance::track(&mut system, angle, arguments.days, arguments.cadence)?;
+    if let Some(x) = &arguments.csv {
+        tracker.save_csv(x)?;
+    }
+    match tracker.classify() {
+        Some(Behaviour::Librating { centre, amplitude }) => println!("{}:{} angle of {} and {} librates around {:.2} degrees with an amplitude of {:.2} degrees",
+            p, q, inner, outer, centre.to_degrees(), amplitude.to_degrees()),
+        Some(Behaviour::Circulating { rate }) => println!("{}:{} angle of {} and {} circulates at {:.6} degrees a day",
+            p, q, inner, outer, rate.to_degrees()),
+        None => println!("Too few samples to classify the {}:{} angle of {} and {}", p, q, inner, outer)
+    }
+    Ok(())
+}
+fn chaos(arguments: &ChaosArguments) -> Result<(), String> {
+    let mut system = arguments.source.build_system()?;
+    arguments.integration.apply(&mut system)?;
+    let bodies = if arguments.bodies.is_empty() {
+        (0..system.size()).collect()
+    } else {
+        arguments.bodies.iter().map(|a| system.find_body(a)).collect::<Result<Vec<usize>, String>>()?
+    };
+    let indicator = chaos::estimate(&mut system, &bodies, arguments.days, arguments.cadence)?;
+    if let Some(x) = &arguments.csv {
+        indicator.save_csv(x)?;
+    }
+    if let Some(x) = indicator.history.last() {
+        println!("MEGNO {:.4} after {} days, 2 for regular motion and growing for chaotic motion", x.megno, arguments.days);
+        println!("Lyapunov exponent {:.6e} per day, Lyapunov time {:.1} years", x.lyapunov, 1.0 / x.lyapunov / 365.25);
+    }
+    Ok(())
+}
+fn eclipses(arguments: &EclipseArguments) -> Result<(), String> {
+    let mut system = arguments.source.build_system()?;
+    arguments.integration.apply(&mut system)?;
+    let observer = system.find_body(&arguments.observer)?;
+    let light = system.find_body(&arguments.light)?;
+    let mut watch = Vec::new();
+    for pair in arguments.eclipse.chunks(2) {
+        watch.push(Watch::Eclipse { source: light, occulter: system.find_body(&pair[0])?, target: system.find_body(&pair[1])? });
+    }
+    for pair in arguments.transit.chunks(2) {
+        watch.push(Watch::Alignment { observer, first: system.find_body(&pair[0])?, second: system.find_body(&pair[1])? });
+    }
+    for planet in arguments.satellites_of.iter() {
+        let planet = system.find_body(planet)?;
+        for moon in (0..system.size()).filter(|a| resonance::primary_of(&system, *a) == Some(planet)) {
+            if system.object_from_index(moon).unwrap().read().unwrap().radius() > 0.0 {
+                watch.push(Watch::Eclipse { source: light, occulter: planet, target: moon });
+                watch.push(Watch::Alignment { observer, first: moon, second: planet });
             }
+        }
+    }
+    if watch.is_empty() {
+        return Err("Failed to predict events! Nothing to watch, use --eclipse, --transit or --satellites-of".to_string())
+    }
+    let scale = TimeScale::from_name(&arguments.time_scale)?;
+    let events = eclipse::predict(&mut system, &watch, Some(observer), arguments.days, !arguments.geometric,
+        arguments.subdivisions)?;
+    for a in events.iter() {
+        println!("{}  {}, magnitude {:.3}", cli::format_epoch(a.maximum, scale), a.describe(&system), a.magnitude);
+        for b in a.contacts.iter() {
+            println!("    {} {}", b.name, cli::format_epoch(b.epoch, scale));
+        }
+    }
+    if let Some(x) = &arguments.csv {
+        eclipse::save_csv(&events, &system, x)?;
+    }
+    Ok(())
+}
+fn find_events(arguments: &EventArguments) -> Result<(), String> {
+    let mut system = arguments.source.build_system()?;
+    arguments.integration.apply(&mut system)?;
+    let central = |system: &System, body: usize| match &arguments.central {
+        Some(x) => system.find_body(x),
+        None => resonance::primary_of(system, body)
+            .ok_or(format!("Failed to find the primary of body {}! Choose one with --central", body))
+    };
+    let mut function = Vec::new();
+    for body in arguments.apsides.iter() {
+        let body = system.find_body(body)?;
+        function.push(events::apsides(&system, body, central(&system, body)?)?);
+    }
+    for body in arguments.nodes.iter() {
+        let body = system.find_body(body)?;
+        function.push(events::nodes(&system, body, central(&system, body)?)?);
+    }
+    let observer = system.find_body(&arguments.observer)?;
+    for pair in arguments.conjunctions.chunks(2) {
+        function.push(events::conjunctions(&system, observer, system.find_body(&pair[0])?, system.find_body(&pair[1])?)?);
+    }
+    if function.is_empty() {
+        return Err("Failed to find events! Nothing to find, use --apsides, --nodes or --conjunctions".to_string())
+    }
+    let scale = TimeScale::from_name(&arguments.time_scale)?;
+    let detector = events::find_events(&mut system, function, arguments.days, arguments.subdivisions)?;
+    for a in detector.detections.iter() {
+        println!("{}  {}", cli::format_epoch(a.epoch, scale), a.label);
+    }
+    if let Some(x) = &arguments.csv {
+        detector.save_csv(x)?;
+    }
+    Ok(())
+}
+fn generate_ephemeris(arguments: &EphemerisArguments) -> Result<(), String> {
+    let mut system = arguments.source.build_system()?;
+    arguments.integration.apply(&mut system)?;
+    let observer = system.find_body(&arguments.observer)?;
+    let target = system.find_body(&arguments.target)?;
+    let sun = system.find_body(&arguments.sun)?;
+    let scale = TimeScale::from_name(&arguments.time_scale)?;
+    let ephemeris = ObserverEphemeris::generate(&mut system, observer, target, sun, arguments.days, arguments.every,
+        arguments.aberration)?;
+    print!("{}", ephemeris.table(scale));
+    if let Some(x) = &arguments.csv {
+        ephemeris.save_csv(x)?;
+    }
+    Ok(())
+}
+fn generate_porkchop(arguments: &PorkchopArguments) -> Result<(), String> {
+    let mut system = arguments.source.build_system()?;
+    arguments.integration.apply(&mut system)?;
+    let leg = Leg {
+        max_revolutions: arguments.revolutions,
+        prograde: !arguments.retrograde,
+        ..Leg::new(system.find_body(&arguments.from)?, system.find_body(&arguments.to)?, system.find_body(&arguments.central)?)
+    };
+    let scale = TimeScale::from_name(&arguments.time_scale)?;
+    let depart = match &arguments.depart {
+        Some(x) => cli::parse_epoch(x)?.to(TimeScale::Tdb).julian_date(),
+        None => system.epoch()
+    };
+    let arrive = match &arguments.arrive {
+        Some(x) => cli::parse_epoch(x)?.to(TimeScale::Tdb).julian_date(),
+        None => depart
+    };
+    let porkchop = Porkchop::generate(&system, &leg, &porkchop::sweep(depart, arguments.depart_days, arguments.every)?,
+        &porkchop::sweep(arrive, arguments.arrive_days, arguments.every)?)?;
+    match porkchop.best() {
+        Some(x) => println!("Lowest C3 {:.3} km²/s² departing {} and arriving {} after {:.1} days, arrival v-infinity {:.3} km/s, {} revolutions",
+            x.c3, cli::format_epoch(x.departure, scale), cli::format_epoch(x.arrival, scale), x.time_of_flight(),
+            x.arrival_v_infinity, x.revolutions),
+        None => println!("No transfer from {} to {} found in the grid", porkchop.from, porkchop.to)
+    }
+    if let Some(x) = &arguments.csv {
+        porkchop.save_csv(x)?;
+    }
+    if let Some(x) = &arguments.svg {
+        porkchop.save_svg(x)?;
+    }
+    Ok(())
+}
+fn design_mission(arguments: &MissionArguments) -> Result<(), String> {
+    let mut system = arguments.source.build_system()?;
+    arguments.integration.apply(&mut system)?;
+    let scale = TimeScale::from_name(&arguments.time_scale)?;
+    let mut encounter = Vec::new();
+    for x in arguments.encounter.chunks(2) {
+        encounter.push((system.find_body(&x[0])?, cli::parse_epoch(&x[1])?.to(TimeScale::Tdb).julian_date()));
+    }
+    let plan = Plan {
+        departure_altitude: arguments.departure_altitude,
+        arrival_altitude: arguments.arrival_altitude,
+        max_revolutions: arguments.revolutions,
+        prograde: !arguments.retrograde,
+        ..Plan::new(system.find_body(&arguments.central)?, encounter)
+    };
+    let design = MissionDesign::generate(&system, &plan)?;
+    let speed = |x: &Option<[f64; 3]>| x.map(|y| format!("{:.3}", vector::norm(&y))).unwrap_or_else(|| "-".to_string());
+    println!("{:<12} {:<10} {:<24} {:>10} {:>10} {:>12} {:>8} {:>8}", "Body", "Kind", "Epoch", "v∞ in", "v∞ out", "Altitude km",
+        "Turn °", "Δv km/s");
+    for x in design.encounter.iter() {
+        println!("{:<12} {:<10} {:<24} {:>10} {:>10} {:>12.0} {:>8.2} {:>8.3}{}", x.name, x.kind.name(), cli::format_epoch(x.epoch, scale),
+            speed(&x.v_infinity_in), speed(&x.v_infinity_out), x.altitude(), x.turn_angle.to_degrees(), x.delta_v,
+            if x.is_feasible() { "" } else { "  below the surface" });
+    }
+    println!("Patched-conic total Δv {:.3} km/s", design.total_delta_v());
+    let refinement = if arguments.patched_only {
+        None
+    } else {
+        let refinement = mission::refine(&system, &design, arguments.tolerance, arguments.iterations)?;
+        for x in refinement.leg.iter() {
+            println!("Leg {} to {} corrected by {:.1} m/s in {} iterations, missing by {:.1} km", x.from, x.to, x.correction * 1000.0,
+                x.iterations, x.miss);
+        }
+        println!("Refined total Δv {:.3} km/s", refinement.total_delta_v());
+        Some(refinement)
+    };
+    if let Some(x) = &arguments.csv {
+        design.save_csv(refinement.as_ref(), x)?;
+    }
+    Ok(())
+}
+fn search_sequences(arguments: &SequenceArguments) -> Result<(), String> {
+    let mut system = arguments.source.build_system()?;
+    arguments.integration.apply(&mut system)?;
+    let scale = TimeScale::from_name(&arguments.time_scale)?;
+    let mut flyby = Vec::new();
+    for x in arguments.flyby.iter() {
+        flyby.push(system.find_body(x)?);
+    }
+    let search = Search {
+        max_flybys: arguments.depth,
+        time_of_flight: arguments.time_of_flight.clone(),
+        departure_altitude: arguments.departure_altitude,
+        arrival_altitude: arguments.arrival_altitude,
+        min_flyby_altitude: arguments.min_altitude,
+        max_launch_v_infinity: arguments.max_launch_v_infinity.unwrap_or(f64::INFINITY),
+        max_delta_v: arguments.max_delta_v.unwrap_or(f64::INFINITY),
+        max_days: arguments.max_days.unwrap_or(f64::INFINITY),
+        beam: arguments.beam,
+        ..Search::new(system.find_body(&arguments.central)?, system.find_body(&arguments.from)?,
+            system.find_body(&arguments.to)?, flyby)
+    };
+    let launch = match &arguments.launch {
+        Some(x) => cli::parse_epoch(x)?.to(TimeScale::Tdb).julian_date(),
+        None => system.epoch()
+    };
+    let launch = porkchop::sweep(launch, arguments.launch_days, arguments.every)?;
+    let found = match &arguments.trajectory {
+        Some(x) => search.run_with_history(&system, &events::History::from_trajectory(&Trajectory::load_binary(x)?, &system)?, &launch)?,
+        None => search.run(&system, &launch)?
+    };
+    if found.is_empty() {
+        println!("No sequence found");
+    }
+    for x in found.iter().take(arguments.top) {
+        let lowest = x.encounter[1..x.encounter.len() - 1].iter().map(|y| y.altitude()).fold(f64::INFINITY, f64::min);
+        println!("{:.3} km/s launching {} for {:.0} days: {}{}", x.total_delta_v(), cli::format_epoch(x.encounter[0].epoch, scale),
+            x.time_of_flight(), x.sequence(), if lowest.is_finite() { format!(", lowest flyby {:.0} km", lowest) } else { String::new() });
+    }
+    Ok(())
+}
+fn main() {
+    let arguments = Arguments::parse();
+    let result = match &arguments.command {
+        None => view(&ViewArguments {
+            trail_days: 40.0,
+            ..ViewArguments::default()
+        }),
+        Some(Command::View(x)) => view(x),
+        Some(Command::Fetch(x)) => fetch(x),
+        Some(Command::Run(x)) => process::exit(cli::execute_run(&x.source, &x.integration, &x.run)),
+        Some(Command::Export(x)) => export(x),
+        Some(Command::Compare(x)) => match compare(x) {
+            Ok(true) => Ok(()),
+            Ok(false) => process::exit(EXIT_RUN),
+            Err(y) => Err(y)
         },
-        _ => ()
+        Some(Command::Reference(x)) => reference(x),
+        Some(Command::Resonance(x)) => resonance(x),
+        Some(Command::Chaos(x)) => chaos(x),
+        Some(Command::Eclipses(x)) => eclipses(x),
+        Some(Command::Events(x)) => find_events(x),
+        Some(Command::Ephemeris(x)) => generate_ephemeris(x),
+        Some(Command::Porkchop(x)) => generate_porkchop(x),
+        Some(Command::Mission(x)) => design_mission(x),
+        Some(Command::Sequences(x)) => search_sequences(x),
+        Some(Command::Validate(x)) => match validate(x) {
+            Ok(true) => Ok(()),
+            Ok(false) => process::exit(EXIT_RUN),
+            Err(y) => Err(y)
+        }
     };
-}
\ No newline at end of file
+    if let Err(x) = result {
+        eprintln!("{}", x);
+        process::exit(EXIT_INPUT)
+    }
+}
@@ -0,0 +1,358 @@
+use clap::Args;
+use crate::{
+    runner::{
+        self,
+        CheckpointOutput,
+        DiagnosticsOutput,
+        RunConfig,
+        Stop,
+        TrajectoryOutput
+    },
+    system::{
+        checkpoint,
+        frame::Frame,
+        horizons_cgi,
+        integrator::Integrator,
+        recorder::TrajectoryFormat,
+        scenario::Scenario,
+        System
+    },
+    timescale::{
+        Epoch,
+        TimeScale
+    }
+};
+pub const EXIT_INPUT: i32 = 3;
+pub const EXIT_RUN: i32 = 4;
+#[derive(Args, Clone, Debug, Default)]
+pub struct SourceArguments {
+    #[clap(short = 'L', long, value_parser, value_name = "FILE", help = "Load a system saved as JSON")]
+    pub load: Option<String>,
+    #[clap(short = 'R', long, value_parser, value_name = "FILE", conflicts_with = "load", help = "Restore a system from a checkpoint")]
+    pub restore: Option<String>,
+    #[clap(short = 'F', long, value_parser, value_name = "EPOCH", conflicts_with_all = &["load", "restore"],
+        help = "Fetch the system from HORIZONS at this epoch, such as 1969-07-16 or \"1969-07-16 13:32 UTC\"")]
+    pub fetch: Option<String>,
+    #[clap(short, long, value_parser, value_name = "FILE", conflicts_with_all = &["load", "restore", "fetch"],
+        help = "Build the system from a TOML scenario")]
+    pub scenario: Option<String>,
+    #[clap(short = 'T', long, value_parser, value_name = "TARGET", multiple_values = true, help = "HORIZONS targets to fetch")]
+    pub targets: Vec<String>,
+    #[clap(long, value_parser, value_name = "FILE", help = "Read HORIZONS targets to fetch from a file")]
+    pub targets_file: Option<String>
+}
+impl SourceArguments {
+    pub fn is_empty(&self) -> bool {
+        self.load.is_none() && self.restore.is_none() && self.fetch.is_none() && self.scenario.is_none()
+    }
+    pub fn target_list(&self) -> Result<Vec<String>, String> {
+        let mut list = self.targets.clone();
+        if let Some(x) = &self.targets_file {
+            list.extend(horizons_cgi::read_target_list(x)?);
+        }
+        Ok(list)
+    }
+    pub fn build_system(&self) -> Result<System, String> {
+        if let Some(x) = &self.scenario {
+            return Scenario::load_toml(x)?.build()
+        }
+        if let Some(x) = &self.restore {
+            return System::load_checkpoint(x)
+        }
+        if let Some(x) = &self.load {
+            return System::load_json(x)
+        }
+        match &self.fetch {
+            Some(x) => {
+                let date = parse_epoch(x)?;
+                let list = self.target_list()?;
+                if list.is_empty() {
+                    return Err("Failed to fetch system! Pass --targets or --targets-file".to_string())
+                }
+                let system = System::fetch_from_horizons(list.as_slice(), &date)?;
+                if system.size() < list.len() {
+                    return Err(format!("Failed to fetch {} of {} targets!", list.len() - system.size(), list.len()))
+                }
+                Ok(system)
+            },
+            None => Err("Failed to build system! Pass one of --load, --restore, --fetch or --scenario".to_string())
+        }
+    }
+    pub fn run_config(&self, system: &System) -> Result<Option<RunConfig>, String> {
+        match &self.scenario {
+            Some(x) => Scenario::load_toml(x)?.run_config(system),
+            None => Ok(None)
+        }
+    }
+}
+#[derive(Args, Clone, Debug, Default)]
+pub struct IntegrationArguments {
+    #[clap(short, long, value_parser, value_name = "NAME", help = "Integrator to use: kdk, dkd or yoshida4")]
+    pub integrator: Option<String>,
+    #[clap(short, long, value_parser, value_name = "DAYS", help = "Time step in days")]
+    pub delta_time: Option<f64>
+}
+impl IntegrationArguments {
+    pub fn apply(&self, system: &mut System) -> Result<(), String> {
+        if let Some(x) = &self.integrator {
+            system.set_integrator(Integrator::from_name(x)?);
+        }
+        if let Some(x) = self.delta_time {
+            if x <= 0.0 || !x.is_finite() {
+                return Err(format!("Failed to set time step {}! It must be positive", x))
+            }
+            system.set_delta_time(x);
+        }
+        Ok(())
+    }
+}
+#[derive(Args, Clone, Debug)]
+pub struct RunArguments {
+    #[clap(long, value_parser, value_name = "EPOCH", conflicts_with = "days",
+        required_unless_present_any = &["days", "scenario"],
+        help = "Integrate until this epoch, TDB unless a UTC, TAI or TT scale follows")]
+    pub until: Option<String>,
+    #[clap(long, value_parser, value_name = "DAYS", help = "Integrate for this many days")]
+    pub days: Option<f64>,
+    #[clap(short = 'S', long, value_parser, value_name = "FILE", help = "Save the final system as JSON")]
+    pub snapshot: Option<String>,
+    #[clap(long, value_parser, value_name = "FILE", help = "Record trajectories, as CSV if the file ends in .csv")]
+    pub trajectory: Option<String>,
+    #[clap(long, value_parser, value_name = "STEPS", default_value_t = 1, help = "Steps between trajectory samples")]
+    pub cadence: usize,
+    #[clap(long, value_parser, value_name = "BODY", multiple_values = true, help = "Bodies to record, all if omitted")]
+    pub bodies: Vec<String>,
+    #[clap(long, value_parser, value_name = "FRAME",
+        help = "Record trajectories in a frame: inertial, body:BODY or synodic:PRIMARY,SECONDARY")]
+    pub frame: Option<String>,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write energy and momentum diagnostics as CSV")]
+    pub diagnostics: Option<String>,
+    #[clap(long, value_parser, value_name = "STEPS", default_value_t = 24, help = "Steps between diagnostics rows")]
+    pub diagnostics_cadence: usize,
+    #[clap(long, value_parser, value_name = "FILE", help = "Checkpoint file, {step} is replaced by the step count")]
+    pub checkpoint: Option<String>,
+    #[clap(long, value_parser, value_name = "STEPS", default_value_t = 0, help = "Steps between automatic checkpoints")]
+    pub checkpoint_every: u64,
+    #[clap(long, value_parser, value_name = "FILE", help = "Write the burns executed by spacecraft as CSV")]
+    pub burns: Option<String>,
+    #[clap(short, long, action, help = "Do not print progress")]
+    pub quiet: bool
+}
+impl RunArguments {
+    pub fn configure(&self, system: &System, scenario: Option<RunConfig>) -> Result<RunConfig, String> {
+        let stop = match (&self.until, self.days, &scenario) {
+            (Some(x), _, _) => Stop::Epoch(parse_epoch(x)?.to(TimeScale::Tdb).julian_date()),
+            (None, Some(x), _) => Stop::Duration(x),
+            (None, None, Some(x)) => x.stop,
+            (None, None, None) => return Err("Failed to find a stop condition! Pass --until or --days".to_string())
+        };
+        runner::select_bodies(system, &self.bodies)?;
+        if let Some(x) = &self.frame {
+            Frame::parse(system, x)?;
+        }
+        let mut config = match scenario {
+            Some(x) => RunConfig {
+                stop,
+                ..x
+            },
+            None => RunConfig::new(stop)
+        };
+        if self.snapshot.is_some() {
+            config.snapshot = self.snapshot.clone();
+        }
+        if let Some(x) = &self.trajectory {
+            config.trajectory = Some(TrajectoryOutput {
+                file_path: x.clone(),
+                format: TrajectoryFormat::from_path(x),
+                cadence: self.cadence,
+                bodies: self.bodies.clone(),
+                frame: self.frame.clone()
+            });
+        }
+        if let Some(x) = &self.diagnostics {
+            config.diagnostics = Some(DiagnosticsOutput {
+                file_path: x.clone(),
+                cadence: self.diagnostics_cadence
+            });
+        }
+        if let Some(x) = &self.checkpoint {
+            config.checkpoint = Some(CheckpointOutput {
+                file_path: x.clone(),
+                interval: self.checkpoint_every
+            });
+        }
+        if self.burns.is_some() {
+            config.burns = self.burns.clone();
+        }
+        config.progress = !self.quiet;
+        Ok(config)
+    }
+    pub fn execute(&self, system: &mut System, scenario: Option<RunConfig>) -> i32 {
+        let config = match self.configure(system, scenario) {
+            Ok(x) => x,
+            Err(x) => {
+                eprintln!("{}", x);
+                return EXIT_INPUT
+            }
+        };
+        if let Some(x) = system.written_by().filter(|a| !self.quiet && *a != env!("CARGO_PKG_VERSION")) {
+            println!("Restored a checkpoint written by nbodysim {} into nbodysim {}", x, env!("CARGO_PKG_VERSION"));
+        }
+        if !self.quiet {
+            println!("Integrating {} bodies from epoch {} with {} and a {} day step...",
+                system.size(), system.epoch(), system.integrator().name(), system.delta_time());
+        }
+        match runner::run(system, &config) {
+            Ok(x) => {
+                if !self.quiet {
+                    println!("Finished {} steps at epoch {} in {:.1?}, relative energy error {:e}",
+                        x.steps, x.epoch, x.wall_time, x.relative_energy_error)
+                }
+                0
+            },
+            Err(x) => {
+                eprintln!("{}", x);
+                EXIT_RUN
+            }
+        }
+    }
+}
+pub fn execute_run(source: &SourceArguments, integration: &IntegrationArguments, run: &RunArguments) -> i32 {
+    let mut system = match source.build_system() {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("{}", x);
+            return EXIT_INPUT
+        }
+    };
+    if let Err(x) = integration.apply(&mut system) {
+        eprintln!("{}", x);
+        return EXIT_INPUT
+    }
+    let scenario = match source.run_config(&system) {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("{}", x);
+            return EXIT_INPUT
+        }
+    };
+    run.execute(&mut system, scenario)
+}
+pub fn parse_epoch(date: &str) -> Result<Epoch, String> {
+    Epoch::parse(date, TimeScale::Tdb)
+}
+pub fn format_epoch(epoch: f64, scale: TimeScale) -> String {
+    Epoch::from_julian_date(epoch, TimeScale::Tdb).to(scale).to_string()
+}
+pub fn file_magic(file_path: &str) -> Result<[u8; 4], String> {
+    use std::io::Read;
+    let mut magic = [0u8; 4];
+    let mut file = match std::fs::File::open(file_path) {
+        Err(x) => return Err(format!("Failed reading {}!\n{}", file_path, x)),
+        Ok(x) => x
+    };
+    match file.read_exact(&mut magic) {
+        Err(x) if x.kind() == std::io::ErrorKind::UnexpectedEof => Ok([0u8; 4]),
+        Err(x) => Err(format!("Failed reading {}!\n{}", file_path, x)),
+        Ok(_) => Ok(magic)
+    }
+}
+pub fn load_system_file(file_path: &str) -> Result<System, String> {
+    if &file_magic(file_path)? == checkpoint::MAGIC {
+        System::load_checkpoint(file_path)
+    } else {
+        System::load_json(file_path)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use clap::Parser;
+    use crate::system::fixture::two_body_system;
+    #[derive(Parser)]
+    struct Arguments {
+        #[clap(flatten)]
+        source: SourceArguments,
+        #[clap(flatten)]
+        integration: IntegrationArguments,
+        #[clap(flatten)]
+        run: RunArguments
+    }
+    #[test]
+    fn validate_arguments() -> Result<(), String> {
+        assert!(Arguments::try_parse_from(["test", "-L", "a.json", "-R", "a.bin", "--days", "1"]).is_err());
+        assert!(Arguments::try_parse_from(["test", "-F", "1969-07-16", "-s", "a.toml", "--days", "1"]).is_err());
+        assert!(Arguments::try_parse_from(["test", "-L", "a.json"]).is_err());
+        assert!(Arguments::try_parse_from(["test", "-L", "a.json", "--until", "1970-01-01", "--days", "1"]).is_err());
+        let arguments = match Arguments::try_parse_from(
+            ["test", "-L", "a.json", "-i", "yoshida4", "-d", "0.5", "--days", "2", "--bodies", "Earth", "--quiet"])
+        {
+            Err(x) => return Err(x.to_string()),
+            Ok(x) => x
+        };
+        let mut system = two_body_system();
+        arguments.integration.apply(&mut system)?;
+        assert_eq!((system.integrator(), system.delta_time()), (Integrator::Yoshida4, 0.5));
+        let config = arguments.run.configure(&system, None)?;
+        assert_eq!(config.stop, Stop::Duration(2.0));
+        assert!(!config.progress);
+        let negative = IntegrationArguments {
+            delta_time: Some(-1.0),
+            ..arguments.integration.clone()
+        };
+        assert!(negative.apply(&mut system).is_err());
+        let unknown = IntegrationArguments {
+            integrator: Some("rk4".to_string()),
+            ..arguments.integration.clone()
+        };
+        assert!(unknown.apply(&mut system).unwrap_err().contains("kdk, dkd, yoshida4"));
+        let missing = RunArguments {
+            bodies: vec!("Venus".to_string()),
+            ..arguments.run.clone()
+        };
+        assert!(missing.configure(&system, None).unwrap_err().contains("Venus"));
+        assert!(SourceArguments::default().build_system().unwrap_err().contains("--load"));
+        let fetch = SourceArguments {
+            fetch: Some("1969-07-16".to_string()),
+            ..SourceArguments::default()
+        };
+        assert!(fetch.build_system().unwrap_err().contains("--targets"));
+        Ok(())
+    }
+    #[test]
+    fn read_targets() -> Result<(), String> {
+        fs::write("cli_targets_test.txt", "399 # Earth\n\n# the Moon\n  301\n").unwrap();
+        let source = SourceArguments {
+            targets: vec!("10".to_string()),
+            targets_file: Some("cli_targets_test.txt".to_string()),
+            ..SourceArguments::default()
+        };
+        let list = source.target_list();
+        fs::remove_file("cli_targets_test.txt").unwrap();
+        assert_eq!(list?, vec!("10", "399", "301"));
+        assert!(source.target_list().unwrap_err().contains("cli_targets_test.txt"));
+        Ok(())
+    }
+    #[test]
+    fn load_by_magic() -> Result<(), String> {
+        let system = two_body_system();
+        system.save_json("cli_magic_test.json")?;
+        system.save_checkpoint("cli_magic_test.bin")?;
+        fs::write("cli_magic_test.txt", "").unwrap();
+        let magic = [file_magic("cli_magic_test.bin"), file_magic("cli_magic_test.json"), file_magic("cli_magic_test.txt")];
+        let loaded = [load_system_file("cli_magic_test.bin"), load_system_file("cli_magic_test.json")];
+        let empty = load_system_file("cli_magic_test.txt");
+        ["cli_magic_test.json", "cli_magic_test.bin", "cli_magic_test.txt"].iter().for_each(|a| fs::remove_file(a).unwrap());
+        assert_eq!(&magic[0].clone()?, checkpoint::MAGIC);
+        assert_eq!(&magic[1].clone()?[..1], b"{");
+        assert_eq!(magic[2].clone()?, [0u8; 4]);
+        for system in loaded {
+            let system = system?;
+            assert_eq!((system.size(), system.epoch()), (2, 2440418.5));
+        }
+        assert!(empty.is_err());
+        assert!(file_magic("cli_magic_test.bin").is_err());
+        Ok(())
+    }
+}
@@ -17,10 +17,37 @@ use kiss3d::{
 use crate::{
     system::{
         body::Body,
+        frame::{
+            Frame,
+            Transform
+        },
+        lagrange::{
+            self,
+            LagrangePoints
+        },
         System,
-    }
+    },
+    units::Length
 };
 use orbital_trail::OrbitalTrail;
+fn draw_label(window: &mut Window, camera: &ArcBall, position: &Point3<f32>, text: &str) {
+    let window_size = Vector2::new(window.size()[0] as f32, window.size()[1] as f32);
+    let window_coordinate = camera.project(position, &window_size);
+    window.draw_text(
+        text,
+        &Point2::new(
+            2.0 * window_coordinate.x,
+            2.0 * (window_size.y - window_coordinate.y)
+        ),
+        24.0,
+        &Font::default(),
+        &Point3::new(
+            0.6,
+            0.6,
+            0.6
+        )
+    );
+}
 struct Graphic {
     name: String,
     model: kiss3d::scene::SceneNode,
@@ -34,7 +61,7 @@ impl Graphic {
             name: body.name().to_string(),
             model:
                 {
-                    let mut model = anchor.add_sphere((body.radius() * 6.6846E-09) as f32);
+                    let mut model = anchor.add_sphere(Length::from_km(body.radius()).au() as f32);
                     model.set_local_translation(
                         Translation3::new(
                             position[0] as f32,
@@ -57,8 +84,16 @@ impl Graphic {
                 )
         }
     }
-    pub fn update(&mut self, body: &Body) {
-        let position = body.position();
+    pub fn reset(&mut self, position: [f64; 3]) {
+        self.trail.reset(
+            Point3::new(
+                position[0] as f32,
+                position[1] as f32,
+                position[2] as f32
+            )
+        );
+    }
+    pub fn update(&mut self, position: [f64; 3]) {
         self.trail.update(
             Point3::new(
                 position[0] as f32,
@@ -71,31 +106,51 @@ impl Graphic {
         let position: Point3<f32> = self.trail.last().expect("No position found!").clone();
         self.model.set_local_transformation(Translation3::new(position.x, position.y, position.z).into());
         self.trail.draw(window, self.color);
-        let window_size = Vector2::new(window.size()[0] as f32, window.size()[1] as f32);
-        let window_coordinate = camera.project(&position, &window_size);
-        window.draw_text(
-            &self.name,
-            &Point2::new(
-                2.0 * window_coordinate.x,
-                2.0 * (window_size.y - window_coordinate.y)
-            ),
-            24.0,
-            &Font::default(),
-            &Point3::new(
-                0.6,
-                0.6,
-                0.6
-            )
-        );
+        draw_label(window, camera, &position, &self.name);
     }
     pub fn last(&self) -> Option<&Point3<f32>> {
         self.trail.last()
     }
 }
+struct LagrangeMarker {
+    primary: usize,
+    secondary: usize,
+    point: [Point3<f32>; 5]
+}
+impl LagrangeMarker {
+    pub fn new(system: &System, primary: usize, secondary: usize, transform: &Transform) -> Result<LagrangeMarker, String> {
+        let mut marker = LagrangeMarker {
+            primary,
+            secondary,
+            point: [Point3::origin(); 5]
+        };
+        marker.set(&system.lagrange_points(primary, secondary)?, transform);
+        Ok(marker)
+    }
+    fn set(&mut self, points: &LagrangePoints, transform: &Transform) {
+        self.point.iter_mut().zip(points.inertial.iter()).for_each(|(a, b)| {
+            let b = transform.position(b);
+            *a = Point3::new(b[0] as f32, b[1] as f32, b[2] as f32)
+        })
+    }
+    pub fn update(&mut self, system: &System, transform: &Transform) {
+        if let Ok(x) = system.lagrange_points(self.primary, self.secondary) {
+            self.set(&x, transform)
+        }
+    }
+    pub fn draw(&self, window: &mut Window, camera: &ArcBall) {
+        self.point.iter().zip(lagrange::NAMES.iter()).for_each(|(a, b)| {
+            window.draw_point(a, &Point3::new(1.0, 0.8, 0.2));
+            draw_label(window, camera, a, b);
+        })
+    }
+}
 pub struct Graphics {
     focus: Option<usize>,
     camera: ArcBall,
-    object: Vec<Graphic>
+    object: Vec<Graphic>,
+    lagrange: Vec<LagrangeMarker>,
+    frame: Frame
 }
 impl Graphics {
     pub fn new(system: &System, eye: Point3<f32>, at: Point3<f32>, trail_size: usize, anchor: &mut SceneNode) -> Graphics {
@@ -117,9 +172,25 @@ impl Graphics {
         Graphics {
             focus: None,
             camera: ArcBall::new_with_frustrum(std::f32::consts::PI / 4.0, 0.00001, 1024.0, eye, at),
-            object
+            object,
+            lagrange: Vec::new(),
+            frame: Frame::Inertial
         }
     }
+    pub fn set_frame(&mut self, system: &System, frame: Frame) -> Result<(), String> {
+        let transform = frame.transform(system)?;
+        self.frame = frame;
+        self.object.iter_mut().enumerate().for_each(|(i, a)| {
+            a.reset(transform.position(&system.object_from_index(i).expect("Index out of bounds!").read().unwrap().position()))
+        });
+        self.lagrange.iter_mut().for_each(|a| a.update(system, &transform));
+        Ok(())
+    }
+    pub fn show_lagrange_points(&mut self, system: &System, primary: usize, secondary: usize) -> Result<(), String> {
+        let marker = LagrangeMarker::new(system, primary, secondary, &self.frame.transform(system)?)?;
+        self.lagrange.push(marker);
+        Ok(())
+    }
     pub fn camera(&mut self) -> &mut ArcBall {
         &mut self.camera
     }
@@ -151,16 +222,20 @@ impl Graphics {
     pub fn unfocus(&mut self) {
         self.focus = None
     }
-    pub fn update(&mut self, system: &System) {
+    pub fn update(&mut self, system: &System) -> Result<(), String> {
+        let transform = self.frame.transform(system)?;
         self.object.iter_mut().enumerate().for_each(|(i, a)| {
-            a.update(&system.object_from_index(i).expect("Index out of bounds!").read().unwrap())
-        })
+            a.update(transform.position(&system.object_from_index(i).expect("Index out of bounds!").read().unwrap().position()))
+        });
+        self.lagrange.iter_mut().for_each(|a| a.update(system, &transform));
+        Ok(())
     }
     pub fn draw(&mut self, window: &mut Window) {
         let camera = &self.camera;
         self.object.iter_mut().for_each(|a| {
             a.draw(window, &camera);
         });
+        self.lagrange.iter().for_each(|a| a.draw(window, camera));
         match self.focus {
             Some(x) => {
                 let new_at = self.object[x].last().unwrap().clone();
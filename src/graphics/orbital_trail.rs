@@ -11,6 +11,9 @@ impl OrbitalTrail {
             point: vec![position; size]
         }
     }
+    pub fn reset(&mut self, position: Point3<f32>) {
+        self.point.iter_mut().for_each(|a| *a = position)
+    }
     pub fn update(&mut self, position: Point3<f32>) {
         self.point.remove(0);
         self.point.push(position)
@@ -1,2 +1,8 @@
 pub mod system;
-pub mod graphics;
\ No newline at end of file
+pub mod graphics;
+pub mod runner;
+pub mod cli;
+pub mod validation;
+pub mod units;
+pub mod timescale;
+pub mod analysis;
\ No newline at end of file
@@ -0,0 +1,223 @@
+use std::{
+    fs,
+    io::{
+        BufWriter,
+        Write
+    },
+    time::{
+        Duration,
+        Instant
+    }
+};
+use crate::system::{
+    diagnostics::Diagnostics,
+    frame::Frame,
+    recorder::{
+        TrajectoryFormat,
+        TrajectoryRecorder
+    },
+    spacecraft,
+    System
+};
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stop {
+    Epoch(f64),
+    Duration(f64)
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrajectoryOutput {
+    pub file_path: String,
+    pub format: TrajectoryFormat,
+    pub cadence: usize,
+    pub bodies: Vec<String>,
+    pub frame: Option<String>
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiagnosticsOutput {
+    pub file_path: String,
+    pub cadence: usize
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckpointOutput {
+    pub file_path: String,
+    pub interval: u64
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunConfig {
+    pub stop: Stop,
+    pub snapshot: Option<String>,
+    pub trajectory: Option<TrajectoryOutput>,
+    pub diagnostics: Option<DiagnosticsOutput>,
+    pub checkpoint: Option<CheckpointOutput>,
+    pub burns: Option<String>,
+    pub progress: bool
+}
+impl RunConfig {
+    pub fn new(stop: Stop) -> RunConfig {
+        RunConfig {
+            stop,
+            snapshot: None,
+            trajectory: None,
+            diagnostics: None,
+            checkpoint: None,
+            burns: None,
+            progress: false
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunSummary {
+    pub steps: u64,
+    pub epoch: f64,
+    pub relative_energy_error: f64,
+    pub wall_time: Duration
+}
+pub fn select_bodies(system: &System, bodies: &[String]) -> Result<Vec<usize>, String> {
+    bodies.iter().map(|a| {
+        match system.index_of(a) {
+            Some(x) => Ok(x),
+            None => Err(format!("Failed to find body {} in system!", a))
+        }
+    }).collect()
+}
+fn write_line(writer: &mut BufWriter<fs::File>, file_path: &str, line: &str) -> Result<(), String> {
+    match writeln!(writer, "{}", line) {
+        Err(x) => Err(format!("Failed writing to {}!\n{}", file_path, x)),
+        _ => Ok(())
+    }
+}
+pub fn run(system: &mut System, config: &RunConfig) -> Result<RunSummary, String> {
+    let start = system.epoch();
+    let target = match config.stop {
+        Stop::Epoch(x) => x,
+        Stop::Duration(x) => start + x
+    };
+    if target < start {
+        return Err(format!("Failed to run! Target epoch {} is before system epoch {}", target, start))
+    }
+    if let Some(x) = &config.trajectory {
+        let selection = select_bodies(system, &x.bodies)?;
+        let frame = match &x.frame {
+            Some(y) => Frame::parse(system, y)?,
+            None => Frame::Inertial
+        };
+        let recorder = TrajectoryRecorder::create(&x.file_path, x.format, system, &selection, frame, x.cadence)?;
+        system.attach_recorder(recorder);
+    }
+    let mut diagnostics = match &config.diagnostics {
+        Some(x) => {
+            if x.cadence == 0 {
+                return Err(format!("Failed to create {}!\nCadence must be at least one step", x.file_path))
+            }
+            let mut writer = match fs::File::create(&x.file_path) {
+                Err(y) => return Err(format!("Failed creating {}!\n{}", x.file_path, y)),
+                Ok(y) => BufWriter::new(y)
+            };
+            write_line(&mut writer, &x.file_path, Diagnostics::csv_header())?;
+            Some((x, writer))
+        },
+        None => None
+    };
+    if let Some(x) = &config.checkpoint {
+        system.set_autocheckpoint(&x.file_path, x.interval);
+    }
+    let initial = Diagnostics::of(system);
+    if let Some((x, writer)) = &mut diagnostics {
+        write_line(writer, &x.file_path, &initial.csv_row(&initial))?;
+    }
+    let clock = Instant::now();
+    let burned = system.burn_log().len();
+    let mut announced = burned;
+    let mut reported = 0;
+    let mut total = 0;
+    let mut written = true;
+    system.run_for(target - start, |system, step, _| {
+        total = step as u64;
+        written = false;
+        if let Some(x) = system.checkpoint_error().map(|a| a.to_string()) {
+            system.set_autocheckpoint("", 0);
+            return Err(x)
+        }
+        if let Some((x, writer)) = &mut diagnostics {
+            if step.is_multiple_of(x.cadence) {
+                write_line(writer, &x.file_path, &Diagnostics::of(system).csv_row(&initial))?;
+                written = true;
+            }
+        }
+        if config.progress {
+            system.burn_log().iter().skip(announced).for_each(|a| println!("     epoch {:.5} {}", a.epoch, a.describe()));
+            announced = system.burn_log().len();
+            let percent = (100.0 * (system.epoch() - start) / (target - start)) as usize;
+            if percent >= reported + 5 {
+                reported = percent - percent % 5;
+                println!("{:>3}% epoch {:.5} after {:.1?}", reported, system.epoch(), clock.elapsed());
+            }
+        }
+        Ok(())
+    })?;
+    system.set_autocheckpoint("", 0);
+    for recorder in system.detach_recorders() {
+        recorder.finish()?;
+    }
+    let last = Diagnostics::of(system);
+    if let Some((x, writer)) = &mut diagnostics {
+        if !written {
+            write_line(writer, &x.file_path, &last.csv_row(&initial))?;
+        }
+        if let Err(y) = writer.flush() {
+            return Err(format!("Failed writing to {}!\n{}", x.file_path, y))
+        }
+    }
+    if let Some(x) = &config.checkpoint {
+        system.save_checkpoint(&x.file_path.replace("{step}", &system.steps().to_string()))?;
+    }
+    if let Some(x) = &config.snapshot {
+        system.save_json(x)?;
+    }
+    if let Some(x) = &config.burns {
+        spacecraft::save_csv(&system.burn_log()[burned..], x)?;
+    }
+    Ok(RunSummary {
+        steps: total,
+        epoch: system.epoch(),
+        relative_energy_error: last.relative_energy_error(&initial),
+        wall_time: clock.elapsed()
+    })
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::fixture::two_body_system;
+    #[test]
+    fn run_to_epoch() -> Result<(), String> {
+        let mut system = two_body_system();
+        system.set_delta_time(0.3);
+        let mut config = RunConfig::new(Stop::Duration(1.0));
+        config.diagnostics = Some(DiagnosticsOutput {
+            file_path: "run_test_diagnostics.csv".to_string(),
+            cadence: 1
+        });
+        let summary = run(&mut system, &config);
+        let contents = fs::read_to_string("run_test_diagnostics.csv");
+        fs::remove_file("run_test_diagnostics.csv").unwrap();
+        let summary = summary?;
+        assert_eq!(summary.steps, 4);
+        assert_eq!(summary.epoch, 2440419.5);
+        assert_eq!(contents.unwrap().lines().count(), 1 + 1 + 4);
+        Ok(())
+    }
+    #[test]
+    fn stop_on_failed_checkpoint() {
+        let mut system = two_body_system();
+        system.set_delta_time(0.3);
+        let mut config = RunConfig::new(Stop::Duration(1.0));
+        config.checkpoint = Some(CheckpointOutput {
+            file_path: "missing_run_test_directory/run_{step}.bin".to_string(),
+            interval: 2
+        });
+        let error = run(&mut system, &config).unwrap_err();
+        assert!(error.contains("missing_run_test_directory/run_2.bin"));
+        assert_eq!(system.steps(), 2);
+        assert!(system.checkpoint_error().is_none());
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::{
+    Read,
+    Write
+};
+pub fn write_u32<W: Write>(writer: &mut W, value: u32) -> Result<(), String> {
+    match writer.write_all(&value.to_le_bytes()) {
+        Err(x) => Err(format!("Failed to write integer!\n{}", x)),
+        _ => Ok(())
+    }
+}
+pub fn write_f64<W: Write>(writer: &mut W, value: f64) -> Result<(), String> {
+    match writer.write_all(&value.to_le_bytes()) {
+        Err(x) => Err(format!("Failed to write float!\n{}", x)),
+        _ => Ok(())
+    }
+}
+pub fn write_str<W: Write>(writer: &mut W, value: &str) -> Result<(), String> {
+    write_u32(writer, value.len() as u32)?;
+    match writer.write_all(value.as_bytes()) {
+        Err(x) => Err(format!("Failed to write {}!\n{}", value, x)),
+        _ => Ok(())
+    }
+}
+pub fn read_u32<R: Read>(reader: &mut R) -> Result<u32, String> {
+    let mut buffer = [0u8; 4];
+    match reader.read_exact(&mut buffer) {
+        Err(x) => Err(format!("Failed to read integer!\n{}", x)),
+        _ => Ok(u32::from_le_bytes(buffer))
+    }
+}
+pub fn read_f64<R: Read>(reader: &mut R) -> Result<f64, String> {
+    let mut buffer = [0u8; 8];
+    match reader.read_exact(&mut buffer) {
+        Err(x) => Err(format!("Failed to read float!\n{}", x)),
+        _ => Ok(f64::from_le_bytes(buffer))
+    }
+}
+pub fn read_f64_or_end<R: Read>(reader: &mut R) -> Result<Option<f64>, String> {
+    let mut buffer = [0u8; 8];
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(format!("Failed to read float!\nFound only {} of 8 bytes", filled)),
+            Ok(x) => filled += x,
+            Err(x) if x.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(x) => return Err(format!("Failed to read float!\n{}", x))
+        }
+    }
+    Ok(Some(f64::from_le_bytes(buffer)))
+}
+pub fn read_str<R: Read>(reader: &mut R) -> Result<String, String> {
+    let length = read_u32(reader)? as usize;
+    let mut buffer = vec![0u8; length];
+    if let Err(x) = reader.read_exact(&mut buffer) {
+        return Err(format!("Failed to read string!\n{}", x))
+    }
+    match String::from_utf8(buffer) {
+        Err(x) => Err(format!("Failed to decode string!\n{}", x)),
+        Ok(x) => Ok(x)
+    }
+}
+pub fn write_u64<W: Write>(writer: &mut W, value: u64) -> Result<(), String> {
+    match writer.write_all(&value.to_le_bytes()) {
+        Err(x) => Err(format!("Failed to write integer!\n{}", x)),
+        _ => Ok(())
+    }
+}
+pub fn read_u64<R: Read>(reader: &mut R) -> Result<u64, String> {
+    let mut buffer = [0u8; 8];
+    match reader.read_exact(&mut buffer) {
+        Err(x) => Err(format!("Failed to read integer!\n{}", x)),
+        _ => Ok(u64::from_le_bytes(buffer))
+    }
+}
@@ -6,13 +6,31 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use crate::units::Units;
+use super::{
+    outgassing::Outgassing,
+    radiation::Radiation,
+    reference_frame::ReferenceFrame,
+    spacecraft::Spacecraft,
+    tide::Tide
+};
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Body {
     name: String,
     gravitational_mass: f64,
     radius: f64,
     position: [f64; 3],
-    velocity: [f64; 3]
+    velocity: [f64; 3],
+    #[serde(default)]
+    frame: ReferenceFrame,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spacecraft: Option<Spacecraft>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    radiation: Option<Radiation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    outgassing: Option<Outgassing>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tide: Option<Tide>
 }
 #[allow(dead_code)]
 impl Body {
@@ -28,9 +46,34 @@ impl Body {
             gravitational_mass,
             radius,
             position,
-            velocity
+            velocity,
+            frame: ReferenceFrame::default(),
+            spacecraft: None,
+            radiation: None,
+            outgassing: None,
+            tide: None
         }
     }
+    pub fn in_frame(mut self, frame: ReferenceFrame) -> Body {
+        self.frame = frame;
+        self
+    }
+    pub fn with_spacecraft(mut self, spacecraft: Spacecraft) -> Body {
+        self.spacecraft = Some(spacecraft);
+        self
+    }
+    pub fn with_radiation(mut self, radiation: Radiation) -> Body {
+        self.radiation = Some(radiation);
+        self
+    }
+    pub fn with_outgassing(mut self, outgassing: Outgassing) -> Body {
+        self.outgassing = Some(outgassing);
+        self
+    }
+    pub fn with_tide(mut self, tide: Tide) -> Body {
+        self.tide = Some(tide);
+        self
+    }
     pub fn load_json(file_path: &str) -> Result<Body, String> {
         let buffer = match fs::read_to_string(file_path) {
             Err(x) => return Err(format!("Failed reading {}!\n{}", file_path, x)),
@@ -65,6 +108,9 @@ impl Body {
     pub fn mass(&self) -> f64 {
         self.gravitational_mass
     }
+    pub fn set_mass(&mut self, gravitational_mass: f64) {
+        self.gravitational_mass = gravitational_mass
+    }
     pub fn radius(&self) -> f64 {
         self.radius
     }
@@ -74,6 +120,53 @@ impl Body {
     pub fn velocity(&self) -> [f64; 3] {
         self.velocity
     }
+    pub fn frame(&self) -> ReferenceFrame {
+        self.frame
+    }
+    pub fn spacecraft(&self) -> Option<&Spacecraft> {
+        self.spacecraft.as_ref()
+    }
+    pub fn spacecraft_mut(&mut self) -> Option<&mut Spacecraft> {
+        self.spacecraft.as_mut()
+    }
+    pub fn radiation(&self) -> Option<&Radiation> {
+        self.radiation.as_ref()
+    }
+    pub fn set_radiation(&mut self, radiation: Option<Radiation>) {
+        self.radiation = radiation
+    }
+    pub fn outgassing(&self) -> Option<&Outgassing> {
+        self.outgassing.as_ref()
+    }
+    pub fn set_outgassing(&mut self, outgassing: Option<Outgassing>) {
+        self.outgassing = outgassing
+    }
+    pub fn tide(&self) -> Option<&Tide> {
+        self.tide.as_ref()
+    }
+    pub fn tide_mut(&mut self) -> Option<&mut Tide> {
+        self.tide.as_mut()
+    }
+    pub fn set_tide(&mut self, tide: Option<Tide>) {
+        self.tide = tide
+    }
+    pub fn apply_impulse(&mut self, delta_velocity: &[f64; 3]) {
+        self.velocity.iter_mut()
+            .zip(delta_velocity.iter())
+            .for_each(|(a, b)| {*a += b})
+    }
+    pub fn convert_frame(&mut self, frame: ReferenceFrame, epoch: f64) {
+        let (position, velocity) = self.frame.convert(&frame, epoch, &self.position, &self.velocity);
+        self.position = position;
+        self.velocity = velocity;
+        self.frame = frame
+    }
+    pub fn convert_units(&mut self, from: &Units, to: &Units) {
+        self.gravitational_mass = from.gravitational_mass(self.gravitational_mass).in_unit(to.gravitational_mass);
+        self.radius = from.radius(self.radius).in_unit(to.radius);
+        self.position = from.position(&self.position).map(|a| a.in_unit(to.length));
+        self.velocity = from.velocity(&self.velocity).map(|a| a.in_unit(to.length, to.time))
+    }
     pub fn apply_acceleration(&mut self, delta_acceleration: Vec<f64>, delta_time: f64) {
         self.velocity.iter_mut()
             .zip(delta_acceleration.iter())
@@ -0,0 +1,416 @@
+use std::{
+    fs,
+    io::{
+        BufReader,
+        BufWriter,
+        Read,
+        Write
+    }
+};
+use super::{
+    binary,
+    body::Body,
+    integrator::Integrator,
+    outgassing::{
+        Outgassing,
+        Sublimation
+    },
+    radiation::Radiation,
+    reference_frame::ReferenceFrame,
+    spacecraft::{
+        BurnRecord,
+        Maneuver,
+        ManeuverFrame,
+        Spacecraft,
+        Steering,
+        Thruster
+    },
+    tide::Tide,
+    System
+};
+pub const MAGIC: &[u8; 4] = b"NBCK";
+pub const VERSION: u32 = 1;
+fn write_f64s<W: Write>(writer: &mut W, value: &[f64]) -> Result<(), String> {
+    value.iter().try_for_each(|a| binary::write_f64(writer, *a))
+}
+fn read_f64s<R: Read, const N: usize>(reader: &mut R) -> Result<[f64; N], String> {
+    let mut value = [0.0; N];
+    for a in value.iter_mut() {
+        *a = binary::read_f64(reader)?;
+    }
+    Ok(value)
+}
+fn read_flag<R: Read>(reader: &mut R) -> Result<bool, String> {
+    match binary::read_u32(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        x => Err(format!("Failed to read flag!\nExpected 0 or 1 but found {}", x))
+    }
+}
+fn write_spacecraft<W: Write>(writer: &mut W, spacecraft: &Spacecraft) -> Result<(), String> {
+    write_f64s(writer, &[spacecraft.dry_mass, spacecraft.fuel_mass, spacecraft.specific_impulse])?;
+    binary::write_u64(writer, spacecraft.executed as u64)?;
+    binary::write_u32(writer, spacecraft.maneuver.len() as u32)?;
+    for maneuver in spacecraft.maneuver.iter() {
+        binary::write_f64(writer, maneuver.epoch)?;
+        write_f64s(writer, &maneuver.delta_v)?;
+        binary::write_str(writer, maneuver.frame.name())?;
+        binary::write_str(writer, maneuver.central.as_deref().unwrap_or(""))?;
+    }
+    binary::write_u32(writer, spacecraft.thruster.is_some() as u32)?;
+    if let Some(x) = &spacecraft.thruster {
+        write_f64s(writer, &[x.thrust, x.specific_impulse])?;
+        binary::write_str(writer, x.steering.name())?;
+        match &x.steering {
+            Steering::Tangential(y) | Steering::AntiSun(y) => binary::write_str(writer, y)?,
+            Steering::Inertial(y) => write_f64s(writer, y)?
+        }
+    }
+    Ok(())
+}
+fn read_spacecraft<R: Read>(reader: &mut R) -> Result<Spacecraft, String> {
+    let [dry_mass, fuel_mass, specific_impulse] = read_f64s(reader)?;
+    let mut spacecraft = Spacecraft::new(dry_mass, fuel_mass, specific_impulse);
+    spacecraft.executed = binary::read_u64(reader)? as usize;
+    for _ in 0..binary::read_u32(reader)? {
+        let epoch = binary::read_f64(reader)?;
+        let delta_v = read_f64s(reader)?;
+        let frame = ManeuverFrame::from_name(&binary::read_str(reader)?)?;
+        let central = Some(binary::read_str(reader)?).filter(|a| !a.is_empty());
+        spacecraft.maneuver.push(Maneuver::new(epoch, delta_v, frame, central));
+    }
+    if read_flag(reader)? {
+        let [thrust, specific_impulse] = read_f64s(reader)?;
+        let steering = match binary::read_str(reader)?.as_str() {
+            "inertial" => Steering::new("inertial", None, Some(read_f64s(reader)?))?,
+            x => Steering::new(x, Some(&binary::read_str(reader)?), None)?
+        };
+        spacecraft.thruster = Some(Thruster::new(thrust, specific_impulse, steering));
+    }
+    Ok(spacecraft)
+}
+fn write_outgassing<W: Write>(writer: &mut W, outgassing: &Outgassing) -> Result<(), String> {
+    let law = &outgassing.sublimation;
+    write_f64s(writer, &[outgassing.a1, outgassing.a2, outgassing.a3, outgassing.delay])?;
+    write_f64s(writer, &[law.alpha, law.r0, law.m, law.n, law.k])
+}
+fn read_outgassing<R: Read>(reader: &mut R) -> Result<Outgassing, String> {
+    let [a1, a2, a3, delay] = read_f64s(reader)?;
+    let [alpha, r0, m, n, k] = read_f64s(reader)?;
+    Ok(Outgassing::new(a1, a2, a3).with_delay(delay).with_sublimation(Sublimation {
+        alpha,
+        r0,
+        m,
+        n,
+        k
+    }))
+}
+fn write_tide<W: Write>(writer: &mut W, tide: &Tide) -> Result<(), String> {
+    write_f64s(writer, &[tide.love_number, tide.time_lag, tide.moment_of_inertia])?;
+    write_f64s(writer, &tide.spin)
+}
+fn read_tide<R: Read>(reader: &mut R) -> Result<Tide, String> {
+    let [love_number, time_lag, moment_of_inertia] = read_f64s(reader)?;
+    Ok(Tide::new(love_number, time_lag, moment_of_inertia, read_f64s(reader)?))
+}
+pub fn save(system: &System, file_path: &str) -> Result<(), String> {
+    let frame = system.frame()?;
+    if !frame.is_inertial() {
+        return Err(format!("Error saving {}!\nBodies are in a rotating {} frame", file_path, frame.name()))
+    }
+    let mut writer = match fs::File::create(file_path) {
+        Err(x) => return Err(format!("Error creating {}!\n{}", file_path, x)),
+        Ok(x) => BufWriter::new(x)
+    };
+    if let Err(x) = writer.write_all(MAGIC) {
+        return Err(format!("Error writing to {}!\n{}", file_path, x))
+    }
+    binary::write_u32(&mut writer, VERSION)?;
+    binary::write_str(&mut writer, env!("CARGO_PKG_VERSION"))?;
+    binary::write_f64(&mut writer, system.epoch)?;
+    binary::write_f64(&mut writer, system.elapsed)?;
+    binary::write_u64(&mut writer, system.steps)?;
+    binary::write_f64(&mut writer, system.delta_time)?;
+    binary::write_str(&mut writer, system.integrator.name())?;
+    binary::write_str(&mut writer, frame.name())?;
+    binary::write_str(&mut writer, system.luminous())?;
+    binary::write_f64(&mut writer, system.luminosity())?;
+    binary::write_u32(&mut writer, system.size() as u32)?;
+    for object in system.object.iter() {
+        let body = object.read().unwrap();
+        binary::write_str(&mut writer, body.name())?;
+        binary::write_f64(&mut writer, body.mass())?;
+        binary::write_f64(&mut writer, body.radius())?;
+        write_f64s(&mut writer, &body.position())?;
+        write_f64s(&mut writer, &body.velocity())?;
+        binary::write_u32(&mut writer, body.spacecraft().is_some() as u32)?;
+        if let Some(x) = body.spacecraft() {
+            write_spacecraft(&mut writer, x)?;
+        }
+        binary::write_u32(&mut writer, body.radiation().is_some() as u32)?;
+        if let Some(x) = body.radiation() {
+            write_f64s(&mut writer, &[x.area_to_mass, x.reflectivity, x.yarkovsky])?;
+        }
+        binary::write_u32(&mut writer, body.outgassing().is_some() as u32)?;
+        if let Some(x) = body.outgassing() {
+            write_outgassing(&mut writer, x)?;
+        }
+        binary::write_u32(&mut writer, body.tide().is_some() as u32)?;
+        if let Some(x) = body.tide() {
+            write_tide(&mut writer, x)?;
+        }
+    }
+    binary::write_u32(&mut writer, system.burn.len() as u32)?;
+    for burn in system.burn.iter() {
+        binary::write_f64(&mut writer, burn.epoch)?;
+        binary::write_str(&mut writer, &burn.body)?;
+        binary::write_str(&mut writer, burn.frame.name())?;
+        write_f64s(&mut writer, &burn.delta_v)?;
+        write_f64s(&mut writer, &[burn.requested, burn.achieved, burn.fuel_used, burn.fuel_remaining])?;
+        binary::write_str(&mut writer, burn.skipped.as_deref().unwrap_or(""))?;
+    }
+    match writer.flush() {
+        Err(x) => Err(format!("Error writing to {}!\n{}", file_path, x)),
+        _ => Ok(())
+    }
+}
+pub fn restore(file_path: &str) -> Result<System, String> {
+    let mut reader = match fs::File::open(file_path) {
+        Err(x) => return Err(format!("Error reading {}!\n{}", file_path, x)),
+        Ok(x) => BufReader::new(x)
+    };
+    let mut magic = [0u8; 4];
+    if let Err(x) = reader.read_exact(&mut magic) {
+        return Err(format!("Error reading {}!\n{}", file_path, x))
+    }
+    if &magic != MAGIC {
+        return Err(format!("Error reading {}!\nNot a checkpoint file", file_path))
+    }
+    let version = binary::read_u32(&mut reader)?;
+    if version != VERSION {
+        return Err(format!("Error reading {}!\nCheckpoint version {} is not supported by this build (version {})",
+            file_path, version, VERSION))
+    }
+    let package_version = binary::read_str(&mut reader)?;
+    let mut system = System::new();
+    system.written_by = Some(package_version);
+    system.epoch = binary::read_f64(&mut reader)?;
+    system.elapsed = binary::read_f64(&mut reader)?;
+    system.steps = binary::read_u64(&mut reader)?;
+    system.delta_time = binary::read_f64(&mut reader)?;
+    system.integrator = Integrator::from_name(&binary::read_str(&mut reader)?)?;
+    let frame = ReferenceFrame::from_name(&binary::read_str(&mut reader)?)?;
+    let luminous = binary::read_str(&mut reader)?;
+    let luminosity = binary::read_f64(&mut reader)?;
+    system.set_luminous(&luminous, luminosity);
+    let count = binary::read_u32(&mut reader)?;
+    for _ in 0..count {
+        let name = binary::read_str(&mut reader)?;
+        let gravitational_mass = binary::read_f64(&mut reader)?;
+        let radius = binary::read_f64(&mut reader)?;
+        let mut body = Body::new(
+            name,
+            gravitational_mass,
+            radius,
+            read_f64s(&mut reader)?,
+            read_f64s(&mut reader)?
+        ).in_frame(frame);
+        if read_flag(&mut reader)? {
+            body = body.with_spacecraft(read_spacecraft(&mut reader)?);
+        }
+        if read_flag(&mut reader)? {
+            let [area_to_mass, reflectivity, yarkovsky] = read_f64s(&mut reader)?;
+            body = body.with_radiation(Radiation::new(area_to_mass, reflectivity, yarkovsky));
+        }
+        if read_flag(&mut reader)? {
+            body = body.with_outgassing(read_outgassing(&mut reader)?);
+        }
+        if read_flag(&mut reader)? {
+            body = body.with_tide(read_tide(&mut reader)?);
+        }
+        system.add(body);
+    }
+    let count = binary::read_u32(&mut reader)?;
+    for _ in 0..count {
+        let epoch = binary::read_f64(&mut reader)?;
+        let body = binary::read_str(&mut reader)?;
+        let frame = ManeuverFrame::from_name(&binary::read_str(&mut reader)?)?;
+        let delta_v = read_f64s(&mut reader)?;
+        let [requested, achieved, fuel_used, fuel_remaining] = read_f64s(&mut reader)?;
+        system.burn.push(BurnRecord {
+            epoch,
+            body,
+            frame,
+            delta_v,
+            requested,
+            achieved,
+            fuel_used,
+            fuel_remaining,
+            skipped: Some(binary::read_str(&mut reader)?).filter(|a| !a.is_empty())
+        });
+    }
+    match system.check_spacecraft().and_then(|_| system.check_sunlight()).and_then(|_| system.check_tides()) {
+        Err(x) => Err(format!("Error reading {}!\n{}", file_path, x)),
+        _ => Ok(system)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::fixture::three_body_system;
+    #[test]
+    fn continue_bit_for_bit() -> Result<(), String> {
+        let mut control = three_body_system();
+        (0..40).for_each(|_| control.step());
+        let mut test = three_body_system();
+        (0..20).for_each(|_| test.step());
+        test.save_checkpoint("checkpoint_test.bin")?;
+        let restored = System::load_checkpoint("checkpoint_test.bin");
+        fs::remove_file("checkpoint_test.bin").unwrap();
+        let mut test = restored?;
+        assert_eq!(test.written_by(), Some(env!("CARGO_PKG_VERSION")));
+        (0..20).for_each(|_| test.step());
+        assert_eq!(control.epoch(), test.epoch());
+        assert_eq!(control.elapsed(), test.elapsed());
+        assert_eq!(control.steps(), test.steps());
+        (0..control.size()).for_each(|i| {
+            let a = control.object_from_index(i).unwrap();
+            let b = test.object_from_index(i).unwrap();
+            assert_eq!(*a.read().unwrap(), *b.read().unwrap());
+        });
+        Ok(())
+    }
+    #[test]
+    fn continue_maneuver_plan() -> Result<(), String> {
+        let spacecraft = || {
+            let mut spacecraft = Spacecraft::new(1000.0, 200.0, 320.0);
+            spacecraft.plan(Maneuver::new(2440419.25, [0.0, 0.0, 0.1], ManeuverFrame::Inertial, None));
+            spacecraft.plan(Maneuver::new(2440419.9, [0.5, 0.0, 0.0], ManeuverFrame::Vnb, Some("Earth".to_string())));
+            Body::new("Probe".to_string(), 0.0, 0.0, [0.42, -0.93, 0.0], [0.0155, 0.0069, 0.0]).with_spacecraft(spacecraft)
+        };
+        let mut control = three_body_system();
+        control.add(spacecraft());
+        (0..40).for_each(|_| control.step());
+        let mut test = three_body_system();
+        test.add(spacecraft());
+        (0..20).for_each(|_| test.step());
+        test.save_checkpoint("checkpoint_maneuver_test.bin")?;
+        let restored = System::load_checkpoint("checkpoint_maneuver_test.bin");
+        fs::remove_file("checkpoint_maneuver_test.bin").unwrap();
+        let mut test = restored?;
+        assert_eq!(test.object_from_index(3).unwrap().read().unwrap().spacecraft().unwrap().executed, 1);
+        (0..20).for_each(|_| test.step());
+        assert_eq!(control.burn_log().len(), 2);
+        assert_eq!(control.burn_log(), test.burn_log());
+        assert_eq!(*control.object_from_index(3).unwrap().read().unwrap(), *test.object_from_index(3).unwrap().read().unwrap());
+        Ok(())
+    }
+    #[test]
+    fn continue_sunlight_forces() -> Result<(), String> {
+        let asteroid = || Body::new("Asteroid".to_string(), 0.0, 0.2, [0.0, 1.1, 0.0], [-0.0165, 0.0, 0.0])
+            .with_radiation(Radiation::new(0.5, 1.3, 1.0E-12))
+            .with_outgassing(Outgassing::new(1.0E-9, -2.0E-10, 1.0E-11));
+        let mut control = three_body_system();
+        control.set_luminous("Sun", 1.2);
+        control.add(asteroid());
+        (0..40).for_each(|_| control.step());
+        let mut test = three_body_system();
+        test.set_luminous("Sun", 1.2);
+        test.add(asteroid());
+        (0..20).for_each(|_| test.step());
+        test.save_checkpoint("checkpoint_sunlight_test.bin")?;
+        let restored = System::load_checkpoint("checkpoint_sunlight_test.bin");
+        fs::remove_file("checkpoint_sunlight_test.bin").unwrap();
+        let mut test = restored?;
+        assert_eq!((test.luminous(), test.luminosity()), ("Sun", 1.2));
+        (0..20).for_each(|_| test.step());
+        assert_eq!(*control.object_from_index(3).unwrap().read().unwrap(), *test.object_from_index(3).unwrap().read().unwrap());
+        Ok(())
+    }
+    #[test]
+    fn continue_tidal_spin() -> Result<(), String> {
+        let tidal_system = || {
+            let system = three_body_system();
+            let earth = system.object_from_index(1).unwrap();
+            earth.write().unwrap().set_tide(Some(Tide::new(0.3, 600.0, 0.33, [0.0, 0.0, 6.3])));
+            system
+        };
+        let mut control = tidal_system();
+        (0..40).for_each(|_| control.step());
+        let mut test = tidal_system();
+        (0..20).for_each(|_| test.step());
+        test.save_checkpoint("checkpoint_tide_test.bin")?;
+        let restored = System::load_checkpoint("checkpoint_tide_test.bin");
+        fs::remove_file("checkpoint_tide_test.bin").unwrap();
+        let mut test = restored?;
+        (0..20).for_each(|_| test.step());
+        (0..control.size()).for_each(|i| {
+            let a = control.object_from_index(i).unwrap();
+            let b = test.object_from_index(i).unwrap();
+            assert_eq!(*a.read().unwrap(), *b.read().unwrap());
+        });
+        Ok(())
+    }
+    #[test]
+    fn restore_frame() -> Result<(), String> {
+        let mut system = three_body_system();
+        system.convert_frame(ReferenceFrame::Icrf)?;
+        system.save_checkpoint("checkpoint_frame_test.bin")?;
+        let restored = System::load_checkpoint("checkpoint_frame_test.bin");
+        fs::remove_file("checkpoint_frame_test.bin").unwrap();
+        let restored = restored?;
+        assert_eq!(restored.frame(), Ok(ReferenceFrame::Icrf));
+        assert_eq!(*restored.object_from_index(1).unwrap().read().unwrap(),
+            *system.object_from_index(1).unwrap().read().unwrap());
+        Ok(())
+    }
+    #[test]
+    fn restore_every_field_exactly() -> Result<(), String> {
+        let mut spacecraft = Spacecraft::new(1000.0 / 3.0, 0.1 + 0.2, 320.0 / 7.0)
+            .with_thruster(Thruster::new(0.1 / 3.0, 2000.0 / 3.0, Steering::AntiSun("Sun".to_string())));
+        spacecraft.plan(Maneuver::new(2440419.25 + 1.0 / 3.0, [0.1, 0.2, 0.3], ManeuverFrame::Inertial, None));
+        spacecraft.plan(Maneuver::new(2440419.9, [0.5 / 3.0, 0.0, 1.0E-17], ManeuverFrame::Rtn, Some("Earth".to_string())));
+        let probe = Body::new("Probe".to_string(), 0.0, 0.0, [0.42, -0.93, 0.0], [0.0155, 0.0069, 0.0])
+            .with_spacecraft(spacecraft)
+            .with_radiation(Radiation::new(0.5 / 3.0, 1.3, 1.0E-12 / 7.0));
+        let comet = Body::new("Comet".to_string(), 1.0E-9 / 3.0, 2.0 / 3.0, [2.1, 0.3, 0.1], [-0.002, 0.011, 0.0])
+            .with_outgassing(Outgassing::new(1.0E-9 / 3.0, -2.0E-10 / 7.0, 1.0E-11 / 9.0).with_delay(35.2 / 3.0)
+                .with_sublimation(Sublimation {
+                    alpha: 0.04 / 3.0,
+                    r0: 10.0 / 3.0,
+                    m: 2.0,
+                    n: 3.0,
+                    k: 2.6 / 3.0
+                }));
+        let mut system = three_body_system();
+        system.object_from_index(1).unwrap().write().unwrap().set_tide(Some(Tide::new(0.3 / 7.0, 600.0 / 7.0, 0.33, [0.1 / 3.0, 0.2 / 3.0, 6.3 / 7.0])));
+        system.add(probe);
+        system.add(comet);
+        system.set_luminous("Sun", 1.0 / 3.0);
+        system.save_checkpoint("checkpoint_field_test.bin")?;
+        let restored = System::load_checkpoint("checkpoint_field_test.bin");
+        fs::remove_file("checkpoint_field_test.bin").unwrap();
+        let restored = restored?;
+        assert_eq!(restored.luminosity(), 1.0 / 3.0);
+        (0..system.size()).for_each(|i| {
+            let a = system.object_from_index(i).unwrap();
+            let b = restored.object_from_index(i).unwrap();
+            assert_eq!(*a.read().unwrap(), *b.read().unwrap());
+        });
+        Ok(())
+    }
+    #[test]
+    fn reject_newer_version() -> Result<(), String> {
+        let mut file = match fs::File::create("checkpoint_version_test.bin") {
+            Err(x) => return Err(format!("Error creating checkpoint_version_test.bin!\n{}", x)),
+            Ok(x) => x
+        };
+        file.write_all(MAGIC).unwrap();
+        binary::write_u32(&mut file, VERSION + 1)?;
+        drop(file);
+        let restored = System::load_checkpoint("checkpoint_version_test.bin");
+        fs::remove_file("checkpoint_version_test.bin").unwrap();
+        assert!(restored.is_err());
+        Ok(())
+    }
+}
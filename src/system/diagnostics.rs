@@ -0,0 +1,81 @@
+use crate::units::GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+use super::System;
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostics {
+    pub epoch: f64,
+    pub energy: f64,
+    pub momentum: [f64; 3],
+    pub angular_momentum: [f64; 3]
+}
+impl Diagnostics {
+    pub fn of(system: &System) -> Diagnostics {
+        let state: Vec<(f64, [f64; 3], [f64; 3])> = system.object.iter().map(|a| {
+            let a_lock = a.read().unwrap();
+            (a_lock.mass() * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2, a_lock.position(), a_lock.velocity())
+        }).collect();
+        let mut energy = 0.0;
+        let mut momentum = [0.0; 3];
+        let mut angular_momentum = [0.0; 3];
+        state.iter().enumerate().for_each(|(i, (mass, position, velocity))| {
+            energy += 0.5 * mass * velocity.iter().map(|a| {a.powf(2.0)}).sum::<f64>();
+            state.iter().skip(i + 1).for_each(|(other_mass, other_position, _)| {
+                let distance = position.iter()
+                    .zip(other_position.iter())
+                    .map(|(s, o)| {(s - o).powf(2.0)}).sum::<f64>().sqrt();
+                energy -= mass * other_mass / distance;
+            });
+            momentum.iter_mut()
+                .zip(velocity.iter())
+                .for_each(|(a, v)| {*a += mass * v});
+            angular_momentum[0] += mass * (position[1] * velocity[2] - position[2] * velocity[1]);
+            angular_momentum[1] += mass * (position[2] * velocity[0] - position[0] * velocity[2]);
+            angular_momentum[2] += mass * (position[0] * velocity[1] - position[1] * velocity[0]);
+        });
+        Diagnostics {
+            epoch: system.epoch(),
+            energy,
+            momentum,
+            angular_momentum
+        }
+    }
+    pub fn relative_energy_error(&self, initial: &Diagnostics) -> f64 {
+        ((self.energy - initial.energy) / initial.energy).abs()
+    }
+    pub fn csv_header() -> &'static str {
+        "time,energy,relative_energy_error,px,py,pz,lx,ly,lz"
+    }
+    pub fn csv_row(&self, initial: &Diagnostics) -> String {
+        format!("{},{},{},{},{},{},{},{},{}",
+            self.epoch,
+            self.energy,
+            self.relative_energy_error(initial),
+            self.momentum[0], self.momentum[1], self.momentum[2],
+            self.angular_momentum[0], self.angular_momentum[1], self.angular_momentum[2]
+        )
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{
+        fixture::two_body_system,
+        integrator::Integrator
+    };
+    #[test]
+    fn conserve_energy() -> Result<(), String> {
+        Integrator::all().into_iter().for_each(|integrator| {
+            let mut system = two_body_system();
+            system.set_integrator(integrator);
+            system.set_delta_time(1.0);
+            let initial = Diagnostics::of(&system);
+            (0..365).for_each(|_| system.step());
+            let last = Diagnostics::of(&system);
+            println!("{} relative energy error {}", integrator.name(), last.relative_energy_error(&initial));
+            assert!(last.relative_energy_error(&initial) < 1.0E-5);
+            last.momentum.iter()
+                .zip(initial.momentum.iter())
+                .for_each(|(a, b)| {assert!((a - b).abs() < 1.0E-18)});
+        });
+        Ok(())
+    }
+}
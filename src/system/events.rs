@@ -0,0 +1,460 @@
+use std::{
+    collections::VecDeque,
+    fmt
+};
+use crate::units::AU_PER_LIGHT_DAY;
+use super::{
+    frame::Frame,
+    recorder::{
+        Trajectory,
+        UNITS
+    },
+    text::{
+        csv_field,
+        write_text
+    },
+    vector,
+    System
+};
+#[derive(Clone, Debug, PartialEq)]
+pub struct State {
+    pub epoch: f64,
+    pub position: Vec<[f64; 3]>,
+    pub velocity: Vec<[f64; 3]>
+}
+impl State {
+    pub fn capture(system: &System) -> State {
+        let (position, velocity) = (0..system.size()).map(|a| {
+            let body = system.object_from_index(a).unwrap();
+            let body = body.read().unwrap();
+            (body.position(), body.velocity())
+        }).unzip();
+        State {
+            epoch: system.epoch(),
+            position,
+            velocity
+        }
+    }
+    fn weights(&self, next: &State, epoch: f64) -> ([f64; 4], [f64; 4]) {
+        let span = next.epoch - self.epoch;
+        let s = (epoch - self.epoch) / span;
+        let (s2, s3) = (s * s, s * s * s);
+        (
+            [2.0 * s3 - 3.0 * s2 + 1.0, (s3 - 2.0 * s2 + s) * span, -2.0 * s3 + 3.0 * s2, (s3 - s2) * span],
+            [(6.0 * s2 - 6.0 * s) / span, 3.0 * s2 - 4.0 * s + 1.0, (6.0 * s - 6.0 * s2) / span, 3.0 * s2 - 2.0 * s]
+        )
+    }
+    fn combine(&self, next: &State, body: usize, weight: &[f64; 4]) -> [f64; 3] {
+        let mut result = [0.0; 3];
+        for (i, a) in result.iter_mut().enumerate() {
+            *a = weight[0] * self.position[body][i] + weight[1] * self.velocity[body][i] +
+                weight[2] * next.position[body][i] + weight[3] * next.velocity[body][i];
+        }
+        result
+    }
+    pub fn position_at(&self, next: &State, body: usize, epoch: f64) -> [f64; 3] {
+        self.combine(next, body, &self.weights(next, epoch).0)
+    }
+    pub fn interpolate(&self, next: &State, epoch: f64) -> State {
+        let (position_weight, velocity_weight) = self.weights(next, epoch);
+        State {
+            epoch,
+            position: (0..self.position.len()).map(|a| self.combine(next, a, &position_weight)).collect(),
+            velocity: (0..self.velocity.len()).map(|a| self.combine(next, a, &velocity_weight)).collect()
+        }
+    }
+    pub fn relative(&self, body: usize, central: usize) -> ([f64; 3], [f64; 3]) {
+        (
+            vector::subtract(&self.position[body], &self.position[central]),
+            vector::subtract(&self.velocity[body], &self.velocity[central])
+        )
+    }
+}
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    state: VecDeque<State>
+}
+impl History {
+    pub fn new() -> History {
+        History {
+            state: VecDeque::new()
+        }
+    }
+    pub fn propagate(system: &System, start: f64, stop: f64) -> History {
+        let mut past = system.snapshot();
+        past.set_delta_time(-system.delta_time().abs());
+        let mut earlier = Vec::new();
+        while past.epoch() > start {
+            past.step();
+            earlier.push(State::capture(&past));
+        }
+        let mut history = History::new();
+        earlier.into_iter().rev().for_each(|a| history.push(a));
+        let mut future = system.snapshot();
+        future.set_delta_time(system.delta_time().abs());
+        history.push(State::capture(&future));
+        while future.epoch() < stop {
+            future.step();
+            history.push(State::capture(&future));
+        }
+        history
+    }
+    pub fn from_trajectory(trajectory: &Trajectory, system: &System) -> Result<History, String> {
+        if trajectory.frame != Frame::Inertial.describe(system) || trajectory.units != UNITS {
+            return Err(format!("Failed to read history from trajectory! It is {} in {} rather than inertial in {}",
+                trajectory.frame, trajectory.units, UNITS))
+        }
+        if trajectory.sample.len() < 2 {
+            return Err("Failed to read history from trajectory! It needs at least two samples".to_string())
+        }
+        let mut column = Vec::new();
+        for i in 0..system.size() {
+            let name = system.object_from_index(i).unwrap().read().unwrap().name().to_string();
+            match trajectory.name.iter().position(|a| *a == name) {
+                Some(x) => column.push(x),
+                None => return Err(format!("Failed to read history from trajectory! {} was not recorded", name))
+            }
+        }
+        let mut history = History::new();
+        for sample in trajectory.sample.iter() {
+            history.push(State {
+                epoch: sample.time,
+                position: column.iter().map(|a| sample.position[*a]).collect(),
+                velocity: column.iter().map(|a| sample.velocity[*a]).collect()
+            });
+        }
+        Ok(history)
+    }
+    pub fn push(&mut self, state: State) {
+        self.state.push_back(state)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.state.is_empty()
+    }
+    pub fn latest(&self) -> Option<&State> {
+        self.state.back()
+    }
+    pub fn forget_before(&mut self, epoch: f64) {
+        while self.state.len() > 2 && self.state[1].epoch < epoch {
+            self.state.pop_front();
+        }
+    }
+    pub fn state_of(&self, body: usize, epoch: f64) -> ([f64; 3], [f64; 3]) {
+        let index = self.state.partition_point(|a| a.epoch <= epoch);
+        if index == 0 || index == self.state.len() {
+            let state = if index == 0 { &self.state[0] } else { &self.state[index - 1] };
+            return (vector::add(&state.position[body], &vector::scale(&state.velocity[body], epoch - state.epoch)),
+                state.velocity[body])
+        }
+        let (first, next) = (&self.state[index - 1], &self.state[index]);
+        let (position_weight, velocity_weight) = first.weights(next, epoch);
+        (first.combine(next, body, &position_weight), first.combine(next, body, &velocity_weight))
+    }
+    pub fn position(&self, body: usize, epoch: f64) -> [f64; 3] {
+        self.state_of(body, epoch).0
+    }
+    pub fn light_time_state(&self, body: usize, viewpoint: &[f64; 3], epoch: f64) -> ([f64; 3], [f64; 3], f64) {
+        let mut delay = 0.0;
+        let mut state = self.state_of(body, epoch);
+        for _ in 0..4 {
+            delay = vector::distance(&state.0, viewpoint) / AU_PER_LIGHT_DAY;
+            state = self.state_of(body, epoch - delay);
+        }
+        (state.0, state.1, delay)
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Rising,
+    Falling,
+    Either
+}
+impl Direction {
+    fn accepts(&self, rising: bool) -> bool {
+        match self {
+            Direction::Rising => rising,
+            Direction::Falling => !rising,
+            Direction::Either => true
+        }
+    }
+}
+type Function = Box<dyn Fn(&State) -> f64 + Send + Sync>;
+type Classifier = Box<dyn Fn(&State, bool) -> String + Send + Sync>;
+pub struct EventFunction {
+    pub name: String,
+    pub direction: Direction,
+    function: Function,
+    classify: Classifier
+}
+impl fmt::Debug for EventFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventFunction").field("name", &self.name).field("direction", &self.direction).finish()
+    }
+}
+impl EventFunction {
+    pub fn new<F>(name: &str, direction: Direction, function: F) -> EventFunction
+    where F: Fn(&State) -> f64 + Send + Sync + 'static {
+        let label = name.to_string();
+        EventFunction {
+            name: name.to_string(),
+            direction,
+            function: Box::new(function),
+            classify: Box::new(move |_, rising| format!("{} {}", label, if rising { "rising" } else { "falling" }))
+        }
+    }
+    pub fn with_labels(self, rising: &str, falling: &str) -> EventFunction {
+        let (rising, falling) = (rising.to_string(), falling.to_string());
+        self.with_classifier(move |_, a| if a { rising.clone() } else { falling.clone() })
+    }
+    pub fn with_classifier<F>(mut self, classify: F) -> EventFunction
+    where F: Fn(&State, bool) -> String + Send + Sync + 'static {
+        self.classify = Box::new(classify);
+        self
+    }
+    pub fn value(&self, state: &State) -> f64 {
+        (self.function)(state)
+    }
+}
+fn name(system: &System, index: usize) -> String {
+    system.object_from_index(index).unwrap().read().unwrap().name().to_string()
+}
+fn check(system: &System, bodies: &[usize]) -> Result<(), String> {
+    match bodies.iter().find(|a| **a >= system.size()) {
+        Some(x) => Err(format!("Failed to find body {} in system!", x)),
+        None if bodies.iter().enumerate().any(|(i, a)| bodies[..i].contains(a)) =>
+            Err(format!("Failed to build event! Bodies {:?} must differ", bodies)),
+        None => Ok(())
+    }
+}
+pub fn apsides(system: &System, body: usize, central: usize) -> Result<EventFunction, String> {
+    check(system, &[body, central])?;
+    let (body_name, central_name) = (name(system, body), name(system, central));
+    Ok(EventFunction::new(&format!("apsides of {}", body_name), Direction::Either, move |a| {
+        let (position, velocity) = a.relative(body, central);
+        vector::dot(&position, &velocity)
+    }).with_classifier(move |a, rising| {
+        format!("{} of {} around {} at {:.6} AU", if rising { "Periapsis" } else { "Apoapsis" },
+            body_name, central_name, vector::norm(&a.relative(body, central).0))
+    }))
+}
+pub fn nodes(system: &System, body: usize, central: usize) -> Result<EventFunction, String> {
+    check(system, &[body, central])?;
+    let (body_name, central_name) = (name(system, body), name(system, central));
+    Ok(EventFunction::new(&format!("nodes of {}", body_name), Direction::Either, move |a| {
+        a.relative(body, central).0[2]
+    }).with_labels(
+        &format!("Ascending node of {} around {}", body_name, central_name),
+        &format!("Descending node of {} around {}", body_name, central_name)))
+}
+fn longitude_difference(state: &State, observer: usize, first: usize, second: usize) -> f64 {
+    let first = vector::subtract(&state.position[first], &state.position[observer]);
+    let second = vector::subtract(&state.position[second], &state.position[observer]);
+    first[1].atan2(first[0]) - second[1].atan2(second[0])
+}
+pub fn conjunctions(system: &System, observer: usize, first: usize, second: usize) -> Result<EventFunction, String> {
+    check(system, &[observer, first, second])?;
+    let (observer_name, first_name, second_name) = (name(system, observer), name(system, first), name(system, second));
+    Ok(EventFunction::new(&format!("conjunctions of {} and {}", first_name, second_name), Direction::Either, move |a| {
+        longitude_difference(a, observer, first, second).sin()
+    }).with_classifier(move |a, _| {
+        let kind = if longitude_difference(a, observer, first, second).cos() > 0.0 { "Conjunction" } else { "Opposition" };
+        format!("{} of {} and {} seen from {}", kind, first_name, second_name, observer_name)
+    }))
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Detection {
+    pub function: usize,
+    pub label: String,
+    pub epoch: f64,
+    pub rising: bool,
+    pub state: State
+}
+#[derive(Debug)]
+pub struct EventDetector {
+    pub subdivisions: usize,
+    pub detections: Vec<Detection>,
+    function: Vec<EventFunction>,
+    previous: Option<(State, Vec<f64>)>
+}
+impl Default for EventDetector {
+    fn default() -> Self {
+        EventDetector::new()
+    }
+}
+impl EventDetector {
+    pub fn new() -> EventDetector {
+        EventDetector {
+            subdivisions: 4,
+            detections: Vec::new(),
+            function: Vec::new(),
+            previous: None
+        }
+    }
+    pub fn add(&mut self, function: EventFunction) -> usize {
+        self.function.push(function);
+        self.previous = None;
+        self.function.len() - 1
+    }
+    pub fn function(&self, index: usize) -> Option<&EventFunction> {
+        self.function.get(index)
+    }
+    fn values(&self, state: &State) -> Vec<f64> {
+        self.function.iter().map(|a| a.value(state)).collect()
+    }
+    fn refine(&self, index: usize, first: &State, second: &State, mut before: f64, mut after: f64) -> State {
+        let positive = self.function[index].value(&first.interpolate(second, after)) >= 0.0;
+        while after - before > 1.0E-9 {
+            let middle = (before + after) / 2.0;
+            if (self.function[index].value(&first.interpolate(second, middle)) >= 0.0) == positive {
+                after = middle
+            } else {
+                before = middle
+            }
+        }
+        first.interpolate(second, (before + after) / 2.0)
+    }
+    pub fn record(&mut self, system: &System) {
+        let state = State::capture(system);
+        let (first, mut previous) = match self.previous.take() {
+            Some(x) => x,
+            None => {
+                let values = self.values(&state);
+                self.previous = Some((state, values));
+                return
+            }
+        };
+        let subdivisions = self.subdivisions.max(1);
+        let mut before = first.epoch;
+        for step in 1..=subdivisions {
+            let epoch = first.epoch + (state.epoch - first.epoch) * step as f64 / subdivisions as f64;
+            let values = if step == subdivisions { self.values(&state) } else { self.values(&first.interpolate(&state, epoch)) };
+            let mut found = Vec::new();
+            for (i, (a, b)) in previous.iter().zip(values.iter()).enumerate() {
+                let rising = *b >= 0.0;
+                if (*a >= 0.0) != rising && self.function[i].direction.accepts(rising) {
+                    let at = self.refine(i, &first, &state, before, epoch);
+                    found.push(Detection {
+                        function: i,
+                        label: (self.function[i].classify)(&at, rising),
+                        epoch: at.epoch,
+                        rising,
+                        state: at
+                    });
+                }
+            }
+            found.sort_by(|a, b| a.epoch.partial_cmp(&b.epoch).unwrap());
+            self.detections.append(&mut found);
+            previous = values;
+            before = epoch;
+        }
+        self.previous = Some((state, previous));
+    }
+    pub fn save_csv(&self, file_path: &str) -> Result<(), String> {
+        let mut contents = "epoch,function,direction,event\n".to_string();
+        for a in self.detections.iter() {
+            contents.push_str(&format!("{},{},{},{}\n", a.epoch, csv_field(&self.function[a.function].name),
+                if a.rising { "rising" } else { "falling" }, csv_field(&a.label)));
+        }
+        write_text(file_path, &contents)
+    }
+}
+pub fn find_events(system: &mut System, function: Vec<EventFunction>, duration: f64, subdivisions: usize)
+    -> Result<EventDetector, String> {
+    if function.is_empty() {
+        return Err("Failed to find events! No event functions were given".to_string())
+    }
+    let mut detector = EventDetector::new();
+    detector.subdivisions = subdivisions;
+    function.into_iter().for_each(|a| { detector.add(a); });
+    let attached = system.detach_detectors();
+    system.attach_detector(detector);
+    system.run_for(duration, |_, _, _| Ok(()))?;
+    let detector = system.detach_detectors().pop().unwrap();
+    attached.into_iter().for_each(|a| system.attach_detector(a));
+    Ok(detector)
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use crate::{
+        system::{
+            body::Body,
+            integrator::Integrator,
+            orbital_elements::OrbitalElements
+        },
+        units::GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+    };
+    use float_eq::assert_float_eq;
+    fn add_orbit(system: &mut System, name: &str, elements: &OrbitalElements) {
+        let (position, velocity) = elements.to_state(1.0);
+        system.add(Body::new(name.to_string(), 0.0, 0.0, position, velocity));
+    }
+    fn kepler_system() -> (System, OrbitalElements) {
+        let elements = OrbitalElements {
+            semi_major_axis: 1.0,
+            eccentricity: 0.3,
+            inclination: 0.4,
+            longitude_of_ascending_node: 0.8,
+            argument_of_periapsis: 1.2,
+            mean_anomaly: 2.0
+        };
+        let mut system = System::new();
+        system.add(Body::new("Primary".to_string(), 1.0 / GRAVITATIONAL_MASS_TO_AU3_PER_DAY2, 0.0, [0.0; 3], [0.0; 3]));
+        add_orbit(&mut system, "Particle", &elements);
+        system.set_integrator(Integrator::Yoshida4);
+        system.set_delta_time(0.01);
+        (system, elements)
+    }
+    #[test]
+    fn find_apsides_and_nodes() -> Result<(), String> {
+        let (mut system, elements) = kepler_system();
+        let function = vec![apsides(&system, 1, 0)?, nodes(&system, 1, 0)?];
+        let detector = find_events(&mut system, function, 4.0 * PI, 4)?;
+        let half = (2.0 * PI - elements.argument_of_periapsis) / 2.0;
+        let eccentric = 2.0 * (((1.0 - elements.eccentricity) / (1.0 + elements.eccentricity)).sqrt() * half.sin()).atan2(half.cos());
+        let node = (eccentric - elements.eccentricity * eccentric.sin()).rem_euclid(2.0 * PI);
+        let mut expected: Vec<(f64, &str)> = (1..5)
+            .map(|a| (a as f64 * PI - elements.mean_anomaly, if a % 2 == 0 { "Periapsis" } else { "Apoapsis" }))
+            .chain((0..2).map(|a| ((node - elements.mean_anomaly).rem_euclid(2.0 * PI) + a as f64 * 2.0 * PI, "Ascending")))
+            .filter(|a| a.0 < 4.0 * PI)
+            .collect();
+        expected.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let found: Vec<&Detection> = detector.detections.iter().filter(|a| !a.label.starts_with("Descending")).collect();
+        assert_eq!(found.len(), expected.len());
+        for (a, b) in found.iter().zip(expected.iter()) {
+            assert!(a.label.starts_with(b.1), "{} instead of {}", a.label, b.1);
+            assert_float_eq!(a.epoch, b.0, abs <= 1.0E-6);
+        }
+        assert_eq!(detector.detections.iter().filter(|a| a.label.starts_with("Descending")).count(), 2);
+        assert!(apsides(&system, 1, 1).is_err());
+        Ok(())
+    }
+    #[test]
+    fn find_conjunctions_and_oppositions() -> Result<(), String> {
+        let mut system = System::new();
+        system.add(Body::new("Primary".to_string(), 1.0 / GRAVITATIONAL_MASS_TO_AU3_PER_DAY2, 0.0, [0.0; 3], [0.0; 3]));
+        for (name, semi_major_axis, mean_anomaly) in [("Inner", 1.0, 0.3), ("Outer", 1.5, 0.0)].iter() {
+            add_orbit(&mut system, name, &OrbitalElements {
+                semi_major_axis: *semi_major_axis,
+                eccentricity: 0.0,
+                inclination: 0.0,
+                longitude_of_ascending_node: 0.0,
+                argument_of_periapsis: 0.0,
+                mean_anomaly: *mean_anomaly
+            });
+        }
+        system.set_integrator(Integrator::Yoshida4);
+        system.set_delta_time(0.01);
+        let function = vec![conjunctions(&system, 0, 1, 2)?];
+        let detector = find_events(&mut system, function, 40.0, 4)?;
+        let rate = 1.0 - 1.5f64.powf(-1.5);
+        let expected: Vec<f64> = (1..).map(|a| (a as f64 * PI - 0.3) / rate).take_while(|a| *a < 40.0).collect();
+        assert_eq!(detector.detections.len(), expected.len());
+        for (i, (a, b)) in detector.detections.iter().zip(expected.iter()).enumerate() {
+            assert_float_eq!(a.epoch, *b, abs <= 1.0E-6);
+            assert!(a.label.starts_with(if i % 2 == 0 { "Opposition" } else { "Conjunction" }), "{}", a.label);
+        }
+        Ok(())
+    }
+}
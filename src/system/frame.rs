@@ -0,0 +1,193 @@
+use super::{
+    vector,
+    System
+};
+fn name(system: &System, index: usize) -> String {
+    match system.object_from_index(index) {
+        Some(x) => x.read().unwrap().name().to_string(),
+        None => format!("body {}", index)
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    origin: [f64; 3],
+    origin_velocity: [f64; 3],
+    axis: [[f64; 3]; 3],
+    angular_velocity: [f64; 3]
+}
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            origin: [0.0; 3],
+            origin_velocity: [0.0; 3],
+            axis: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            angular_velocity: [0.0; 3]
+        }
+    }
+    pub fn centred(system: &System, body: usize) -> Result<Transform, String> {
+        let object = match system.object_from_index(body) {
+            Some(x) => x,
+            None => return Err(format!("Failed to find body {} in system!", body))
+        };
+        let object = object.read().unwrap();
+        Ok(Transform {
+            origin: object.position(),
+            origin_velocity: object.velocity(),
+            ..Transform::identity()
+        })
+    }
+    pub fn synodic(system: &System, primary: usize, secondary: usize) -> Result<Transform, String> {
+        if primary == secondary {
+            return Err(format!("Failed to build synodic frame! Primary and secondary are both body {}", primary))
+        }
+        let (first, second) = match (system.object_from_index(primary), system.object_from_index(secondary)) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Err(format!("Failed to find bodies {} and {} in system!", primary, secondary))
+        };
+        let first = first.read().unwrap();
+        let second = second.read().unwrap();
+        let total = first.mass() + second.mass();
+        if total <= 0.0 {
+            return Err(format!("Failed to build synodic frame of {} and {}! Neither has mass", first.name(), second.name()))
+        }
+        let mass_ratio = second.mass() / total;
+        let separation = vector::subtract(&second.position(), &first.position());
+        let relative_velocity = vector::subtract(&second.velocity(), &first.velocity());
+        let distance = vector::norm(&separation);
+        let angular_momentum = vector::cross(&separation, &relative_velocity);
+        if distance == 0.0 || vector::norm(&angular_momentum) == 0.0 {
+            return Err(format!("Failed to build synodic frame of {} and {}! They do not orbit each other",
+                first.name(), second.name()))
+        }
+        let x_axis = vector::scale(&separation, 1.0 / distance);
+        let z_axis = vector::normalize(&angular_momentum);
+        Ok(Transform {
+            origin: vector::add(
+                &vector::scale(&first.position(), 1.0 - mass_ratio),
+                &vector::scale(&second.position(), mass_ratio)),
+            origin_velocity: vector::add(
+                &vector::scale(&first.velocity(), 1.0 - mass_ratio),
+                &vector::scale(&second.velocity(), mass_ratio)),
+            axis: [x_axis, vector::cross(&z_axis, &x_axis), z_axis],
+            angular_velocity: vector::scale(&angular_momentum, 1.0 / distance.powi(2))
+        })
+    }
+    fn rotate(&self, a: &[f64; 3]) -> [f64; 3] {
+        [vector::dot(&self.axis[0], a), vector::dot(&self.axis[1], a), vector::dot(&self.axis[2], a)]
+    }
+    fn unrotate(&self, a: &[f64; 3]) -> [f64; 3] {
+        vector::add(
+            &vector::add(&vector::scale(&self.axis[0], a[0]), &vector::scale(&self.axis[1], a[1])),
+            &vector::scale(&self.axis[2], a[2]))
+    }
+    pub fn position(&self, position: &[f64; 3]) -> [f64; 3] {
+        self.rotate(&vector::subtract(position, &self.origin))
+    }
+    pub fn velocity(&self, position: &[f64; 3], velocity: &[f64; 3]) -> [f64; 3] {
+        let offset = vector::subtract(position, &self.origin);
+        self.rotate(&vector::subtract(
+            &vector::subtract(velocity, &self.origin_velocity),
+            &vector::cross(&self.angular_velocity, &offset)))
+    }
+    pub fn inverse_position(&self, position: &[f64; 3]) -> [f64; 3] {
+        vector::add(&self.origin, &self.unrotate(position))
+    }
+    pub fn inverse_velocity(&self, position: &[f64; 3], velocity: &[f64; 3]) -> [f64; 3] {
+        let offset = self.unrotate(position);
+        vector::add(
+            &vector::add(&self.origin_velocity, &self.unrotate(velocity)),
+            &vector::cross(&self.angular_velocity, &offset))
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Frame {
+    #[default]
+    Inertial,
+    BodyCentred(usize),
+    Synodic(usize, usize)
+}
+impl Frame {
+    pub fn parse(system: &System, frame: &str) -> Result<Frame, String> {
+        let (kind, bodies) = match frame.find(':') {
+            Some(x) => (&frame[..x], &frame[x + 1..]),
+            None => (frame, "")
+        };
+        match (kind.trim().to_lowercase().as_str(), bodies.split(',').collect::<Vec<&str>>().as_slice()) {
+            ("inertial", [""]) => Ok(Frame::Inertial),
+            ("body", [x]) if !x.trim().is_empty() => Ok(Frame::BodyCentred(system.find_body(x)?)),
+            ("synodic", [x, y]) => Ok(Frame::Synodic(system.find_body(x)?, system.find_body(y)?)),
+            _ => Err(format!("Failed to parse frame {}! Expected inertial, body:BODY or synodic:PRIMARY,SECONDARY", frame))
+        }
+    }
+    pub fn describe(&self, system: &System) -> String {
+        match self {
+            Frame::Inertial => "inertial".to_string(),
+            Frame::BodyCentred(x) => format!("centred on {}", name(system, *x)),
+            Frame::Synodic(x, y) => format!("synodic with {} and {}", name(system, *x), name(system, *y))
+        }
+    }
+    pub fn transform(&self, system: &System) -> Result<Transform, String> {
+        match self {
+            Frame::Inertial => Ok(Transform::identity()),
+            Frame::BodyCentred(x) => Transform::centred(system, *x),
+            Frame::Synodic(x, y) => Transform::synodic(system, *x, *y)
+        }
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::{
+        body::Body,
+        fixture
+    };
+    use float_eq::assert_float_eq;
+    fn sun_jupiter_system() -> System {
+        let mut system = System::new();
+        system.add(fixture::sun_1969());
+        system.add(Body::new("Jupiter (599)".to_string(), 126686531.9003704, 71492.0,
+            [-4.915049098735937, -1.672689916089224, 0.1168479823453633],
+            [0.002224934617929962, -0.006461418016429227, -0.00002285693454574066]));
+        system.add(Body::new("Hektor".to_string(), 0.0, 0.0, [-1.0, -5.0, 0.3], [0.007, -0.002, 0.0001]));
+        system
+    }
+    #[test]
+    fn round_trip_frames() -> Result<(), String> {
+        let system = sun_jupiter_system();
+        let hektor = system.object_from_index(2).unwrap();
+        let (position, velocity) = {
+            let hektor = hektor.read().unwrap();
+            (hektor.position(), hektor.velocity())
+        };
+        for frame in ["inertial", "body:Jupiter", "synodic:Sun,Jupiter", "synodic:0,1"].iter() {
+            let transform = Frame::parse(&system, frame)?.transform(&system)?;
+            let local = transform.position(&position);
+            let local_velocity = transform.velocity(&position, &velocity);
+            assert_float_eq!(transform.inverse_position(&local), position, abs <= [1.0E-14; 3]);
+            assert_float_eq!(transform.inverse_velocity(&local, &local_velocity), velocity, abs <= [1.0E-17; 3]);
+        }
+        assert!(Frame::parse(&system, "synodic:Sun").is_err());
+        assert!(Frame::parse(&system, "synodic:Sun,Sun")?.transform(&system).is_err());
+        assert!(Frame::parse(&system, "body:Saturn").is_err());
+        Ok(())
+    }
+    #[test]
+    fn synodic_pair_is_fixed() -> Result<(), String> {
+        let mut system = sun_jupiter_system();
+        let frame = Frame::Synodic(0, 1);
+        for _ in 0..4 {
+            let transform = frame.transform(&system)?;
+            for i in 0..2 {
+                let body = system.object_from_index(i).unwrap();
+                let body = body.read().unwrap();
+                let position = transform.position(&body.position());
+                assert_float_eq!(position[1], 0.0, abs <= 1.0E-12);
+                assert_float_eq!(position[2], 0.0, abs <= 1.0E-12);
+                let velocity = transform.velocity(&body.position(), &body.velocity());
+                assert_float_eq!(velocity[1], 0.0, abs <= 1.0E-12);
+            }
+            (0..240).for_each(|_| system.step());
+        }
+        Ok(())
+    }
+}
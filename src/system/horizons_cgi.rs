@@ -1,12 +1,21 @@
-use time::Date;
 use regex::Regex;
-use super::body::Body;
-pub fn fetch_target_body<T: AsRef<str>>(target: T, date: &Date) -> Result<Body, String> {
+use super::{
+    body::Body,
+    outgassing::{
+        Outgassing,
+        Sublimation
+    }
+};
+use crate::timescale::{
+    Epoch,
+    TimeScale
+};
+pub fn fetch_target_body<T: AsRef<str>>(target: T, date: &Epoch) -> Result<Body, String> {
         let response = query_horizons_server_for(target, date).unwrap();
         generate_body_from(&response)
 }
-fn query_horizons_server_for<T: AsRef<str>>(target: T, date: &Date) -> Result<String, String> {
-    let next_day: Date = date.next_day();
+fn query_horizons_server_for<T: AsRef<str>>(target: T, date: &Epoch) -> Result<String, String> {
+    let start = date.to(TimeScale::Tdb).julian_date();
     let message = format!(
         "{}{}",
         "https://ssd.jpl.nasa.gov/horizons_batch.cgi?batch=1",
@@ -14,8 +23,9 @@ fn query_horizons_server_for<T: AsRef<str>>(target: T, date: &Date) -> Result<St
             &CENTER=%27500@0%27\
             &MAKE_EPHEM=%27YES%27\
             &TABLE_TYPE=%27VECTORS%27\
-            &START_TIME=%27{}%27\
-            &STOP_TIME=%27{}%27\
+            &START_TIME=%27JD{:.9}%27\
+            &STOP_TIME=%27JD{:.9}%27\
+            &TIME_TYPE=%27TDB%27\
             &STEP_SIZE=%271%27\
             &OUT_UNITS=%27AU-D%27\
             &REF_PLANE=%27ECLIPTIC%27\
@@ -27,8 +37,8 @@ fn query_horizons_server_for<T: AsRef<str>>(target: T, date: &Date) -> Result<St
             &OBJ_DATA=%27YES%27\
             &VEC_TABLE=%272%27",
             target.as_ref(),
-            date.format("%Y-%m-%d"),
-            next_day.format("%Y-%m-%d")
+            start,
+            start + 1.0
         )
     );
     let response = match reqwest::blocking::get(message) {
@@ -57,7 +67,18 @@ fn generate_body_from(response: &str) -> Result<Body,String> {
         "$name ($number)"
     ){
         Ok(x) => x,
-        Err(x) => return Err(format!("Failed to find target name!\n{}", x))
+        Err(x) => match search_and_replace(
+            r"JPL/\w+\s+(?P<number>\d+[PDI](-\w+)?)/(?P<name>[\w'-]+( [\w'-]+)*)",
+            &filtered[0],
+            "$name ($number)"
+        ).or_else(|_| search_and_replace(
+            r"JPL/\w+\s+(?P<name>[\w'-]+( [\w'-]+)*)\s+\((?P<number>[PCDXI]/\d+ [\w-]+)\)",
+            &filtered[0],
+            "$name ($number)"
+        )) {
+            Ok(y) => y,
+            Err(_) => return Err(format!("Failed to find target name!\n{}", x))
+        }
     };
     let regex = r"(?P<x>(-?\+?)\d+.\d+E(\+?-?)\d+),\s+(?P<y>(-?\+?)\d+.\d+E(\+?-?)\d+),\s+(?P<z>(-?\+?)\d+.\d+E(\+?-?)\d+),\s*(?x)
         (?P<vx>(-?\+?)\d+.\d+E(\+?-?)\d+),\s+(?P<vy>(-?\+?)\d+.\d+E(\+?-?)\d+),\s+(?P<vz>(-?\+?)\d+.\d+E(\+?-?)\d+),\s*\n\d+";
@@ -129,7 +150,8 @@ fn generate_body_from(response: &str) -> Result<Body,String> {
             }
         }
     };
-    Ok(Body::new(
+    let outgassing = parse_outgassing(&filtered[0]);
+    let body = Body::new(
             target.into(),
             gravitational_mass,
             radius,
@@ -143,9 +165,34 @@ fn generate_body_from(response: &str) -> Result<Body,String> {
                 floats[4].clone()?,
                 floats[5].clone()?
             ],
-    ))
+    );
+    Ok(match outgassing {
+        Some(x) => body.with_outgassing(x),
+        None => body
+    })
 }
-pub fn fetch_target_bodies<T: AsRef<str>>(target: &[T], date: &Date) -> Vec<Body> {
+fn parse_outgassing(object_data: &str) -> Option<Outgassing> {
+    let parameter = |name: &str| search_and_replace(
+        &format!(r"\b{}=\s*(?P<value>[-+]?(\d+\.?\d*|\.\d+)([eE][-+]?\d+)?)", name),
+        object_data,
+        "$value"
+    ).ok().and_then(|x| x.parse::<f64>().ok());
+    let (a1, a2, a3) = (parameter("A1"), parameter("A2"), parameter("A3"));
+    if [a1, a2, a3].iter().all(|a| a.unwrap_or(0.0) == 0.0) {
+        return None
+    }
+    let law = Sublimation::default();
+    Some(Outgassing::new(a1.unwrap_or(0.0), a2.unwrap_or(0.0), a3.unwrap_or(0.0))
+        .with_delay(parameter("DT").unwrap_or(0.0))
+        .with_sublimation(Sublimation {
+            alpha: parameter("ALN").unwrap_or(law.alpha),
+            r0: parameter("R0").unwrap_or(law.r0),
+            m: parameter("NM").unwrap_or(law.m),
+            n: parameter("NN").unwrap_or(law.n),
+            k: parameter("NK").unwrap_or(law.k)
+        }))
+}
+pub fn fetch_target_bodies<T: AsRef<str>>(target: &[T], date: &Epoch) -> Vec<Body> {
     target.iter().map(|a| {
         println!("Fetching {}...", a.as_ref());
         match fetch_target_body(a, date) {
@@ -162,10 +209,22 @@ pub fn fetch_target_bodies<T: AsRef<str>>(target: &[T], date: &Date) -> Vec<Body
         }
     }).map(|a| {a.unwrap()}).collect()
 }
+pub fn read_target_list(file_path: &str) -> Result<Vec<String>, String> {
+    let buffer = match std::fs::read_to_string(file_path) {
+        Err(x) => return Err(format!("Failed reading {}!\n{}", file_path, x)),
+        Ok(x) => x
+    };
+    Ok(buffer.lines()
+        .map(|a| {a.split('#').next().unwrap_or("").trim()})
+        .filter(|a| !a.is_empty())
+        .map(|a| {a.to_string()})
+        .collect())
+}
 #[cfg(test)]
 mod test {
     use super::*;
     use float_eq::assert_float_eq;
+    const CERES_RESPONSE: &str = "*******************************************************************************\nJPL/HORIZONS                  1 Ceres (A801 AA)            2021-Apr-18 18:12:43\nRec #:       1 (+COV) Soln.date: 2021-Apr-13_11:04:44   # obs: 1075 (1995-2021)\n \nIAU76/J2000 helio. ecliptic osc. elements (au, days, deg., period=Julian yrs):\n \n  EPOCH=  2458849.5 ! 2020-Jan-01.00 (TDB)         Residual RMS= .24563\n   EC= .07687465013145245  QR= 2.556401146697176   TP= 2458240.1791309435\n   OM= 80.3011901917491    W=  73.80896808746482   IN= 10.59127767086216\n   A= 2.769289292143484    MA= 130.3159688200986   ADIST= 2.982177437589792\n   PER= 4.60851            N= .213870839           ANGMOM= .028541613\n   DAN= 2.69515            DDN= 2.81323            L= 153.8445988\n   B= 10.1666388           MOID= 1.59231997        TP= 2018-May-01.6791309435\n \nAsteroid physical parameters (km, seconds, rotational period in hours):\n   GM= 62.6284             RAD= 469.7              ROTPER= 9.07417\n   H= 3.53                 G= .120                 B-V= .713\n                           ALBEDO= .090            STYP= C\n \nASTEROID comments: \n1: soln ref.= JPL#48, OCC=0           radar(60 delay, 0 Dop.)\n2: source=ORB\n*******************************************************************************\n \n \n*******************************************************************************\nEphemeris / WWW_USER Sun Apr 18 18:12:43 2021 Pasadena, USA      / Horizons\n*******************************************************************************\nTarget body name: 1 Ceres (A801 AA)               {source: JPL#48}\nCenter body name: Solar System Barycenter (0)     {source: DE431}\nCenter-site name: BODY CENTER\n*******************************************************************************\nStart time      : A.D. 1969-Jul-16 00:00:00.0000 TDB\nStop  time      : A.D. 1969-Jul-17 00:00:00.0000 TDB\nStep-size       : 1 steps\n*******************************************************************************\nCenter geodetic : 0.00000000,0.00000000,0.0000000 {E-lon(deg),Lat(deg),Alt(km)}\nCenter cylindric: 0.00000000,0.00000000,0.0000000 {E-lon(deg),Dxy(km),Dz(km)}\nCenter radii    : (undefined)                                                  \nSmall perturbers: Yes                             {source: SB431-N16}\nOutput units    : AU-D\nOutput type     : GEOMETRIC cartesian states\nOutput format   : 2 (position and velocity)\nReference frame : Ecliptic of J2000.0\n*******************************************************************************\nInitial IAU76/J2000 heliocentric ecliptic osculating elements (au, days, deg.):\n  EPOCH=  2458849.5 ! 2020-Jan-01.00 (TDB)         Residual RMS= .24563        \n   EC= .07687465013145245  QR= 2.556401146697176   TP= 2458240.1791309435      \n   OM= 80.3011901917491    W=  73.80896808746482   IN= 10.59127767086216       \n  Equivalent ICRF heliocentric cartesian coordinates (au, au/d):\n   X= 1.007608869627324E+00  Y=-2.390064275218395E+00  Z=-1.332124522752835E+00\n  VX= 9.201724467231788E-03 VY= 3.370381135450014E-03 VZ=-2.850337057427248E-04\nAsteroid physical parameters (km, seconds, rotational period in hours):        \n   GM= 62.6284             RAD= 469.7              ROTPER= 9.07417             \n   H= 3.53                 G= .120                 B-V= .713                   \n                           ALBEDO= .090            STYP= C                     \n*******************************************************************************\n            JDTDB,            Calendar Date (TDB),                      X,                      Y,                      Z,                     VX,                     VY,                     VZ,\n**************************************************************************************************************************************************************************************************\n$$SOE\n2440418.500000000, A.D. 1969-Jul-16 00:00:00.0000,  1.592773884234155E+00, -2.463766259162856E+00, -3.653478442536497E-01,  8.151272151318315E-03,  5.008550137955732E-03, -1.362500743964101E-03,\n2440419.500000000, A.D. 1969-Jul-17 00:00:00.0000,  1.600916030101704E+00, -2.458743583559882E+00, -3.667082473956584E-01,  8.133005182370315E-03,  5.036789680357272E-03, -1.358303206642045E-03,\n$$EOE\n**************************************************************************************************************************************************************************************************\nCoordinate system description:\n\n  Ecliptic at the standard reference epoch\n\n    Reference epoch: J2000.0\n    X-Y plane: adopted Earth orbital plane at the reference epoch\n               Note: obliquity of 84381.448 arcseconds (IAU76) wrt ICRF equator\n    X-axis   : ICRF\n    Z-axis   : perpendicular to the X-Y plane in the directional (+ or -) sense\n               of Earth's north pole at the reference epoch.\n\n  Symbol meaning [1 au= 149597870.700 km, 1 day= 86400.0 s]:\n\n    JDTDB    Julian Day Number, Barycentric Dynamical Time\n      X      X-component of position vector (au)\n      Y      Y-component of position vector (au)\n      Z      Z-component of position vector (au)\n      VX     X-component of velocity vector (au/day)                           \n      VY     Y-component of velocity vector (au/day)                           \n      VZ     Z-component of velocity vector (au/day)                           \n\nGeometric states/elements have no aberrations applied.\n\n\n Computations by ...\n     Solar System Dynamics Group, Horizons On-Line Ephemeris System\n     4800 Oak Grove Drive, Jet Propulsion Laboratory\n     Pasadena, CA  91109   USA\n     Information  : https://ssd.jpl.nasa.gov/\n     Documentation: https://ssd.jpl.nasa.gov/?horizons_doc\n     Connect      : https://ssd.jpl.nasa.gov/?horizons (browser)\n                    telnet ssd.jpl.nasa.gov 6775       (command-line)\n                    e-mail command interface available\n                    Script and CGI interfaces available\n     Author       : Jon.D.Giorgini@jpl.nasa.gov\n*******************************************************************************"; // cSpell:enable
     fn compare(control: &Body, test: &Body) {
         assert_eq!(control.name(), test.name());
         assert_eq!(control.mass(), test.mass());
@@ -285,14 +344,42 @@ mod test {
     }
     #[test]
     fn generate_asteroid() -> Result<(), String> {
-        let response = "*******************************************************************************\nJPL/HORIZONS                  1 Ceres (A801 AA)            2021-Apr-18 18:12:43\nRec #:       1 (+COV) Soln.date: 2021-Apr-13_11:04:44   # obs: 1075 (1995-2021)\n \nIAU76/J2000 helio. ecliptic osc. elements (au, days, deg., period=Julian yrs):\n \n  EPOCH=  2458849.5 ! 2020-Jan-01.00 (TDB)         Residual RMS= .24563\n   EC= .07687465013145245  QR= 2.556401146697176   TP= 2458240.1791309435\n   OM= 80.3011901917491    W=  73.80896808746482   IN= 10.59127767086216\n   A= 2.769289292143484    MA= 130.3159688200986   ADIST= 2.982177437589792\n   PER= 4.60851            N= .213870839           ANGMOM= .028541613\n   DAN= 2.69515            DDN= 2.81323            L= 153.8445988\n   B= 10.1666388           MOID= 1.59231997        TP= 2018-May-01.6791309435\n \nAsteroid physical parameters (km, seconds, rotational period in hours):\n   GM= 62.6284             RAD= 469.7              ROTPER= 9.07417\n   H= 3.53                 G= .120                 B-V= .713\n                           ALBEDO= .090            STYP= C\n \nASTEROID comments: \n1: soln ref.= JPL#48, OCC=0           radar(60 delay, 0 Dop.)\n2: source=ORB\n*******************************************************************************\n \n \n*******************************************************************************\nEphemeris / WWW_USER Sun Apr 18 18:12:43 2021 Pasadena, USA      / Horizons\n*******************************************************************************\nTarget body name: 1 Ceres (A801 AA)               {source: JPL#48}\nCenter body name: Solar System Barycenter (0)     {source: DE431}\nCenter-site name: BODY CENTER\n*******************************************************************************\nStart time      : A.D. 1969-Jul-16 00:00:00.0000 TDB\nStop  time      : A.D. 1969-Jul-17 00:00:00.0000 TDB\nStep-size       : 1 steps\n*******************************************************************************\nCenter geodetic : 0.00000000,0.00000000,0.0000000 {E-lon(deg),Lat(deg),Alt(km)}\nCenter cylindric: 0.00000000,0.00000000,0.0000000 {E-lon(deg),Dxy(km),Dz(km)}\nCenter radii    : (undefined)                                                  \nSmall perturbers: Yes                             {source: SB431-N16}\nOutput units    : AU-D\nOutput type     : GEOMETRIC cartesian states\nOutput format   : 2 (position and velocity)\nReference frame : Ecliptic of J2000.0\n*******************************************************************************\nInitial IAU76/J2000 heliocentric ecliptic osculating elements (au, days, deg.):\n  EPOCH=  2458849.5 ! 2020-Jan-01.00 (TDB)         Residual RMS= .24563        \n   EC= .07687465013145245  QR= 2.556401146697176   TP= 2458240.1791309435      \n   OM= 80.3011901917491    W=  73.80896808746482   IN= 10.59127767086216       \n  Equivalent ICRF heliocentric cartesian coordinates (au, au/d):\n   X= 1.007608869627324E+00  Y=-2.390064275218395E+00  Z=-1.332124522752835E+00\n  VX= 9.201724467231788E-03 VY= 3.370381135450014E-03 VZ=-2.850337057427248E-04\nAsteroid physical parameters (km, seconds, rotational period in hours):        \n   GM= 62.6284             RAD= 469.7              ROTPER= 9.07417             \n   H= 3.53                 G= .120                 B-V= .713                   \n                           ALBEDO= .090            STYP= C                     \n*******************************************************************************\n            JDTDB,            Calendar Date (TDB),                      X,                      Y,                      Z,                     VX,                     VY,                     VZ,\n**************************************************************************************************************************************************************************************************\n$$SOE\n2440418.500000000, A.D. 1969-Jul-16 00:00:00.0000,  1.592773884234155E+00, -2.463766259162856E+00, -3.653478442536497E-01,  8.151272151318315E-03,  5.008550137955732E-03, -1.362500743964101E-03,\n2440419.500000000, A.D. 1969-Jul-17 00:00:00.0000,  1.600916030101704E+00, -2.458743583559882E+00, -3.667082473956584E-01,  8.133005182370315E-03,  5.036789680357272E-03, -1.358303206642045E-03,\n$$EOE\n**************************************************************************************************************************************************************************************************\nCoordinate system description:\n\n  Ecliptic at the standard reference epoch\n\n    Reference epoch: J2000.0\n    X-Y plane: adopted Earth orbital plane at the reference epoch\n               Note: obliquity of 84381.448 arcseconds (IAU76) wrt ICRF equator\n    X-axis   : ICRF\n    Z-axis   : perpendicular to the X-Y plane in the directional (+ or -) sense\n               of Earth's north pole at the reference epoch.\n\n  Symbol meaning [1 au= 149597870.700 km, 1 day= 86400.0 s]:\n\n    JDTDB    Julian Day Number, Barycentric Dynamical Time\n      X      X-component of position vector (au)\n      Y      Y-component of position vector (au)\n      Z      Z-component of position vector (au)\n      VX     X-component of velocity vector (au/day)                           \n      VY     Y-component of velocity vector (au/day)                           \n      VZ     Z-component of velocity vector (au/day)                           \n\nGeometric states/elements have no aberrations applied.\n\n\n Computations by ...\n     Solar System Dynamics Group, Horizons On-Line Ephemeris System\n     4800 Oak Grove Drive, Jet Propulsion Laboratory\n     Pasadena, CA  91109   USA\n     Information  : https://ssd.jpl.nasa.gov/\n     Documentation: https://ssd.jpl.nasa.gov/?horizons_doc\n     Connect      : https://ssd.jpl.nasa.gov/?horizons (browser)\n                    telnet ssd.jpl.nasa.gov 6775       (command-line)\n                    e-mail command interface available\n                    Script and CGI interfaces available\n     Author       : Jon.D.Giorgini@jpl.nasa.gov\n*******************************************************************************"; // cSpell:enable
-        let test = generate_body_from(response).unwrap();
+        let test = generate_body_from(CERES_RESPONSE).unwrap();
         compare_ceres_to(&test);
+        assert!(test.outgassing().is_none());
+        Ok(())
+    }
+    #[test]
+    fn generate_comet() -> Result<(), String> {
+        let header = "JPL/HORIZONS                  1 Ceres (A801 AA)            2021-Apr-18 18:12:43";
+        let response = CERES_RESPONSE
+            .replace(header, "JPL/HORIZONS                    1P/Halley                2021-Apr-18 18:12:43")
+            .replace("Asteroid physical parameters (km, seconds, rotational period in hours):\n   GM= 62.6284             RAD= 469.7              ROTPER= 9.07417\n   H= 3.53                 G= .120                 B-V= .713\n                           ALBEDO= .090            STYP= C\n \nASTEROID comments: \n1: soln ref.= JPL#48, OCC=0           radar(60 delay, 0 Dop.)\n2: source=ORB\n",
+                "Comet physical (GM= km^3/s^2; RAD= km):\n   GM= n.a.                RAD= 5.5\n   M1=  5.5      M2=  13.6     k1=  8.      k2=  5.      PHCOF=  .030\n   A1= 2.7e-10   A2= 1.553E-10 A3= -.4e-11  DT= 0.\n   R0= 5.  ALN= .0337 NM= 2.  NN= 3.  NK= 2.6\n \nCOMET comments \n1: soln ref.= JPL#J863/77\n2: k1=8., k2=5., phase coef.=0.03;\n");
+        let test = generate_body_from(&response)?;
+        assert_eq!(test.name(), "Halley (1P)");
+        let outgassing = test.outgassing().cloned().unwrap();
+        assert_eq!((outgassing.a1, outgassing.a2, outgassing.a3, outgassing.delay), (2.7E-10, 1.553E-10, -0.4E-11, 0.0));
+        assert_eq!(outgassing.sublimation, Sublimation {
+            alpha: 0.0337,
+            r0: 5.0,
+            m: 2.0,
+            n: 3.0,
+            k: 2.6
+        });
+        let response = response.replace("JPL/HORIZONS                    1P/Halley               ",
+            "JPL/HORIZONS              NEOWISE (C/2020 F3)         ")
+            .replace("   R0= 5.  ALN= .0337 NM= 2.  NN= 3.  NK= 2.6\n", "");
+        let test = generate_body_from(&response)?;
+        assert_eq!(test.name(), "NEOWISE (C/2020 F3)");
+        assert_eq!(test.outgassing().unwrap().sublimation, Sublimation::default());
+        let test = generate_body_from(&response.replace("DT= 0.", "DT= 35.2"))?;
+        assert_eq!(test.outgassing().unwrap().delay, 35.2);
         Ok(())
     }
     #[test]
     fn fetch_targets() -> Result<(), String> {
-        let date = Date::try_from_ymd(1969,7,16).unwrap();
+        let date = Epoch::from_calendar(1969, 7, 16, 0, 0, 0.0, TimeScale::Tdb)?;
         let test = fetch_target_body(r"sun", &date)?;
         compare_sun_to(&test);
         let test = fetch_target_body(r"199", &date)?;
@@ -320,7 +407,7 @@ mod test {
                 "899",
                 "134340"
             ),
-            &Date::try_from_ymd(1969, 7, 16).unwrap()
+            &Epoch::from_calendar(1969, 7, 16, 0, 0, 0.0, TimeScale::Tdb)?
         );
         let control = [
             "Sun (10)",
@@ -0,0 +1,62 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use super::System;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Integrator {
+    #[default]
+    KickDriftKick,
+    DriftKickDrift,
+    Yoshida4
+}
+const YOSHIDA_4: [f64; 2] = [
+    1.3512071919596578,
+    -1.7024143839193153
+];
+impl Integrator {
+    pub fn all() -> Vec<Integrator> {
+        vec!(Integrator::KickDriftKick, Integrator::DriftKickDrift, Integrator::Yoshida4)
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::KickDriftKick => "kdk",
+            Integrator::DriftKickDrift => "dkd",
+            Integrator::Yoshida4 => "yoshida4"
+        }
+    }
+    pub fn from_name(name: &str) -> Result<Integrator, String> {
+        match Integrator::all().into_iter().find(|a| a.name() == name.to_lowercase()) {
+            Some(x) => Ok(x),
+            None => Err(format!("Failed to find integrator {}! Expected one of: {}",
+                name,
+                Integrator::all().iter().map(|a| a.name()).collect::<Vec<_>>().join(", ")))
+        }
+    }
+    pub fn order(&self) -> usize {
+        match self {
+            Integrator::KickDriftKick => 2,
+            Integrator::DriftKickDrift => 2,
+            Integrator::Yoshida4 => 4
+        }
+    }
+    pub(super) fn step(&self, system: &mut System, delta_time: f64) {
+        match self {
+            Integrator::KickDriftKick => {
+                system.exchange_gravitational_forces(delta_time/2.0);
+                system.apply_individual_velocities(delta_time);
+                system.exchange_gravitational_forces(delta_time/2.0);
+            },
+            Integrator::DriftKickDrift => {
+                system.apply_individual_velocities(delta_time/2.0);
+                system.exchange_gravitational_forces(delta_time);
+                system.apply_individual_velocities(delta_time/2.0);
+            },
+            Integrator::Yoshida4 => {
+                [YOSHIDA_4[0], YOSHIDA_4[1], YOSHIDA_4[0]].iter().for_each(|a| {
+                    Integrator::KickDriftKick.step(system, a * delta_time)
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use super::{
+    frame::Transform,
+    vector,
+    System
+};
+pub const NAMES: [&str; 5] = ["L1", "L2", "L3", "L4", "L5"];
+fn collinear_force(mass_ratio: f64, x: f64) -> (f64, f64) {
+    let primary = x + mass_ratio;
+    let secondary = x - 1.0 + mass_ratio;
+    let value = x - (1.0 - mass_ratio) * primary / primary.abs().powi(3) - mass_ratio * secondary / secondary.abs().powi(3);
+    let slope = 1.0 + 2.0 * (1.0 - mass_ratio) / primary.abs().powi(3) + 2.0 * mass_ratio / secondary.abs().powi(3);
+    (value, slope)
+}
+fn collinear_point(mass_ratio: f64, guess: f64) -> f64 {
+    let mut x = guess;
+    for _ in 0..64 {
+        let (value, slope) = collinear_force(mass_ratio, x);
+        let change = value / slope;
+        x -= change;
+        if change.abs() < 1.0E-15 {
+            break
+        }
+    }
+    x
+}
+pub fn normalized_points(mass_ratio: f64) -> [[f64; 3]; 5] {
+    let hill = (mass_ratio / 3.0).cbrt();
+    [
+        [collinear_point(mass_ratio, 1.0 - mass_ratio - hill), 0.0, 0.0],
+        [collinear_point(mass_ratio, 1.0 - mass_ratio + hill), 0.0, 0.0],
+        [collinear_point(mass_ratio, -1.0 - 5.0 * mass_ratio / 12.0), 0.0, 0.0],
+        [0.5 - mass_ratio, 3.0f64.sqrt() / 2.0, 0.0],
+        [0.5 - mass_ratio, -(3.0f64.sqrt()) / 2.0, 0.0]
+    ]
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct LagrangePoints {
+    pub epoch: f64,
+    pub primary: usize,
+    pub secondary: usize,
+    pub mass_ratio: f64,
+    pub rotating: [[f64; 3]; 5],
+    pub inertial: [[f64; 3]; 5],
+    pub inertial_velocity: [[f64; 3]; 5]
+}
+impl LagrangePoints {
+    pub fn of(system: &System, primary: usize, secondary: usize) -> Result<LagrangePoints, String> {
+        let transform = Transform::synodic(system, primary, secondary)?;
+        let first = system.object_from_index(primary).unwrap();
+        let second = system.object_from_index(secondary).unwrap();
+        let first = first.read().unwrap();
+        let second = second.read().unwrap();
+        let mass_ratio = second.mass() / (first.mass() + second.mass());
+        let separation = vector::subtract(&second.position(), &first.position());
+        let distance = vector::norm(&separation);
+        let radial_rate = vector::dot(&separation, &vector::subtract(&second.velocity(), &first.velocity())) / distance;
+        let mut points = LagrangePoints {
+            epoch: system.epoch(),
+            primary,
+            secondary,
+            mass_ratio,
+            rotating: [[0.0; 3]; 5],
+            inertial: [[0.0; 3]; 5],
+            inertial_velocity: [[0.0; 3]; 5]
+        };
+        for (i, point) in normalized_points(mass_ratio).iter().enumerate() {
+            let rotating = vector::scale(point, distance);
+            points.rotating[i] = rotating;
+            points.inertial[i] = transform.inverse_position(&rotating);
+            points.inertial_velocity[i] = transform.inverse_velocity(&rotating, &vector::scale(point, radial_rate));
+        }
+        Ok(points)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::{
+        body::Body,
+        fixture
+    };
+    use float_eq::assert_float_eq;
+    #[test]
+    fn balance_forces_in_rotating_frame() -> Result<(), String> {
+        for mass_ratio in [1.0E-6, 3.0035E-6, 9.5E-4, 0.012, 0.3].iter() {
+            let points = normalized_points(*mass_ratio);
+            for point in points.iter().take(3) {
+                assert_float_eq!(collinear_force(*mass_ratio, point[0]).0, 0.0, abs <= 1.0E-12);
+            }
+            assert!(points[0][0] > -mass_ratio && points[0][0] < 1.0 - mass_ratio);
+            assert!(points[1][0] > 1.0 - mass_ratio);
+            assert!(points[2][0] < -mass_ratio);
+        }
+        Ok(())
+    }
+    #[test]
+    fn sun_earth_points() -> Result<(), String> {
+        let mut system = System::new();
+        system.add(fixture::sun());
+        system.add(Body::new("Earth (399)".to_string(), 403503.235502, 6371.01,
+            [1.0, 0.0, 0.0], [0.0, 0.01720209895, 0.0]));
+        system.add(Body::new("Moon (301)".to_string(), 0.0, 1737.4, [1.0, 0.00257, 0.0], [-0.0006, 0.0172, 0.0]));
+        let points = system.lagrange_points(0, 1)?;
+        assert_float_eq!(points.rotating[3], [0.5 - points.mass_ratio, 0.75f64.sqrt(), 0.0], abs <= [1.0E-12; 3]);
+        assert_float_eq!(points.inertial[0][0], 0.99, abs <= 2.0E-4);
+        assert_float_eq!(points.inertial[1][0], 1.01, abs <= 2.0E-4);
+        assert_float_eq!(points.inertial_velocity[1][1], 0.01720209895 * points.inertial[1][0], abs <= 1.0E-12);
+        assert!(system.lagrange_points(1, 1).is_err());
+        assert!(system.lagrange_points(2, 3).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use std::f64::consts::PI;
+use super::vector;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Branch {
+    Single,
+    Left,
+    Right
+}
+impl Branch {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Branch::Single => "single",
+            Branch::Left => "left",
+            Branch::Right => "right"
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LambertSolution {
+    pub revolutions: u32,
+    pub branch: Branch,
+    pub departure_velocity: [f64; 3],
+    pub arrival_velocity: [f64; 3]
+}
+fn hypergeometric(z: f64) -> f64 {
+    let (mut sum, mut term) = (1.0, 1.0f64);
+    let mut j = 0.0;
+    while term.abs() > 1.0E-11 && j < 1000.0 {
+        term *= (3.0 + j) * (1.0 + j) / (2.5 + j) * z / (j + 1.0);
+        sum += term;
+        j += 1.0;
+    }
+    sum
+}
+fn lagrange_time(lambda: f64, x: f64, revolutions: u32) -> f64 {
+    let a = 1.0 / (1.0 - x * x);
+    if a > 0.0 {
+        let alpha = 2.0 * x.acos();
+        let beta = 2.0 * (lambda * lambda / a).sqrt().asin().copysign(lambda);
+        a * a.sqrt() * ((alpha - alpha.sin()) - (beta - beta.sin()) + 2.0 * PI * revolutions as f64) / 2.0
+    } else {
+        let alpha = 2.0 * x.acosh();
+        let beta = 2.0 * (-lambda * lambda / a).sqrt().asinh().copysign(lambda);
+        -a * (-a).sqrt() * ((beta - beta.sinh()) - (alpha - alpha.sinh())) / 2.0
+    }
+}
+fn time_of_flight(lambda: f64, x: f64, revolutions: u32) -> f64 {
+    let distance = (x - 1.0).abs();
+    if distance < 0.2 && distance > 0.01 {
+        return lagrange_time(lambda, x, revolutions)
+    }
+    let energy = x * x - 1.0;
+    let rho = energy.abs();
+    let z = (1.0 + lambda * lambda * energy).sqrt();
+    if distance < 0.01 {
+        let eta = z - lambda * x;
+        let s1 = 0.5 * (1.0 - lambda - x * eta);
+        let q = 4.0 / 3.0 * hypergeometric(s1);
+        (eta.powi(3) * q + 4.0 * lambda * eta) / 2.0 + revolutions as f64 * PI / rho.powf(1.5)
+    } else {
+        let y = rho.sqrt();
+        let g = x * z - lambda * energy;
+        let d = if energy < 0.0 {
+            revolutions as f64 * PI + g.acos()
+        } else {
+            (y * (z - lambda * x) + g).ln()
+        };
+        (x - lambda * z - d / y) / energy
+    }
+}
+fn derivatives(lambda: f64, x: f64, time: f64) -> (f64, f64, f64) {
+    let (l2, l3) = (lambda * lambda, lambda.powi(3));
+    let umx2 = 1.0 - x * x;
+    let y = (1.0 - l2 * umx2).sqrt();
+    let first = (3.0 * time * x - 2.0 + 2.0 * l3 * x / y) / umx2;
+    let second = (3.0 * time + 5.0 * x * first + 2.0 * (1.0 - l2) * l3 / y.powi(3)) / umx2;
+    let third = (7.0 * x * second + 8.0 * first - 6.0 * (1.0 - l2) * l2 * l3 * x / y.powi(5)) / umx2;
+    (first, second, third)
+}
+fn householder(lambda: f64, target: f64, guess: f64, revolutions: u32) -> Option<f64> {
+    let mut x = guess;
+    for _ in 0..32 {
+        let time = time_of_flight(lambda, x, revolutions);
+        let (first, second, third) = derivatives(lambda, x, time);
+        let delta = time - target;
+        let next = x - delta * (first * first - delta * second / 2.0) /
+            (first * (first * first - delta * second) + third * delta * delta / 6.0);
+        if !next.is_finite() {
+            return None
+        }
+        let change = (next - x).abs();
+        x = next;
+        if change < 1.0E-13 {
+            return Some(x)
+        }
+    }
+    None
+}
+fn maximum_revolutions(lambda: f64, target: f64) -> u32 {
+    let mut revolutions = (target / PI).floor() as u32;
+    let single = lambda.acos() + lambda * (1.0 - lambda * lambda).sqrt();
+    if revolutions > 0 && target < single + revolutions as f64 * PI {
+        let (mut x, mut minimum) = (0.0, single + revolutions as f64 * PI);
+        for _ in 0..12 {
+            let (first, second, third) = derivatives(lambda, x, minimum);
+            let next = if first != 0.0 { x - first * second / (second * second - first * third / 2.0) } else { x };
+            let change = (next - x).abs();
+            minimum = time_of_flight(lambda, next, revolutions);
+            x = next;
+            if change < 1.0E-13 {
+                break
+            }
+        }
+        if minimum > target {
+            revolutions -= 1;
+        }
+    }
+    revolutions
+}
+pub fn solve(
+    departure: &[f64; 3],
+    arrival: &[f64; 3],
+    time_of_flight_days: f64,
+    gravitational_parameter: f64,
+    prograde: bool,
+    max_revolutions: u32
+) -> Result<Vec<LambertSolution>, String> {
+    if time_of_flight_days <= 0.0 || gravitational_parameter <= 0.0 {
+        return Err(format!("Failed to solve Lambert's problem! Time of flight {} and gravitational parameter {} must be positive",
+            time_of_flight_days, gravitational_parameter))
+    }
+    let chord = vector::norm(&vector::subtract(arrival, departure));
+    let (r1, r2) = (vector::norm(departure), vector::norm(arrival));
+    let normal = vector::cross(departure, arrival);
+    if r1 == 0.0 || r2 == 0.0 || vector::norm(&normal) <= 1.0E-12 * r1 * r2 {
+        return Err("Failed to solve Lambert's problem! Positions must not be zero or collinear with the central body".to_string())
+    }
+    let semi_perimeter = (chord + r1 + r2) / 2.0;
+    let (unit1, unit2, normal) = (vector::normalize(departure), vector::normalize(arrival), vector::normalize(&normal));
+    let mut lambda = (1.0 - chord / semi_perimeter).max(0.0).sqrt();
+    let (mut tangent1, mut tangent2) = if normal[2] < 0.0 {
+        lambda = -lambda;
+        (vector::cross(&unit1, &normal), vector::cross(&unit2, &normal))
+    } else {
+        (vector::cross(&normal, &unit1), vector::cross(&normal, &unit2))
+    };
+    if !prograde {
+        lambda = -lambda;
+        tangent1 = vector::scale(&tangent1, -1.0);
+        tangent2 = vector::scale(&tangent2, -1.0);
+    }
+    let (tangent1, tangent2) = (vector::normalize(&tangent1), vector::normalize(&tangent2));
+    let target = (2.0 * gravitational_parameter / semi_perimeter.powi(3)).sqrt() * time_of_flight_days;
+    let single = lambda.acos() + lambda * (1.0 - lambda * lambda).sqrt();
+    let parabolic = 2.0 / 3.0 * (1.0 - lambda.powi(3));
+    let guess = if target >= single {
+        -(target - single) / (target - single + 4.0)
+    } else if target <= parabolic {
+        parabolic * (parabolic - target) / (0.4 * (1.0 - lambda.powi(5)) * target) + 1.0
+    } else {
+        (target / single).powf(2.0f64.ln() / (parabolic / single).ln()) - 1.0
+    };
+    let mut root = vec!((0, Branch::Single, householder(lambda, target, guess, 0)));
+    for revolutions in 1..=maximum_revolutions(lambda, target).min(max_revolutions) {
+        let left = ((revolutions as f64 * PI + PI) / (8.0 * target)).powf(2.0 / 3.0);
+        let right = (8.0 * target / (revolutions as f64 * PI)).powf(2.0 / 3.0);
+        root.push((revolutions, Branch::Left, householder(lambda, target, (left - 1.0) / (left + 1.0), revolutions)));
+        root.push((revolutions, Branch::Right, householder(lambda, target, (right - 1.0) / (right + 1.0), revolutions)));
+    }
+    let gamma = (gravitational_parameter * semi_perimeter / 2.0).sqrt();
+    let rho = (r1 - r2) / chord;
+    let sigma = (1.0 - rho * rho).max(0.0).sqrt();
+    Ok(root.into_iter().filter_map(|(revolutions, branch, x)| {
+        let x = x?;
+        let y = (1.0 - lambda * lambda + lambda * lambda * x * x).sqrt();
+        let radial1 = gamma * ((lambda * y - x) - rho * (lambda * y + x)) / r1;
+        let radial2 = -gamma * ((lambda * y - x) + rho * (lambda * y + x)) / r2;
+        let transverse = gamma * sigma * (y + lambda * x);
+        Some(LambertSolution {
+            revolutions,
+            branch,
+            departure_velocity: vector::add(&vector::scale(&unit1, radial1), &vector::scale(&tangent1, transverse / r1)),
+            arrival_velocity: vector::add(&vector::scale(&unit2, radial2), &vector::scale(&tangent2, transverse / r2))
+        })
+    }).collect())
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::orbital_elements::OrbitalElements;
+    use float_eq::assert_float_eq;
+    #[test]
+    fn solve_single_revolution() -> Result<(), String> {
+        let solution = solve(&[15945.34, 0.0, 0.0], &[12214.83899, 10249.46731, 0.0], 76.0 * 60.0, 398600.4418, true, 0)?;
+        assert_eq!(solution.len(), 1);
+        assert_float_eq!(solution[0].departure_velocity, [2.058913, 2.915965, 0.0], abs <= [1.0E-5; 3]);
+        assert_float_eq!(solution[0].arrival_velocity, [-3.451565, 0.910315, 0.0], abs <= [1.0E-5; 3]);
+        let retrograde = solve(&[15945.34, 0.0, 0.0], &[12214.83899, 10249.46731, 0.0], 76.0 * 60.0, 398600.4418, false, 0)?;
+        assert!(retrograde[0].departure_velocity[1] < 0.0);
+        assert!(solve(&[1.0, 0.0, 0.0], &[-2.0, 0.0, 0.0], 100.0, 1.0, true, 0).is_err());
+        Ok(())
+    }
+    #[test]
+    fn solve_multiple_revolutions() -> Result<(), String> {
+        let mu = 2.959122082855911E-4;
+        let departure = [1.0, 0.0, 0.0];
+        let arrival = [0.0, 1.5, 0.1];
+        let solution = solve(&departure, &arrival, 1500.0, mu, true, 2)?;
+        assert_eq!(solution.len(), 5);
+        assert_eq!(solution.iter().filter(|a| a.revolutions == 2).count(), 2);
+        for a in solution.iter() {
+            let elements = OrbitalElements::from_state(mu, &departure, &a.departure_velocity);
+            assert!(elements.eccentricity < 1.0);
+            assert_eq!((1500.0 / elements.period(mu)).floor() as u32, a.revolutions);
+            let (position, velocity) = elements.propagate(mu, 1500.0).to_state(mu);
+            assert_float_eq!(position, arrival, abs <= [1.0E-9; 3]);
+            assert_float_eq!(velocity, a.arrival_velocity, abs <= [1.0E-11; 3]);
+        }
+        Ok(())
+    }
+}
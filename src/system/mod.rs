@@ -1,5 +1,24 @@
 pub mod body;
 pub mod horizons_cgi;
+pub mod recorder;
+pub mod integrator;
+pub mod checkpoint;
+pub mod diagnostics;
+pub mod events;
+pub mod frame;
+pub mod lagrange;
+pub mod lambert;
+pub mod orbital_elements;
+pub mod outgassing;
+pub mod radiation;
+pub mod reference_frame;
+pub mod scenario;
+pub mod spacecraft;
+pub mod tide;
+pub mod variational;
+pub mod vector;
+mod binary;
+pub(crate) mod text;
 use std::{
     sync::{
         Arc,
@@ -13,14 +32,92 @@ use serde::{
     Serialize,
 };
 use body::Body;
+use recorder::TrajectoryRecorder;
+use events::EventDetector;
+use integrator::Integrator;
+use orbital_elements::OrbitalElements;
+use lagrange::LagrangePoints;
+use reference_frame::ReferenceFrame;
+use spacecraft::BurnRecord;
+use variational::TangentVector;
+use crate::units::{
+    Speed,
+    Units,
+    GRAVITATIONAL_MASS_TO_AU3_PER_DAY2,
+    M_PER_S2_TO_AU_PER_DAY2
+};
+use crate::timescale::{
+    Epoch,
+    TimeScale
+};
+#[derive(Debug)]
+struct AutoCheckpoint {
+    file_path: String,
+    interval: u64,
+    error: Option<String>
+}
+fn default_delta_time() -> f64 {
+    1.0/24.0
+}
+fn default_luminous() -> String {
+    "Sun".to_string()
+}
+fn default_luminosity() -> f64 {
+    1.0
+}
 #[derive(Debug,Serialize,Deserialize)]
 pub struct System {
-    object: Vec<Arc<RwLock<Body>>>
+    #[serde(default)]
+    epoch: f64,
+    #[serde(default)]
+    elapsed: f64,
+    #[serde(default)]
+    steps: u64,
+    #[serde(default = "default_delta_time")]
+    delta_time: f64,
+    #[serde(default)]
+    integrator: Integrator,
+    #[serde(default)]
+    units: Units,
+    #[serde(default = "default_luminous")]
+    luminous: String,
+    #[serde(default = "default_luminosity")]
+    luminosity: f64,
+    object: Vec<Arc<RwLock<Body>>>,
+    #[serde(skip)]
+    recorder: Vec<TrajectoryRecorder>,
+    #[serde(skip)]
+    detector: Vec<EventDetector>,
+    #[serde(skip)]
+    autocheckpoint: Option<AutoCheckpoint>,
+    #[serde(skip)]
+    tangent: Vec<TangentVector>,
+    #[serde(skip)]
+    burn: Vec<BurnRecord>,
+    #[serde(skip)]
+    thrust: Vec<f64>,
+    #[serde(skip)]
+    written_by: Option<String>
 }
 impl System {
     pub fn new() -> System {
         System {
-            object: Vec::new()
+            epoch: 0.0,
+            elapsed: 0.0,
+            steps: 0,
+            delta_time: default_delta_time(),
+            integrator: Integrator::default(),
+            units: Units::canonical(),
+            luminous: default_luminous(),
+            luminosity: default_luminosity(),
+            object: Vec::new(),
+            recorder: Vec::new(),
+            detector: Vec::new(),
+            autocheckpoint: None,
+            tangent: Vec::new(),
+            burn: Vec::new(),
+            thrust: Vec::new(),
+            written_by: None
         }
     }
     pub fn save_json(&self, file_path: &str) -> Result<(), String> {
@@ -45,13 +142,27 @@ impl System {
             Err(x) => return Err(format!("Error reading {}!\n{}", file_path, x)),
             x => x.unwrap()
         };
-        match serde_json::from_str(&buffer) {
-            Err(x) => Err(format!("Error deserializing {}!\n{}", file_path, x)),
-            Ok(x) => Ok(x)
+        let mut system: System = match serde_json::from_str(&buffer) {
+            Err(x) => return Err(format!("Error deserializing {}!\n{}", file_path, x)),
+            Ok(x) => x
+        };
+        if !system.units.is_canonical() {
+            let units = system.units;
+            system.object.iter().for_each(|a| a.write().unwrap().convert_units(&units, &Units::canonical()));
+            system.units = Units::canonical();
+        }
+        match system.frame() {
+            Err(x) => Err(format!("Error loading {}!\n{}", file_path, x)),
+            Ok(x) if !x.is_inertial() => Err(format!("Error loading {}!\nBodies are in a rotating {} frame", file_path, x.name())),
+            _ => match system.check_spacecraft().and_then(|_| system.check_sunlight()).and_then(|_| system.check_tides()) {
+                Err(x) => Err(format!("Error loading {}!\n{}", file_path, x)),
+                _ => Ok(system)
+            }
         }
     }
-    pub fn fetch_from_horizons<T: AsRef<str>>(list: &[T], date: &time::Date) -> Result<System, String> {
+    pub fn fetch_from_horizons<T: AsRef<str>>(list: &[T], date: &Epoch) -> Result<System, String> {
         let mut system = System::new();
+        system.set_date(date);
         horizons_cgi::fetch_target_bodies(list, date)
             .iter().for_each(|a| {
                 system.add(a.clone())
@@ -59,43 +170,227 @@ impl System {
         Ok(system)
     }
     pub fn add(&mut self, body: Body) {
-        self.object.push(Arc::new(RwLock::new(body)))
+        self.object.push(Arc::new(RwLock::new(body)));
+        self.tangent.iter_mut().for_each(|a| a.push())
     }
     pub fn size(&self) -> usize {
         self.object.len()
     }
+    pub fn epoch(&self) -> f64 {
+        self.epoch
+    }
+    pub fn set_epoch(&mut self, epoch: f64) {
+        self.epoch = epoch
+    }
+    pub fn date(&self) -> Epoch {
+        Epoch::from_julian_date(self.epoch, TimeScale::Tdb)
+    }
+    pub fn set_date(&mut self, date: &Epoch) {
+        self.epoch = date.to(TimeScale::Tdb).julian_date()
+    }
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+    pub fn delta_time(&self) -> f64 {
+        self.delta_time
+    }
+    pub fn set_delta_time(&mut self, delta_time: f64) {
+        self.delta_time = delta_time
+    }
+    pub fn integrator(&self) -> Integrator {
+        self.integrator
+    }
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator
+    }
+    pub fn luminous(&self) -> &str {
+        &self.luminous
+    }
+    pub fn luminosity(&self) -> f64 {
+        self.luminosity
+    }
+    pub fn set_luminous(&mut self, name: &str, luminosity: f64) {
+        self.luminous = name.to_string();
+        self.luminosity = luminosity
+    }
+    pub fn set_autocheckpoint(&mut self, file_path: &str, interval: u64) {
+        self.autocheckpoint = if interval > 0 {
+            Some(AutoCheckpoint {
+                file_path: file_path.to_string(),
+                interval,
+                error: None
+            })
+        } else {
+            None
+        }
+    }
+    pub fn checkpoint_error(&self) -> Option<&str> {
+        self.autocheckpoint.as_ref().and_then(|a| a.error.as_deref())
+    }
+    pub fn written_by(&self) -> Option<&str> {
+        self.written_by.as_deref()
+    }
+    pub fn save_checkpoint(&self, file_path: &str) -> Result<(), String> {
+        checkpoint::save(self, file_path)
+    }
+    pub fn load_checkpoint(file_path: &str) -> Result<System, String> {
+        checkpoint::restore(file_path)
+    }
+    pub fn attach_recorder(&mut self, recorder: TrajectoryRecorder) {
+        self.recorder.push(recorder)
+    }
+    pub fn detach_recorders(&mut self) -> Vec<TrajectoryRecorder> {
+        std::mem::take(&mut self.recorder)
+    }
+    pub fn attach_detector(&mut self, mut detector: EventDetector) {
+        detector.record(self);
+        self.detector.push(detector)
+    }
+    pub fn detach_detectors(&mut self) -> Vec<EventDetector> {
+        std::mem::take(&mut self.detector)
+    }
+    pub fn add_tangent(&mut self, tangent: TangentVector) -> Result<usize, String> {
+        if tangent.size() != self.size() {
+            return Err(format!("Failed to add tangent vector! It has {} bodies but the system has {}",
+                tangent.size(), self.size()))
+        }
+        self.tangent.push(tangent);
+        Ok(self.tangent.len() - 1)
+    }
+    pub fn tangent(&self, index: usize) -> Option<&TangentVector> {
+        self.tangent.get(index)
+    }
+    pub fn tangent_mut(&mut self, index: usize) -> Option<&mut TangentVector> {
+        self.tangent.get_mut(index)
+    }
+    pub fn detach_tangents(&mut self) -> Vec<TangentVector> {
+        std::mem::take(&mut self.tangent)
+    }
+    pub fn linearized_accelerations(&self, tangent: &TangentVector) -> Vec<[f64; 3]> {
+        variational::linearized_accelerations(&self.gravitational_state(), &tangent.position)
+    }
+    fn gravitational_state(&self) -> Vec<(f64, [f64; 3])> {
+        self.object.iter().map(|a| {
+            let a_lock = a.read().unwrap();
+            (a_lock.mass(), a_lock.position())
+        }).collect()
+    }
+    fn gravitational_accelerations(&self) -> Vec<[f64; 3]> {
+        use rayon::prelude::*;
+        let state = self.gravitational_state();
+        state.par_iter().enumerate().map(|(i, (_, a_position))| {
+            state.iter().enumerate()
+                .filter(|(j, (b_mass, _))| *j != i && *b_mass != 0.0)
+                .fold([0.0; 3], |mut acceleration, (_, (b_mass, b_position))| {
+                    let position_difference: Vec<f64> = b_position.iter()
+                        .zip(a_position.iter())
+                        .map(|(o, s)| {o - s}).collect();
+                    let inv_r3 = position_difference.iter().map(|a| {a.powf(2.0)}).sum::<f64>().powf(-1.5) * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+                    acceleration.iter_mut()
+                        .zip(position_difference.iter())
+                        .for_each(|(a, x)| {*a += inv_r3 * b_mass * x});
+                    acceleration
+                })
+        }).collect()
+    }
     fn exchange_gravitational_forces(&mut self, delta_time: f64) {
-        use itertools::Itertools;
         use rayon::prelude::*;
-        self.object.iter().combinations(2).collect::<Vec<_>>()
-            .par_iter().for_each(|x| {
-                let a = x[0].clone();
-                let a_lock = a.read().unwrap();
-                let a_mass = a_lock.mass();
-                let a_position = a_lock.position();
-                drop(a_lock);
-                let b = x[1].clone();
-                let b_lock = b.read().unwrap();
-                let b_mass = b_lock.mass();
-                let b_position = b_lock.position();
-                drop(b_lock);
-                let position_difference: Vec<f64> = a_position.iter()
-                    .zip(b_position.iter())
-                    .map(|(s, o)| {s - o}).collect();
-                let inv_r3 = position_difference.iter().map(|a| {a.powf(2.0)}).sum::<f64>().powf(-1.5) * 2.22972471E-15;
-                let mut a_lock = a.write().unwrap();
-                a_lock.apply_acceleration(
-                    position_difference.iter().map(|x| {inv_r3 * b_mass * x}).collect(),
-                    delta_time
-                );
-                drop(a_lock);
-                let mut b_lock = b.write().unwrap();
-                b_lock.apply_acceleration(
-                    position_difference.iter().map(|x| {inv_r3 * a_mass * x}).collect(),
-                    delta_time
-                );
-                drop(b_lock)
-            })
+        if !self.tangent.is_empty() {
+            let state = self.gravitational_state();
+            self.tangent.iter_mut().for_each(|a| {
+                let acceleration = variational::linearized_accelerations(&state, &a.position);
+                a.kick(&acceleration, delta_time)
+            });
+        }
+        let mut acceleration = self.gravitational_accelerations();
+        self.add_thrust_accelerations(&mut acceleration);
+        self.add_sunlight_accelerations(&mut acceleration);
+        self.add_tidal_accelerations(&mut acceleration, delta_time);
+        self.object.par_iter().zip(acceleration.par_iter()).for_each(|(a, b)| {
+            a.write().unwrap().apply_acceleration(b.to_vec(), delta_time)
+        })
+    }
+    fn add_thrust_accelerations(&self, acceleration: &mut [[f64; 3]]) {
+        for (i, object) in self.object.iter().enumerate() {
+            let thrust = match self.thrust.get(i) {
+                Some(x) if *x != 0.0 => x * M_PER_S2_TO_AU_PER_DAY2,
+                _ => continue
+            };
+            let steering = match object.read().unwrap().spacecraft().and_then(|a| a.thruster.as_ref()) {
+                Some(x) => x.steering.clone(),
+                None => continue
+            };
+            let reference = match steering.reference() {
+                Some(x) => match self.index_of(x) {
+                    Some(y) => {
+                        let y_lock = self.object[y].read().unwrap();
+                        (y_lock.position(), y_lock.velocity())
+                    },
+                    None => continue
+                },
+                None => ([0.0; 3], [0.0; 3])
+            };
+            let body = object.read().unwrap();
+            let direction = steering.direction(
+                &vector::subtract(&body.position(), &reference.0),
+                &vector::subtract(&body.velocity(), &reference.1));
+            acceleration[i] = vector::add(&acceleration[i], &vector::scale(&direction, thrust));
+        }
+    }
+    fn add_sunlight_accelerations(&self, acceleration: &mut [[f64; 3]]) {
+        let light = match self.index_of(&self.luminous) {
+            Some(x) => x,
+            None => return
+        };
+        let (source_mass, source_position, source_velocity) = {
+            let light_lock = self.object[light].read().unwrap();
+            (light_lock.mass(), light_lock.position(), light_lock.velocity())
+        };
+        for (i, object) in self.object.iter().enumerate() {
+            let body = object.read().unwrap();
+            if i == light || (body.radiation().is_none() && body.outgassing().is_none()) {
+                continue
+            }
+            let position = vector::subtract(&body.position(), &source_position);
+            let velocity = vector::subtract(&body.velocity(), &source_velocity);
+            if let Some(x) = body.radiation() {
+                acceleration[i] = vector::add(&acceleration[i], &x.acceleration(&position, &velocity, self.luminosity));
+            }
+            if let Some(x) = body.outgassing() {
+                let gravitational_parameter = (source_mass + body.mass()) * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+                acceleration[i] = vector::add(&acceleration[i], &x.acceleration(&position, &velocity, gravitational_parameter));
+            }
+        }
+    }
+    fn add_tidal_accelerations(&self, acceleration: &mut [[f64; 3]], delta_time: f64) {
+        for (j, object) in self.object.iter().enumerate() {
+            let (mass, radius, position, velocity, mut tide) = {
+                let body = object.read().unwrap();
+                match body.tide() {
+                    Some(x) if body.radius() > 0.0 && body.mass() > 0.0 =>
+                        (body.mass(), body.radius(), body.position(), body.velocity(), *x),
+                    _ => continue
+                }
+            };
+            let spin = tide;
+            for (i, perturber) in self.object.iter().enumerate() {
+                let (perturber_mass, relative_position, relative_velocity) = {
+                    let perturber = perturber.read().unwrap();
+                    if i == j || perturber.mass() == 0.0 {
+                        continue
+                    }
+                    (perturber.mass(), vector::subtract(&perturber.position(), &position), vector::subtract(&perturber.velocity(), &velocity))
+                };
+                let extra = spin.acceleration(radius, perturber_mass, &relative_position, &relative_velocity);
+                acceleration[i] = vector::add(&acceleration[i], &extra);
+                acceleration[j] = vector::add(&acceleration[j], &vector::scale(&extra, -perturber_mass / mass));
+                tide.spin_kick(radius, mass, perturber_mass, &relative_position, &extra, delta_time);
+            }
+            object.write().unwrap().set_tide(Some(tide));
+        }
     }
     fn apply_individual_velocities(&mut self, delta_time: f64) {
         use rayon::iter::{
@@ -104,12 +399,253 @@ impl System {
         };
         self.object.par_iter_mut().for_each(|a| {
             a.clone().write().unwrap().update_position(delta_time)
-        })
+        });
+        self.tangent.iter_mut().for_each(|a| a.drift(delta_time))
+    }
+    fn advance(&mut self, delta_time: f64) {
+        self.epoch += delta_time;
+        self.elapsed += delta_time;
+        self.steps += 1;
+        let mut recorder = std::mem::take(&mut self.recorder);
+        recorder.iter_mut().for_each(|a| {
+            a.record(self)
+        });
+        self.recorder = recorder;
+        let mut detector = std::mem::take(&mut self.detector);
+        detector.iter_mut().for_each(|a| {
+            a.record(self)
+        });
+        self.detector = detector;
+        if let Some(x) = self.autocheckpoint.as_ref().filter(|a| a.error.is_none() && self.steps.is_multiple_of(a.interval)) {
+            let file_path = x.file_path.replace("{step}", &self.steps.to_string());
+            if let Err(y) = checkpoint::save(self, &file_path) {
+                self.autocheckpoint.as_mut().unwrap().error = Some(format!("Failed to write checkpoint {}!\n{}", file_path, y))
+            }
+        }
     }
     pub fn kick_drift_kick_step(&mut self, delta_time: f64) {
-        self.exchange_gravitational_forces(delta_time/2.0);
-        self.apply_individual_velocities(delta_time);
-        self.exchange_gravitational_forces(delta_time/2.0);
+        self.integrate(Integrator::KickDriftKick, delta_time);
+        self.advance(delta_time);
+    }
+    fn integrate(&mut self, integrator: Integrator, delta_time: f64) {
+        self.thrust = self.object.iter()
+            .map(|a| a.write().unwrap().spacecraft_mut().map_or(0.0, |b| b.thrust(delta_time)))
+            .collect();
+        integrator.step(self, delta_time);
+        self.thrust.clear();
+    }
+    pub fn step(&mut self) {
+        self.step_by(self.delta_time)
+    }
+    pub fn step_by(&mut self, delta_time: f64) {
+        let integrator = self.integrator;
+        let mut done = 0.0;
+        if delta_time > 0.0 {
+            while let Some((body, epoch)) = self.next_burn(self.epoch + delta_time) {
+                let split = (epoch - self.epoch).max(done);
+                if split > done {
+                    self.integrate(integrator, split - done);
+                    done = split;
+                }
+                self.execute_burn(body, self.epoch + done);
+            }
+        }
+        if delta_time != done {
+            self.integrate(integrator, delta_time - done);
+        }
+        self.advance(delta_time);
+    }
+    pub fn run_for<F>(&mut self, duration: f64, mut visit: F) -> Result<(), String>
+    where
+        F: FnMut(&mut System, usize, bool) -> Result<(), String>
+    {
+        let target = self.epoch + duration;
+        let total = (duration / self.delta_time - 1.0E-6).ceil().max(0.0) as usize;
+        for step in 1..=total {
+            if step == total {
+                self.step_by(target - self.epoch);
+            } else {
+                self.step();
+            }
+            visit(self, step, step == total)?;
+        }
+        Ok(())
+    }
+    fn next_burn(&self, before: f64) -> Option<(usize, f64)> {
+        self.object.iter().enumerate().filter_map(|(i, a)| {
+            let a_lock = a.read().unwrap();
+            a_lock.spacecraft().and_then(|x| x.next_maneuver()).map(|x| (i, x.epoch))
+        }).filter(|(_, a)| *a <= before)
+            .fold(None, |next: Option<(usize, f64)>, a| match next {
+                Some(x) if x.1 <= a.1 => Some(x),
+                _ => Some(a)
+            })
+    }
+    fn execute_burn(&mut self, body: usize, epoch: f64) {
+        let maneuver = self.object[body].read().unwrap().spacecraft().unwrap().next_maneuver().unwrap().clone();
+        let reference = match &maneuver.central {
+            Some(x) => match self.index_of(x) {
+                Some(y) => Some(self.object[y].read().unwrap().clone()),
+                None => {
+                    let mut object = self.object[body].write().unwrap();
+                    let name = object.name().to_string();
+                    let spacecraft = object.spacecraft_mut().unwrap();
+                    spacecraft.executed += 1;
+                    self.burn.push(BurnRecord {
+                        epoch,
+                        body: name,
+                        frame: maneuver.frame,
+                        delta_v: [0.0; 3],
+                        requested: vector::norm(&maneuver.delta_v),
+                        achieved: 0.0,
+                        fuel_used: 0.0,
+                        fuel_remaining: spacecraft.fuel_mass,
+                        skipped: Some(format!("central body {} not found", x))
+                    });
+                    return
+                }
+            },
+            None => None
+        };
+        let mut object = self.object[body].write().unwrap();
+        let (position, velocity) = match &reference {
+            Some(x) => (vector::subtract(&object.position(), &x.position()), vector::subtract(&object.velocity(), &x.velocity())),
+            None => (object.position(), object.velocity())
+        };
+        let delta_v = maneuver.frame.to_inertial(&maneuver.delta_v, &position, &velocity);
+        let requested = Speed::from_km_per_s(vector::norm(&delta_v));
+        let spacecraft = object.spacecraft_mut().unwrap();
+        let (achieved, fuel_used) = spacecraft.burn(requested);
+        let fuel_remaining = spacecraft.fuel_mass;
+        let delta_v = if requested.km_per_s() > 0.0 { vector::scale(&delta_v, achieved / requested) } else { delta_v };
+        object.apply_impulse(&delta_v.map(|a| Speed::from_km_per_s(a).au_per_day()));
+        self.burn.push(BurnRecord {
+            epoch,
+            body: object.name().to_string(),
+            frame: maneuver.frame,
+            delta_v,
+            requested: requested.km_per_s(),
+            achieved: achieved.km_per_s(),
+            fuel_used,
+            fuel_remaining,
+            skipped: None
+        });
+    }
+    pub fn burn_log(&self) -> &[BurnRecord] {
+        &self.burn
+    }
+    pub fn check_spacecraft(&self) -> Result<(), String> {
+        for object in self.object.iter() {
+            let a_lock = object.read().unwrap();
+            if let Some(x) = a_lock.spacecraft() {
+                if let Err(y) = x.check() {
+                    return Err(format!("Failed to check spacecraft {}!\n{}", a_lock.name(), y))
+                }
+                if let Some(y) = x.maneuver.iter().filter_map(|b| b.central.as_ref()).find(|b| self.index_of(b).is_none()) {
+                    return Err(format!("Failed to find central body {} of the maneuvers of {}!", y, a_lock.name()))
+                }
+                if let Some(y) = x.thruster.as_ref().and_then(|b| b.steering.reference()).filter(|b| self.index_of(b).is_none()) {
+                    return Err(format!("Failed to find reference body {} of the thruster of {}!", y, a_lock.name()))
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn check_sunlight(&self) -> Result<(), String> {
+        let mut lit = false;
+        for object in self.object.iter() {
+            let a_lock = object.read().unwrap();
+            if let Some(x) = a_lock.radiation() {
+                if let Err(y) = x.check() {
+                    return Err(format!("Failed to check radiation forces on {}!\n{}", a_lock.name(), y))
+                }
+                lit = true;
+            }
+            if let Some(x) = a_lock.outgassing() {
+                if let Err(y) = x.check() {
+                    return Err(format!("Failed to check outgassing of {}!\n{}", a_lock.name(), y))
+                }
+                lit = true;
+            }
+        }
+        if lit && self.index_of(&self.luminous).is_none() {
+            return Err(format!("Failed to find luminous body {} driving the radiation forces and outgassing!", self.luminous))
+        }
+        if !(self.luminosity >= 0.0 && self.luminosity.is_finite()) {
+            return Err(format!("Failed to check luminous body {}! Luminosity {} must not be negative", self.luminous, self.luminosity))
+        }
+        Ok(())
+    }
+    pub fn check_tides(&self) -> Result<(), String> {
+        for object in self.object.iter() {
+            let a_lock = object.read().unwrap();
+            if let Some(x) = a_lock.tide() {
+                if let Err(y) = x.check() {
+                    return Err(format!("Failed to check tides of {}!\n{}", a_lock.name(), y))
+                }
+                if a_lock.mass() <= 0.0 || a_lock.radius() <= 0.0 {
+                    return Err(format!("Failed to check tides of {}! A tidally deformed body needs a mass and a radius", a_lock.name()))
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        let identifier = format!("({})", name);
+        self.object.iter().position(|a| {
+            let a_lock = a.read().unwrap();
+            a_lock.name() == name || a_lock.name().ends_with(&identifier)
+        }).or_else(|| {
+            self.object.iter().position(|a| {
+                a.read().unwrap().name().split(" (").next() == Some(name)
+            })
+        })
+    }
+    pub fn find_body(&self, body: &str) -> Result<usize, String> {
+        let body = body.trim();
+        match self.index_of(body) {
+            Some(x) => Ok(x),
+            None => match body.parse::<usize>() {
+                Ok(x) if x < self.size() => Ok(x),
+                _ => Err(format!("Failed to find body {} in system!", body))
+            }
+        }
+    }
+    pub fn frame(&self) -> Result<ReferenceFrame, String> {
+        let first = match self.object.first() {
+            Some(x) => x.read().unwrap(),
+            None => return Ok(ReferenceFrame::default())
+        };
+        match self.object.iter().map(|a| a.read().unwrap()).find(|a| a.frame() != first.frame()) {
+            Some(x) => Err(format!("Failed to find a common reference frame! {} is in {} but {} is in {}",
+                first.name(), first.frame().name(), x.name(), x.frame().name())),
+            None => Ok(first.frame())
+        }
+    }
+    pub fn convert_frame(&mut self, frame: ReferenceFrame) -> Result<(), String> {
+        if !frame.is_inertial() {
+            return Err(format!("Failed to convert system! The {} frame rotates and cannot be integrated in", frame.name()))
+        }
+        self.frame()?;
+        let epoch = self.epoch;
+        self.object.iter().for_each(|a| a.write().unwrap().convert_frame(frame, epoch));
+        Ok(())
+    }
+    pub fn osculating_elements(&self, body: usize, central: usize) -> Result<OrbitalElements, String> {
+        let (body, central) = match (self.object_from_index(body), self.object_from_index(central)) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Err(format!("Failed to find bodies {} and {} in system!", body, central))
+        };
+        let body = body.read().unwrap();
+        let central = central.read().unwrap();
+        Ok(OrbitalElements::from_state(
+            (body.mass() + central.mass()) * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2,
+            &vector::subtract(&body.position(), &central.position()),
+            &vector::subtract(&body.velocity(), &central.velocity())
+        ))
+    }
+    pub fn lagrange_points(&self, primary: usize, secondary: usize) -> Result<LagrangePoints, String> {
+        LagrangePoints::of(self, primary, secondary)
     }
     pub fn object_from_index(&self, index: usize) -> Option<Arc<RwLock<Body>>> {
         if index < self.size() {
@@ -118,6 +654,78 @@ impl System {
             None
         }
     }
+    pub fn snapshot(&self) -> System {
+        let mut system = System {
+            epoch: self.epoch,
+            elapsed: self.elapsed,
+            steps: self.steps,
+            delta_time: self.delta_time,
+            integrator: self.integrator,
+            luminous: self.luminous.clone(),
+            luminosity: self.luminosity,
+            ..System::new()
+        };
+        self.object.iter().for_each(|a| system.add(a.read().unwrap().clone()));
+        system
+    }
+}
+#[cfg(test)]
+pub(crate) mod fixture {
+    use crate::units::GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+    use super::{
+        body::Body,
+        integrator::Integrator,
+        orbital_elements::OrbitalElements,
+        System
+    };
+    pub const SUN: f64 = 132712440041.93938;
+    pub fn sun() -> Body {
+        Body::new("Sun (10)".to_string(), SUN, 695700.0, [0.0; 3], [0.0; 3])
+    }
+    pub fn two_body_system() -> System {
+        let mut system = System::new();
+        system.set_epoch(2440418.5);
+        system.add(sun());
+        system.add(Body::new("Earth (399)".to_string(), 398600.435436, 6371.01, [1.0, 0.0, 0.0], [0.0, 0.0172, 0.0]));
+        system
+    }
+    pub fn sun_1969() -> Body {
+        Body::new("Sun (10)".to_string(), SUN, 695700.0,
+            [0.004494340582683912, 0.0009104614297180857, -0.00006099490045495054],
+            [-4.728900304182371e-7, 5.597222756099664e-6, -1.29597103647589e-8])
+    }
+    pub fn three_body_system() -> System {
+        let mut system = System::new();
+        system.set_epoch(2440418.5);
+        system.add(sun_1969());
+        system.add(Body::new("Earth (399)".to_string(), 398600.435436, 6371.01,
+            [0.4133060075292528, -0.9296817278172866, -0.0001236944559827514],
+            [0.01547590112466981, 0.006866255831713478, 9.70628931268737e-7]));
+        system.add(Body::new("Mars (499)".to_string(), 42828.375214, 3389.92,
+            [0.1509005281414424, -1.434525832388259, -0.03372714180567889],
+            [0.01445229001740577, 0.002626682942613087, -0.0003013214316118337]));
+        system
+    }
+    pub fn circular_planets(planet: &[(&str, f64, f64, f64, f64)]) -> System {
+        let mut system = System::new();
+        system.set_integrator(Integrator::Yoshida4);
+        system.set_delta_time(1.0);
+        system.add(sun());
+        let mu = SUN * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+        for (name, mass, radius, semi_major_axis, mean_anomaly) in planet.iter().copied() {
+            let elements = OrbitalElements {
+                semi_major_axis,
+                eccentricity: 0.0,
+                inclination: 0.0,
+                longitude_of_ascending_node: 0.0,
+                argument_of_periapsis: 0.0,
+                mean_anomaly
+            };
+            let (position, velocity) = elements.to_state(mu);
+            system.add(Body::new(name.to_string(), mass, radius, position, velocity));
+        }
+        system
+    }
 }
 #[cfg(test)]
 mod test {
@@ -135,6 +743,19 @@ mod test {
         assert_float_eq!(control.velocity(), test.velocity(), ulps <= [1,1,1]);
     }
     #[test]
+    fn pull_pair_together() {
+        let mut system = System::new();
+        system.add(Body::new("Earth".to_string(), 398600.435436, 6371.01, [0.0; 3], [0.0; 3]));
+        system.add(Body::new("Moon".to_string(), 4902.800066, 1737.4, [0.00257, 0.0, 0.0], [0.0; 3]));
+        system.kick_drift_kick_step(0.1);
+        let earth = system.object_from_index(0).unwrap();
+        let moon = system.object_from_index(1).unwrap();
+        let (earth, moon) = (earth.read().unwrap(), moon.read().unwrap());
+        assert!(earth.velocity()[0] > 0.0 && moon.velocity()[0] < 0.0);
+        assert!(moon.position()[0] - earth.position()[0] < 0.00257);
+        assert_float_eq!(398600.435436 * earth.velocity()[0] + 4902.800066 * moon.velocity()[0], 0.0, abs <= 1.0E-12);
+    }
+    #[test]
     fn save_and_load_json() -> Result <(), String> {
         let mut system = System::new();
         system.add(
@@ -178,6 +799,236 @@ mod test {
         Ok(())
     }
     #[test]
+    fn load_json_in_other_units() -> Result<(), String> {
+        let contents = r#"{
+            "epoch": 2440418.5,
+            "units": { "length": "m", "time": "s", "gravitational_mass": "m3/s2", "radius": "m" },
+            "object": [{
+                "name": "Earth (399)",
+                "gravitational_mass": 3.98600435436e14,
+                "radius": 6371010.0,
+                "position": [149597870700.0, 0.0, -74798935350.0],
+                "velocity": [0.0, 29784.7, 0.0]
+            }]
+        }"#;
+        fs::write("units_test.json", contents).unwrap();
+        let load = System::load_json("units_test.json");
+        fs::remove_file("units_test.json").unwrap();
+        let system = load?;
+        let earth = system.object_from_index(0).unwrap();
+        let earth = earth.read().unwrap();
+        assert_float_eq!(earth.mass(), 398600.435436, r2nd <= 1.0E-15);
+        assert_float_eq!(earth.radius(), 6371.01, r2nd <= 1.0E-15);
+        assert_float_eq!(earth.position(), [1.0, 0.0, -0.5], abs <= [1.0E-15; 3]);
+        assert_float_eq!(earth.velocity()[1], 29.7847 / crate::units::AU_PER_DAY_TO_KM_PER_S, r2nd <= 1.0E-15);
+        assert!(system.units.is_canonical());
+        Ok(())
+    }
+    #[test]
+    fn find_bodies_by_name_before_index() {
+        let mut system = fixture::two_body_system();
+        system.add(Body::new("Ceres (1)".to_string(), 62.6284, 469.7, [2.77, 0.0, 0.0], [0.0, 0.0103, 0.0]));
+        assert_eq!(system.find_body("1"), Ok(2));
+        assert_eq!(system.find_body(" Earth "), Ok(1));
+        assert_eq!(system.find_body("399"), Ok(1));
+        assert_eq!(system.find_body("0"), Ok(0));
+        assert!(system.find_body("5").is_err());
+        assert!(system.find_body("Vesta").is_err());
+    }
+    #[test]
+    fn reject_mixed_frames() -> Result<(), String> {
+        let mut system = System::new();
+        system.add(Body::new("Sun".to_string(), 132712440041.93938, 695700.0, [0.0; 3], [0.0; 3]));
+        system.add(Body::new("Earth".to_string(), 398600.435436, 6371.01, [1.0, 0.0, 0.0], [0.0, 0.0172, 0.0])
+            .in_frame(ReferenceFrame::Icrf));
+        assert!(system.frame().is_err());
+        assert!(system.convert_frame(ReferenceFrame::Icrf).is_err());
+        assert_eq!(system.save_json("mixed_frame_test.json"), Ok(()));
+        let load = System::load_json("mixed_frame_test.json");
+        fs::remove_file("mixed_frame_test.json").unwrap();
+        let error = load.unwrap_err();
+        assert!(error.contains("Sun is in ecliptic but Earth is in icrf"));
+        Ok(())
+    }
+    #[test]
+    fn split_steps_at_burns() {
+        use spacecraft::{
+            Maneuver,
+            ManeuverFrame,
+            Spacecraft
+        };
+        let mut spacecraft = Spacecraft::new(1000.0, 2000.0, 450.0);
+        spacecraft.plan(Maneuver::new(0.3, [1.0, 0.0, 0.0], ManeuverFrame::Inertial, None));
+        spacecraft.plan(Maneuver::new(0.5, [0.0, 0.0, 2.0], ManeuverFrame::Rtn, Some("Marker".to_string())));
+        let mut system = System::new();
+        system.set_delta_time(0.5);
+        system.add(Body::new("Marker".to_string(), 0.0, 0.0, [-1.0, 0.0, 0.0], [0.0, -0.01, 0.0]));
+        system.add(Body::new("Probe".to_string(), 0.0, 0.0, [0.0; 3], [0.0, 0.01, 0.0]).with_spacecraft(spacecraft));
+        system.step();
+        assert_eq!(system.burn_log().len(), 2);
+        assert_eq!(system.burn_log()[0].epoch, 0.3);
+        assert_eq!(system.burn_log()[1].epoch, 0.5);
+        let kick = Speed::from_km_per_s(1.0).au_per_day();
+        let probe = system.object_from_index(1).unwrap();
+        let probe = probe.read().unwrap();
+        assert_float_eq!(probe.position(), [0.2 * kick, 0.005, 0.0], abs <= [1.0E-15; 3]);
+        assert_float_eq!(probe.velocity(), [kick, 0.01, 2.0 * kick], abs <= [1.0E-15; 3]);
+        let exhaust = 450.0 * spacecraft::STANDARD_GRAVITY / 1000.0;
+        assert_float_eq!(probe.spacecraft().unwrap().mass(), 3000.0 * (-3.0 / exhaust).exp(), r2nd <= 1.0E-12);
+    }
+    #[test]
+    fn log_skipped_burns() {
+        use spacecraft::{
+            Maneuver,
+            ManeuverFrame,
+            Spacecraft
+        };
+        let mut spacecraft = Spacecraft::new(1000.0, 2000.0, 450.0);
+        spacecraft.plan(Maneuver::new(0.25, [0.0, 3.0, 4.0], ManeuverFrame::Vnb, Some("Ghost".to_string())));
+        let mut system = System::new();
+        system.set_delta_time(0.5);
+        system.add(Body::new("Probe".to_string(), 0.0, 0.0, [0.0; 3], [0.0, 0.01, 0.0]).with_spacecraft(spacecraft));
+        assert!(system.check_spacecraft().unwrap_err().contains("Ghost"));
+        system.step();
+        assert_eq!(system.burn_log().len(), 1);
+        let burn = &system.burn_log()[0];
+        assert_eq!(burn.skipped, Some("central body Ghost not found".to_string()));
+        assert_eq!((burn.epoch, burn.requested, burn.achieved, burn.fuel_remaining), (0.25, 5.0, 0.0, 2000.0));
+        assert!(burn.describe().contains("skipped"));
+        let probe = system.object_from_index(0).unwrap();
+        let probe = probe.read().unwrap();
+        assert_eq!(probe.velocity(), [0.0, 0.01, 0.0]);
+        assert_eq!(probe.spacecraft().unwrap().executed, 1);
+    }
+    #[test]
+    fn thrust_with_every_integrator() {
+        use spacecraft::{
+            Spacecraft,
+            Steering,
+            Thruster
+        };
+        let thruster = Thruster::new(0.1, 2000.0, Steering::Inertial([0.0, 1.0, 0.0]));
+        let flow = thruster.mass_flow();
+        let spacecraft = Spacecraft::new(100.0, 50.0, 300.0).with_thruster(thruster);
+        for integrator in Integrator::all() {
+            let mut system = System::new();
+            system.set_integrator(integrator);
+            system.set_delta_time(5.0);
+            system.add(Body::new("Probe".to_string(), 0.0, 0.0, [0.0; 3], [0.0; 3]).with_spacecraft(spacecraft.clone()));
+            (0..20).for_each(|_| system.step());
+            let probe = system.object_from_index(0).unwrap();
+            let probe = probe.read().unwrap();
+            let fuel = probe.spacecraft().unwrap().fuel_mass;
+            assert_float_eq!(fuel, 50.0 - flow * 100.0 * crate::units::DAY_TO_S, r2nd <= 1.0E-12);
+            let delta_v = 2000.0 * spacecraft::STANDARD_GRAVITY * (150.0 / (100.0 + fuel)).ln() / crate::units::AU_PER_DAY_TO_M_PER_S;
+            assert_float_eq!(probe.velocity(), [0.0, delta_v, 0.0], r2nd <= [1.0E-12; 3]);
+        }
+    }
+    #[test]
+    fn run_dry_within_a_step() {
+        use spacecraft::{
+            Spacecraft,
+            Steering,
+            Thruster
+        };
+        let thruster = Thruster::new(0.1, 2000.0, Steering::Inertial([0.0, 1.0, 0.0]));
+        let fuel = thruster.mass_flow() * 12.5 * crate::units::DAY_TO_S;
+        let spacecraft = Spacecraft::new(100.0, fuel, 300.0).with_thruster(thruster);
+        let run = |integrator: Integrator| {
+            let mut system = System::new();
+            system.set_integrator(integrator);
+            system.set_delta_time(5.0);
+            system.add(Body::new("Probe".to_string(), 0.0, 0.0, [0.0; 3], [0.0; 3]).with_spacecraft(spacecraft.clone()));
+            (0..4).for_each(|_| system.step());
+            let probe = system.object_from_index(0).unwrap();
+            let probe = probe.read().unwrap();
+            (probe.position(), probe.velocity(), probe.spacecraft().unwrap().fuel_mass)
+        };
+        let control = run(Integrator::KickDriftKick);
+        let delta_v = 2000.0 * spacecraft::STANDARD_GRAVITY * ((100.0 + fuel) / 100.0).ln() / crate::units::AU_PER_DAY_TO_M_PER_S;
+        assert_eq!(control.2, 0.0);
+        assert_float_eq!(control.1, [0.0, delta_v, 0.0], r2nd <= [1.0E-12; 3]);
+        let test = run(Integrator::Yoshida4);
+        assert_eq!(test.2, 0.0);
+        assert_float_eq!(test.0, control.0, r2nd <= [1.0E-12; 3]);
+        assert_float_eq!(test.1, control.1, r2nd <= [1.0E-12; 3]);
+    }
+    #[test]
+    fn push_with_sunlight() {
+        use outgassing::Outgassing;
+        use radiation::Radiation;
+        let mu = fixture::SUN * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+        let asteroid = |speed: f64| Body::new("Asteroid".to_string(), 0.0, 0.5, [1.0, 0.0, 0.0], [0.0, speed, 0.0]);
+        let orbit = |asteroid: Body| {
+            let mut system = System::new();
+            system.set_integrator(Integrator::Yoshida4);
+            system.set_delta_time(0.5);
+            system.add(fixture::sun());
+            system.add(asteroid);
+            (0..400).for_each(|_| system.step());
+            let asteroid = system.object_from_index(1).unwrap();
+            let asteroid = asteroid.read().unwrap();
+            (vector::norm(&asteroid.position()), OrbitalElements::from_state(mu, &asteroid.position(), &asteroid.velocity()))
+        };
+        let beta = 1.3 * radiation::SOLAR_RADIATION_PRESSURE * M_PER_S2_TO_AU_PER_DAY2 / mu;
+        let (distance, _) = orbit(asteroid((mu * (1.0 - beta)).sqrt()).with_radiation(Radiation::new(1.0, 1.3, 0.0)));
+        assert_float_eq!(distance, 1.0, abs <= 1.0E-8);
+        let (distance, _) = orbit(asteroid(mu.sqrt()).with_radiation(Radiation::new(1.0, 1.3, 0.0)));
+        assert!(distance > 1.0 + beta);
+        let (_, elements) = orbit(asteroid(mu.sqrt()).with_radiation(Radiation::new(0.0, 1.0, 1.0E-10)));
+        assert_float_eq!(elements.semi_major_axis - 1.0, 2.0E-10 * 200.0 / mu.sqrt(), r2nd <= 1.0E-2);
+        let (_, comet) = orbit(asteroid(mu.sqrt()).with_outgassing(Outgassing::new(0.0, 1.0E-10, 0.0)));
+        assert_float_eq!(comet.semi_major_axis, elements.semi_major_axis, abs <= 1.0E-9);
+        let (_, comet) = orbit(asteroid(mu.sqrt()).with_outgassing(Outgassing::new(0.0, 0.0, 1.0E-10)));
+        assert!(comet.inclination > 0.0 && (comet.semi_major_axis - 1.0).abs() < 1.0E-9);
+    }
+    #[test]
+    fn raise_tides_on_earth() {
+        use tide::Tide;
+        let earth = 398600.435436;
+        let moon = 4902.800066;
+        let mu = (earth + moon) * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+        let pair = |tide: Tide| {
+            let mut system = System::new();
+            system.set_integrator(Integrator::Yoshida4);
+            system.set_delta_time(0.01);
+            system.add(Body::new("Earth".to_string(), earth, 6371.01, [0.0; 3], [0.0; 3]).with_tide(tide));
+            system.add(Body::new("Moon".to_string(), moon, 1737.4, [0.0005, 0.0, 0.0], [0.0, (mu / 0.0005).sqrt(), 0.0]));
+            system
+        };
+        let momentum = |system: &System| {
+            let mut total = [0.0; 3];
+            for object in system.object.iter() {
+                let body = object.read().unwrap();
+                total = vector::add(&total, &vector::scale(&vector::cross(&body.position(), &body.velocity()), body.mass()));
+                if let Some(x) = body.tide() {
+                    let radius = body.radius() / crate::units::AU_TO_KM;
+                    total = vector::add(&total, &vector::scale(&x.spin, x.moment_of_inertia * body.mass() * radius * radius));
+                }
+            }
+            total
+        };
+        let semi_major_axis = |system: &System| {
+            let earth = system.object_from_index(0).unwrap().read().unwrap().clone();
+            let moon = system.object_from_index(1).unwrap().read().unwrap().clone();
+            OrbitalElements::from_state(mu, &vector::subtract(&moon.position(), &earth.position()),
+                &vector::subtract(&moon.velocity(), &earth.velocity())).semi_major_axis
+        };
+        let mut tidal = pair(Tide::new(0.3, 6000.0, 0.33, [0.0, 0.0, 6.3]));
+        let mut rigid = pair(Tide::new(0.3, 0.0, 0.33, [0.0, 0.0, 6.3]));
+        let initial = momentum(&tidal);
+        (0..1000).for_each(|_| {
+            tidal.step();
+            rigid.step();
+        });
+        assert_float_eq!(momentum(&tidal), initial, r2nd <= [1.0E-12; 3]);
+        assert!(semi_major_axis(&tidal) > semi_major_axis(&rigid) + 1.0E-10);
+        assert!(tidal.object_from_index(0).unwrap().read().unwrap().tide().unwrap().spin[2] < 6.3);
+        let system = pair(Tide::new(0.3, 6000.0, 0.33, [0.0, 0.0, 6.3]));
+        system.object_from_index(0).unwrap().write().unwrap().set_mass(0.0);
+        assert!(system.check_tides().is_err());
+    }
+    #[test]
     pub fn fetch_inner_planets() -> Result<(), String> {
         let mut control = System::new();
         control.add(
@@ -261,7 +1112,7 @@ mod test {
             )
         );
         let list = vec!("10", "199", "299", "399", "499");
-        let date = time::Date::try_from_ymd(1969,07,16).unwrap();
+        let date = Epoch::from_calendar(1969, 7, 16, 0, 0, 0.0, TimeScale::Tdb)?;
         let test = System::fetch_from_horizons(&list, &date).unwrap();
         test.object.iter().enumerate().for_each(|(i, a)| {
             compare(&a.read().unwrap(), &control.object_from_index(i).unwrap().read().unwrap());
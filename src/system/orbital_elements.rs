@@ -0,0 +1,175 @@
+use std::f64::consts::PI;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use super::vector;
+const EPSILON: f64 = 1.0E-11;
+fn wrap(angle: f64) -> f64 {
+    angle.rem_euclid(2.0 * PI)
+}
+fn signed_angle(from: &[f64; 3], to: &[f64; 3], axis: &[f64; 3]) -> f64 {
+    wrap(vector::dot(&vector::cross(from, to), axis).atan2(vector::dot(from, to)))
+}
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    pub mean_anomaly: f64
+}
+impl OrbitalElements {
+    pub fn from_state(gravitational_parameter: f64, position: &[f64; 3], velocity: &[f64; 3]) -> OrbitalElements {
+        let radius = vector::norm(position);
+        let speed_squared = vector::dot(velocity, velocity);
+        let angular_momentum = vector::cross(position, velocity);
+        let normal = vector::normalize(&angular_momentum);
+        let eccentricity_vector = vector::scale(
+            &vector::subtract(
+                &vector::scale(position, speed_squared - gravitational_parameter / radius),
+                &vector::scale(velocity, vector::dot(position, velocity))
+            ),
+            1.0 / gravitational_parameter
+        );
+        let eccentricity = vector::norm(&eccentricity_vector);
+        let semi_major_axis = 1.0 / (2.0 / radius - speed_squared / gravitational_parameter);
+        let inclination = angular_momentum[0].hypot(angular_momentum[1]).atan2(angular_momentum[2]);
+        let node_vector = [-angular_momentum[1], angular_momentum[0], 0.0];
+        let node = if vector::norm(&node_vector) > EPSILON * vector::norm(&angular_momentum) {
+            vector::normalize(&node_vector)
+        } else {
+            [1.0, 0.0, 0.0]
+        };
+        let periapsis = if eccentricity > EPSILON {
+            vector::scale(&eccentricity_vector, 1.0 / eccentricity)
+        } else {
+            node
+        };
+        let true_anomaly = signed_angle(&periapsis, position, &normal);
+        OrbitalElements {
+            semi_major_axis,
+            eccentricity,
+            inclination,
+            longitude_of_ascending_node: wrap(node[1].atan2(node[0])),
+            argument_of_periapsis: signed_angle(&node, &periapsis, &normal),
+            mean_anomaly: OrbitalElements::mean_from_true(eccentricity, true_anomaly)
+        }
+    }
+    fn mean_from_true(eccentricity: f64, true_anomaly: f64) -> f64 {
+        let half = true_anomaly / 2.0;
+        if eccentricity < 1.0 {
+            let eccentric = 2.0 * (((1.0 - eccentricity) / (1.0 + eccentricity)).sqrt() * half.sin()).atan2(half.cos());
+            wrap(eccentric - eccentricity * eccentric.sin())
+        } else {
+            let hyperbolic = 2.0 * (((eccentricity - 1.0) / (eccentricity + 1.0)).sqrt() * half.tan()).atanh();
+            eccentricity * hyperbolic.sinh() - hyperbolic
+        }
+    }
+    pub fn eccentric_anomaly(&self) -> f64 {
+        let mean = self.mean_anomaly;
+        let eccentricity = self.eccentricity;
+        if eccentricity < 1.0 {
+            let mean = (mean + PI).rem_euclid(2.0 * PI) - PI;
+            let mut eccentric = if eccentricity > 0.8 { PI.copysign(mean) } else { mean };
+            for _ in 0..64 {
+                let change = (eccentric - eccentricity * eccentric.sin() - mean) / (1.0 - eccentricity * eccentric.cos());
+                eccentric -= change;
+                if change.abs() < 1.0E-15 {
+                    break
+                }
+            }
+            eccentric
+        } else {
+            let mut hyperbolic = (mean / eccentricity).asinh();
+            for _ in 0..64 {
+                let change = (eccentricity * hyperbolic.sinh() - hyperbolic - mean) / (eccentricity * hyperbolic.cosh() - 1.0);
+                hyperbolic -= change;
+                if change.abs() < 1.0E-15 {
+                    break
+                }
+            }
+            hyperbolic
+        }
+    }
+    pub fn true_anomaly(&self) -> f64 {
+        let anomaly = self.eccentric_anomaly();
+        let eccentricity = self.eccentricity;
+        if eccentricity < 1.0 {
+            wrap(2.0 * ((1.0 + eccentricity).sqrt() * (anomaly / 2.0).sin())
+                .atan2((1.0 - eccentricity).sqrt() * (anomaly / 2.0).cos()))
+        } else {
+            2.0 * (((eccentricity + 1.0) / (eccentricity - 1.0)).sqrt() * (anomaly / 2.0).tanh()).atan()
+        }
+    }
+    pub fn to_state(&self, gravitational_parameter: f64) -> ([f64; 3], [f64; 3]) {
+        let true_anomaly = self.true_anomaly();
+        let semi_latus_rectum = self.semi_major_axis * (1.0 - self.eccentricity.powf(2.0));
+        let radius = semi_latus_rectum / (1.0 + self.eccentricity * true_anomaly.cos());
+        let speed = (gravitational_parameter / semi_latus_rectum).sqrt();
+        let position = [radius * true_anomaly.cos(), radius * true_anomaly.sin(), 0.0];
+        let velocity = [-speed * true_anomaly.sin(), speed * (self.eccentricity + true_anomaly.cos()), 0.0];
+        (self.rotate_from_perifocal(&position), self.rotate_from_perifocal(&velocity))
+    }
+    fn rotate_from_perifocal(&self, a: &[f64; 3]) -> [f64; 3] {
+        let (sin_o, cos_o) = self.longitude_of_ascending_node.sin_cos();
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+        let (sin_w, cos_w) = self.argument_of_periapsis.sin_cos();
+        [
+            (cos_o * cos_w - sin_o * sin_w * cos_i) * a[0] + (-cos_o * sin_w - sin_o * cos_w * cos_i) * a[1],
+            (sin_o * cos_w + cos_o * sin_w * cos_i) * a[0] + (-sin_o * sin_w + cos_o * cos_w * cos_i) * a[1],
+            (sin_w * sin_i) * a[0] + (cos_w * sin_i) * a[1]
+        ]
+    }
+    pub fn mean_motion(&self, gravitational_parameter: f64) -> f64 {
+        (gravitational_parameter / self.semi_major_axis.abs().powf(3.0)).sqrt()
+    }
+    pub fn period(&self, gravitational_parameter: f64) -> f64 {
+        2.0 * PI / self.mean_motion(gravitational_parameter)
+    }
+    pub fn longitude_of_periapsis(&self) -> f64 {
+        wrap(self.longitude_of_ascending_node + self.argument_of_periapsis)
+    }
+    pub fn mean_longitude(&self) -> f64 {
+        wrap(self.longitude_of_periapsis() + self.mean_anomaly)
+    }
+    pub fn propagate(&self, gravitational_parameter: f64, delta_time: f64) -> OrbitalElements {
+        let mean_anomaly = self.mean_anomaly + self.mean_motion(gravitational_parameter) * delta_time;
+        OrbitalElements {
+            mean_anomaly: if self.eccentricity < 1.0 { wrap(mean_anomaly) } else { mean_anomaly },
+            ..*self
+        }
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    const MU: f64 = 2.959122082855911E-4;
+    #[test]
+    fn round_trip_state() -> Result<(), String> {
+        [
+            ([0.4133060075292528, -0.9296817278172866, -0.0001236944559827514], [0.01547590112466981, 0.006866255831713478, 9.70628931268737e-7]),
+            ([1.592773884234155, -2.463766259162856, -0.3653478442536497], [0.008151272151318315, 0.005008550137955732, -0.001362500743964101]),
+            ([1.0, 0.0, 0.0], [0.0, 0.03, 0.001]),
+            ([1.0, 0.0, 0.0], [0.0, -0.0172, 0.0])
+        ].iter().for_each(|(position, velocity)| {
+            let elements = OrbitalElements::from_state(MU, position, velocity);
+            let (test_position, test_velocity) = elements.to_state(MU);
+            assert_float_eq!(*position, test_position, abs <= [1.0E-12; 3]);
+            assert_float_eq!(*velocity, test_velocity, abs <= [1.0E-14; 3]);
+        });
+        Ok(())
+    }
+    #[test]
+    fn earth_elements() -> Result<(), String> {
+        let elements = OrbitalElements::from_state(MU,
+            &[0.4133060075292528, -0.9296817278172866, -0.0001236944559827514],
+            &[0.01547590112466981, 0.006866255831713478, 9.70628931268737e-7]);
+        assert_float_eq!(elements.semi_major_axis, 1.0, abs <= 0.01);
+        assert_float_eq!(elements.eccentricity, 0.0167, abs <= 0.005);
+        assert_float_eq!(elements.period(MU), 365.25, abs <= 5.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{
+    Deserialize,
+    Serialize
+};
+use super::{
+    orbital_elements::OrbitalElements,
+    vector
+};
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sublimation {
+    pub alpha: f64,
+    pub r0: f64,
+    pub m: f64,
+    pub n: f64,
+    pub k: f64
+}
+impl Default for Sublimation {
+    fn default() -> Sublimation {
+        Sublimation {
+            alpha: 0.1112620426,
+            r0: 2.808,
+            m: 2.15,
+            n: 5.093,
+            k: 4.6142
+        }
+    }
+}
+impl Sublimation {
+    pub fn g(&self, distance: f64) -> f64 {
+        let ratio = distance / self.r0;
+        self.alpha * ratio.powf(-self.m) * (1.0 + ratio.powf(self.n)).powf(-self.k)
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Outgassing {
+    pub a1: f64,
+    pub a2: f64,
+    pub a3: f64,
+    #[serde(default)]
+    pub delay: f64,
+    #[serde(default)]
+    pub sublimation: Sublimation
+}
+impl Outgassing {
+    pub fn new(a1: f64, a2: f64, a3: f64) -> Outgassing {
+        Outgassing {
+            a1,
+            a2,
+            a3,
+            delay: 0.0,
+            sublimation: Sublimation::default()
+        }
+    }
+    pub fn with_delay(mut self, delay: f64) -> Outgassing {
+        self.delay = delay;
+        self
+    }
+    pub fn with_sublimation(mut self, sublimation: Sublimation) -> Outgassing {
+        self.sublimation = sublimation;
+        self
+    }
+    pub fn check(&self) -> Result<(), String> {
+        if ![self.a1, self.a2, self.a3, self.delay].iter().all(|a| a.is_finite()) {
+            return Err(format!("Non-gravitational parameters A1 {}, A2 {}, A3 {} and DT {} must be finite",
+                self.a1, self.a2, self.a3, self.delay))
+        }
+        let law = &self.sublimation;
+        if !(law.alpha > 0.0 && law.r0 > 0.0) || ![law.m, law.n, law.k].iter().all(|a| a.is_finite()) {
+            return Err(format!("Sublimation law with alpha {} and r0 {} must be positive and finite", law.alpha, law.r0))
+        }
+        Ok(())
+    }
+    pub fn delayed_distance(&self, position: &[f64; 3], velocity: &[f64; 3], gravitational_parameter: f64) -> f64 {
+        if self.delay == 0.0 || gravitational_parameter <= 0.0 {
+            return vector::norm(position)
+        }
+        let (earlier, _) = OrbitalElements::from_state(gravitational_parameter, position, velocity)
+            .propagate(gravitational_parameter, -self.delay)
+            .to_state(gravitational_parameter);
+        vector::norm(&earlier)
+    }
+    pub fn acceleration(&self, position: &[f64; 3], velocity: &[f64; 3], gravitational_parameter: f64) -> [f64; 3] {
+        let distance = vector::norm(position);
+        let normal = vector::cross(position, velocity);
+        if distance == 0.0 || vector::norm(&normal) == 0.0 {
+            return [0.0; 3]
+        }
+        let g = self.sublimation.g(self.delayed_distance(position, velocity, gravitational_parameter));
+        let radial = vector::scale(position, 1.0 / distance);
+        let normal = vector::normalize(&normal);
+        let transverse = vector::cross(&normal, &radial);
+        vector::scale(&vector::add(&vector::add(&vector::scale(&radial, self.a1), &vector::scale(&transverse, self.a2)),
+            &vector::scale(&normal, self.a3)), g)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    const MU: f64 = 2.959122082855911E-4;
+    #[test]
+    fn outgas_along_the_orbit() {
+        let law = Sublimation::default();
+        assert_float_eq!(law.g(1.0), 1.0, abs <= 1.0E-4);
+        assert!(law.g(5.0) < 0.01 * law.g(2.0));
+        let comet = Outgassing::new(2.0E-8, 1.0E-9, -3.0E-10);
+        let acceleration = comet.acceleration(&[0.0, 2.0, 0.0], &[-0.01, 0.0, 0.0], MU);
+        assert_float_eq!(acceleration, [-1.0E-9 * law.g(2.0), 2.0E-8 * law.g(2.0), -3.0E-10 * law.g(2.0)], abs <= [1.0E-24; 3]);
+        let wider = comet.with_sublimation(Sublimation {
+            r0: 5.0,
+            ..law
+        });
+        assert!(wider.acceleration(&[0.0, 2.0, 0.0], &[-0.01, 0.0, 0.0], MU)[1] > acceleration[1]);
+        assert!(Outgassing::new(f64::NAN, 0.0, 0.0).check().is_err());
+        assert!(Outgassing::new(0.0, 0.0, 0.0).with_sublimation(Sublimation {
+            r0: 0.0,
+            ..law
+        }).check().is_err());
+        assert!(Outgassing::new(0.0, 0.0, 0.0).with_delay(f64::INFINITY).check().is_err());
+    }
+    #[test]
+    fn outgas_at_an_earlier_distance() {
+        let comet = Outgassing::new(1.0E-8, 0.0, 0.0);
+        let (position, velocity) = ([0.0, 1.0, 0.0], [-0.02, 0.005, 0.0]);
+        assert_eq!(comet.delayed_distance(&position, &velocity, MU), 1.0);
+        let delayed = comet.with_delay(10.0);
+        let (earlier, _) = OrbitalElements::from_state(MU, &position, &velocity).propagate(MU, -10.0).to_state(MU);
+        assert_float_eq!(delayed.delayed_distance(&position, &velocity, MU), vector::norm(&earlier), r2nd <= 1.0E-15);
+        assert!(delayed.delayed_distance(&position, &velocity, MU) < 1.0);
+        let law = Sublimation::default();
+        let g = law.g(delayed.delayed_distance(&position, &velocity, MU));
+        assert_float_eq!(delayed.acceleration(&position, &velocity, MU), [0.0, 1.0E-8 * g, 0.0], abs <= [1.0E-22; 3]);
+        assert!(delayed.acceleration(&position, &velocity, MU)[1] > comet.acceleration(&position, &velocity, MU)[1]);
+    }
+}
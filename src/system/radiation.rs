@@ -0,0 +1,64 @@
+use serde::{
+    Deserialize,
+    Serialize
+};
+use crate::units::M_PER_S2_TO_AU_PER_DAY2;
+use super::vector;
+pub const SOLAR_RADIATION_PRESSURE: f64 = 1361.0 / 299792458.0;
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Radiation {
+    pub area_to_mass: f64,
+    pub reflectivity: f64,
+    #[serde(default)]
+    pub yarkovsky: f64
+}
+impl Radiation {
+    pub fn new(area_to_mass: f64, reflectivity: f64, yarkovsky: f64) -> Radiation {
+        Radiation {
+            area_to_mass,
+            reflectivity,
+            yarkovsky
+        }
+    }
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.area_to_mass >= 0.0 && self.area_to_mass.is_finite()) {
+            return Err(format!("Area to mass ratio {} must not be negative", self.area_to_mass))
+        }
+        if !(0.0..=2.0).contains(&self.reflectivity) {
+            return Err(format!("Reflectivity coefficient {} must be between 0 and 2", self.reflectivity))
+        }
+        if !self.yarkovsky.is_finite() {
+            return Err(format!("Yarkovsky parameter {} must be finite", self.yarkovsky))
+        }
+        Ok(())
+    }
+    pub fn acceleration(&self, position: &[f64; 3], velocity: &[f64; 3], luminosity: f64) -> [f64; 3] {
+        let distance = vector::norm(position);
+        if distance == 0.0 {
+            return [0.0; 3]
+        }
+        let flux = luminosity / (distance * distance);
+        let pressure = self.reflectivity * self.area_to_mass * SOLAR_RADIATION_PRESSURE * M_PER_S2_TO_AU_PER_DAY2 * flux;
+        let radial = vector::scale(position, 1.0 / distance);
+        let transverse = vector::cross(&vector::cross(position, velocity), position);
+        let transverse = if vector::norm(&transverse) > 0.0 { vector::normalize(&transverse) } else { [0.0; 3] };
+        vector::add(&vector::scale(&radial, pressure), &vector::scale(&transverse, self.yarkovsky * flux))
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    #[test]
+    fn push_away_and_along() {
+        let radiation = Radiation::new(0.01, 1.5, 0.0);
+        let acceleration = radiation.acceleration(&[2.0, 0.0, 0.0], &[0.0, 0.01, 0.0], 1.0);
+        assert_float_eq!(acceleration[0], 1.5 * 0.01 * SOLAR_RADIATION_PRESSURE * M_PER_S2_TO_AU_PER_DAY2 / 4.0, r2nd <= 1.0E-12);
+        assert_float_eq!(acceleration[1], 0.0, abs <= 1.0E-30);
+        let yarkovsky = Radiation::new(0.0, 1.0, -2.0E-14).acceleration(&[0.0, 2.0, 0.0], &[-0.01, 0.0, 0.0], 1.0);
+        assert_float_eq!(yarkovsky, [0.5E-14, 0.0, 0.0], abs <= [1.0E-28; 3]);
+        assert!(Radiation::new(-1.0, 1.0, 0.0).check().is_err());
+        assert!(Radiation::new(1.0, 2.5, 0.0).check().is_err());
+        assert!(Radiation::new(1.0, 1.3, f64::NAN).check().is_err());
+    }
+}
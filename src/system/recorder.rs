@@ -0,0 +1,384 @@
+use std::{
+    fs,
+    io::{
+        BufReader,
+        BufWriter,
+        Write
+    }
+};
+use super::{
+    binary,
+    frame::Frame,
+    text::{
+        csv_field,
+        write_text
+    },
+    System
+};
+pub const MAGIC: &[u8; 4] = b"NBTR";
+const VERSION: u32 = 2;
+pub const UNITS: &str = "position AU, velocity AU/d, time JD";
+fn csv_header(name: &[String], units: &str, frame: &str, epoch: f64) -> String {
+    format!("# bodies: {}\n# units: {}\n# frame: {}\n# epoch: {}\ntime,body,x,y,z,vx,vy,vz\n",
+        name.iter().map(|a| csv_field(a)).collect::<Vec<_>>().join(";"), units, frame, epoch)
+}
+fn csv_row(time: f64, name: &str, position: &[f64; 3], velocity: &[f64; 3]) -> String {
+    format!("{},{},{},{},{},{},{},{}\n",
+        time, csv_field(name),
+        position[0], position[1], position[2],
+        velocity[0], velocity[1], velocity[2]
+    )
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrajectoryFormat {
+    Csv,
+    Binary
+}
+impl TrajectoryFormat {
+    pub fn from_path(file_path: &str) -> TrajectoryFormat {
+        if file_path.to_lowercase().ends_with(".csv") {
+            TrajectoryFormat::Csv
+        } else {
+            TrajectoryFormat::Binary
+        }
+    }
+}
+#[derive(Debug)]
+pub struct TrajectoryRecorder {
+    file_path: String,
+    format: TrajectoryFormat,
+    selection: Vec<usize>,
+    name: Vec<String>,
+    frame: Frame,
+    cadence: usize,
+    count: usize,
+    writer: BufWriter<fs::File>,
+    error: Option<String>
+}
+impl TrajectoryRecorder {
+    pub fn create(
+        file_path: &str,
+        format: TrajectoryFormat,
+        system: &System,
+        selection: &[usize],
+        frame: Frame,
+        cadence: usize
+    ) -> Result<TrajectoryRecorder, String> {
+        if cadence == 0 {
+            return Err(format!("Failed to create {}!\nCadence must be at least one step", file_path))
+        }
+        let selection: Vec<usize> = if selection.is_empty() {
+            (0..system.size()).collect()
+        } else {
+            selection.to_vec()
+        };
+        let mut name = Vec::new();
+        for index in selection.iter() {
+            match system.object_from_index(*index) {
+                Some(x) => name.push(x.read().unwrap().name().to_string()),
+                None => return Err(format!("Failed to create {}!\nNo body at index {}", file_path, index))
+            }
+        }
+        let writer = match fs::File::create(file_path) {
+            Err(x) => return Err(format!("Failed creating {}!\n{}", file_path, x)),
+            Ok(x) => BufWriter::new(x)
+        };
+        let mut recorder = TrajectoryRecorder {
+            file_path: file_path.to_string(),
+            format,
+            selection,
+            name,
+            frame,
+            cadence,
+            count: 0,
+            writer,
+            error: None
+        };
+        recorder.write_header(&frame.describe(system), system.epoch())?;
+        recorder.sample(system)?;
+        Ok(recorder)
+    }
+    fn write_header(&mut self, frame: &str, epoch: f64) -> Result<(), String> {
+        match self.format {
+            TrajectoryFormat::Csv => {
+                let header = csv_header(&self.name, UNITS, frame, epoch);
+                match self.writer.write_all(header.as_bytes()) {
+                    Err(x) => Err(format!("Failed writing to {}!\n{}", self.file_path, x)),
+                    _ => Ok(())
+                }
+            },
+            TrajectoryFormat::Binary => {
+                if let Err(x) = self.writer.write_all(MAGIC) {
+                    return Err(format!("Failed writing to {}!\n{}", self.file_path, x))
+                }
+                binary::write_u32(&mut self.writer, VERSION)?;
+                binary::write_str(&mut self.writer, UNITS)?;
+                binary::write_str(&mut self.writer, frame)?;
+                binary::write_f64(&mut self.writer, epoch)?;
+                binary::write_u32(&mut self.writer, self.name.len() as u32)?;
+                for name in self.name.iter() {
+                    binary::write_str(&mut self.writer, name)?;
+                }
+                Ok(())
+            }
+        }
+    }
+    pub fn sample(&mut self, system: &System) -> Result<(), String> {
+        let time = system.epoch();
+        let transform = self.frame.transform(system)?;
+        if self.format == TrajectoryFormat::Binary {
+            binary::write_f64(&mut self.writer, time)?;
+        }
+        for (index, name) in self.selection.iter().zip(self.name.iter()) {
+            let body = match system.object_from_index(*index) {
+                Some(x) => x,
+                None => return Err(format!("Failed to sample {}!\nNo body at index {}", name, index))
+            };
+            let body = body.read().unwrap();
+            let position = transform.position(&body.position());
+            let velocity = transform.velocity(&body.position(), &body.velocity());
+            match self.format {
+                TrajectoryFormat::Csv => {
+                    let line = csv_row(time, name, &position, &velocity);
+                    if let Err(x) = self.writer.write_all(line.as_bytes()) {
+                        return Err(format!("Failed writing to {}!\n{}", self.file_path, x))
+                    }
+                },
+                TrajectoryFormat::Binary => {
+                    for value in position.iter().chain(velocity.iter()) {
+                        binary::write_f64(&mut self.writer, *value)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn record(&mut self, system: &System) {
+        if self.error.is_some() {
+            return
+        }
+        self.count += 1;
+        if self.count.is_multiple_of(self.cadence) {
+            if let Err(x) = self.sample(system) {
+                self.error = Some(x)
+            }
+        }
+    }
+    pub fn finish(mut self) -> Result<(), String> {
+        if let Some(x) = self.error.take() {
+            return Err(x)
+        }
+        match self.writer.flush() {
+            Err(x) => Err(format!("Failed writing to {}!\n{}", self.file_path, x)),
+            _ => Ok(())
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrajectorySample {
+    pub time: f64,
+    pub position: Vec<[f64; 3]>,
+    pub velocity: Vec<[f64; 3]>
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trajectory {
+    pub units: String,
+    pub frame: String,
+    pub epoch: f64,
+    pub name: Vec<String>,
+    pub sample: Vec<TrajectorySample>
+}
+impl Trajectory {
+    pub fn from_system(system: &System, frame: Frame) -> Result<Trajectory, String> {
+        let transform = frame.transform(system)?;
+        let mut name = Vec::new();
+        let mut position = Vec::new();
+        let mut velocity = Vec::new();
+        (0..system.size()).for_each(|i| {
+            let body = system.object_from_index(i).unwrap();
+            let body = body.read().unwrap();
+            name.push(body.name().to_string());
+            position.push(transform.position(&body.position()));
+            velocity.push(transform.velocity(&body.position(), &body.velocity()));
+        });
+        Ok(Trajectory {
+            units: UNITS.to_string(),
+            frame: frame.describe(system),
+            epoch: system.epoch(),
+            name,
+            sample: vec!(TrajectorySample {
+                time: system.epoch(),
+                position,
+                velocity
+            })
+        })
+    }
+    pub fn save_csv(&self, file_path: &str) -> Result<(), String> {
+        let mut contents = csv_header(&self.name, &self.units, &self.frame, self.epoch);
+        for sample in self.sample.iter() {
+            for (i, name) in self.name.iter().enumerate() {
+                contents.push_str(&csv_row(sample.time, name, &sample.position[i], &sample.velocity[i]));
+            }
+        }
+        write_text(file_path, &contents)
+    }
+    pub fn load_binary(file_path: &str) -> Result<Trajectory, String> {
+        use std::io::Read;
+        let mut reader = match fs::File::open(file_path) {
+            Err(x) => return Err(format!("Failed reading {}!\n{}", file_path, x)),
+            Ok(x) => BufReader::new(x)
+        };
+        let mut magic = [0u8; 4];
+        if let Err(x) = reader.read_exact(&mut magic) {
+            return Err(format!("Failed reading {}!\n{}", file_path, x))
+        }
+        if &magic != MAGIC {
+            return Err(format!("Failed reading {}!\nNot a trajectory file", file_path))
+        }
+        let version = binary::read_u32(&mut reader)?;
+        if version == 0 || version > VERSION {
+            return Err(format!("Failed reading {}!\nUnsupported trajectory version {}", file_path, version))
+        }
+        let units = binary::read_str(&mut reader)?;
+        let frame = if version > 1 {
+            binary::read_str(&mut reader)?
+        } else {
+            Frame::Inertial.describe(&System::new())
+        };
+        let epoch = binary::read_f64(&mut reader)?;
+        let count = binary::read_u32(&mut reader)? as usize;
+        let mut name = Vec::new();
+        for _ in 0..count {
+            name.push(binary::read_str(&mut reader)?);
+        }
+        let mut sample = Vec::new();
+        while let Some(time) = binary::read_f64_or_end(&mut reader)? {
+            let mut position = Vec::new();
+            let mut velocity = Vec::new();
+            for _ in 0..count {
+                let mut state = [0.0; 6];
+                for value in state.iter_mut() {
+                    *value = match binary::read_f64(&mut reader) {
+                        Err(x) => return Err(format!("Failed reading {}!\nTruncated sample at {}\n{}", file_path, time, x)),
+                        Ok(x) => x
+                    };
+                }
+                position.push([state[0], state[1], state[2]]);
+                velocity.push([state[3], state[4], state[5]]);
+            }
+            sample.push(TrajectorySample {
+                time,
+                position,
+                velocity
+            });
+        }
+        Ok(Trajectory {
+            units,
+            frame,
+            epoch,
+            name,
+            sample
+        })
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{
+        body::Body,
+        fixture::two_body_system
+    };
+    #[test]
+    fn record_binary() -> Result<(), String> {
+        let mut system = two_body_system();
+        let recorder = TrajectoryRecorder::create("record_test.bin", TrajectoryFormat::Binary, &system, &[1], Frame::Inertial, 2)?;
+        system.attach_recorder(recorder);
+        (0..4).for_each(|_| system.kick_drift_kick_step(0.5));
+        for recorder in system.detach_recorders() {
+            recorder.finish()?;
+        }
+        let trajectory = Trajectory::load_binary("record_test.bin");
+        fs::remove_file("record_test.bin").unwrap();
+        let trajectory = trajectory?;
+        assert_eq!(trajectory.name, vec!("Earth (399)".to_string()));
+        assert_eq!(trajectory.epoch, 2440418.5);
+        assert_eq!(trajectory.sample.len(), 3);
+        assert_eq!(trajectory.sample[2].time, 2440420.5);
+        let earth = system.object_from_index(1).unwrap();
+        assert_eq!(trajectory.sample[2].position[0], earth.read().unwrap().position());
+        Ok(())
+    }
+    #[test]
+    fn record_csv() -> Result<(), String> {
+        let mut system = two_body_system();
+        let recorder = TrajectoryRecorder::create("record_test.csv", TrajectoryFormat::Csv, &system, &[], Frame::Inertial, 1)?;
+        system.attach_recorder(recorder);
+        (0..3).for_each(|_| system.kick_drift_kick_step(1.0));
+        for recorder in system.detach_recorders() {
+            recorder.finish()?;
+        }
+        let contents = fs::read_to_string("record_test.csv");
+        fs::remove_file("record_test.csv").unwrap();
+        let contents = match contents {
+            Err(x) => return Err(format!("Failed reading record_test.csv!\n{}", x)),
+            Ok(x) => x
+        };
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "# bodies: Sun (10);Earth (399)");
+        assert_eq!(lines[2], "# frame: inertial");
+        assert_eq!(lines[4], "time,body,x,y,z,vx,vy,vz");
+        assert_eq!(lines.len(), 5 + 4 * 2);
+        assert!(lines[12].starts_with("2440421.5,Earth (399),"));
+        Ok(())
+    }
+    #[test]
+    fn quote_names_and_reject_truncated_samples() -> Result<(), String> {
+        let mut system = two_body_system();
+        system.add(Body::new("Ceres, \"A801 AA\"".to_string(), 62.6284, 469.7, [2.77, 0.0, 0.0], [0.0, 0.0103, 0.0]));
+        let trajectory = Trajectory::from_system(&system, Frame::Inertial)?;
+        trajectory.save_csv("record_quote_test.csv")?;
+        let contents = fs::read_to_string("record_quote_test.csv");
+        fs::remove_file("record_quote_test.csv").unwrap();
+        let contents = contents.unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "# bodies: Sun (10);Earth (399);\"Ceres, \"\"A801 AA\"\"\"");
+        assert!(lines[7].starts_with("2440418.5,\"Ceres, \"\"A801 AA\"\"\",2.77,"));
+        let recorder = TrajectoryRecorder::create("record_truncated_test.bin", TrajectoryFormat::Binary, &system, &[], Frame::Inertial, 1)?;
+        system.attach_recorder(recorder);
+        system.kick_drift_kick_step(1.0);
+        for recorder in system.detach_recorders() {
+            recorder.finish()?;
+        }
+        let whole = fs::read("record_truncated_test.bin").unwrap();
+        let sample = 8 + 3 * 6 * 8;
+        fs::write("record_truncated_test.bin", &whole[..whole.len() - sample + 3]).unwrap();
+        let truncated = Trajectory::load_binary("record_truncated_test.bin");
+        fs::write("record_truncated_test.bin", &whole[..whole.len() - sample]).unwrap();
+        let shortened = Trajectory::load_binary("record_truncated_test.bin");
+        fs::remove_file("record_truncated_test.bin").unwrap();
+        assert!(truncated.is_err());
+        assert_eq!(shortened?.sample.len(), 1);
+        Ok(())
+    }
+    #[test]
+    fn record_body_centred() -> Result<(), String> {
+        let mut system = two_body_system();
+        let frame = Frame::parse(&system, "body:Sun")?;
+        let recorder = TrajectoryRecorder::create("record_frame_test.bin", TrajectoryFormat::Binary, &system, &[], frame, 1)?;
+        system.attach_recorder(recorder);
+        (0..3).for_each(|_| system.kick_drift_kick_step(1.0));
+        for recorder in system.detach_recorders() {
+            recorder.finish()?;
+        }
+        let trajectory = Trajectory::load_binary("record_frame_test.bin");
+        fs::remove_file("record_frame_test.bin").unwrap();
+        let trajectory = trajectory?;
+        assert_eq!(trajectory.frame, "centred on Sun (10)");
+        let sun = system.object_from_index(0).unwrap();
+        let earth = system.object_from_index(1).unwrap();
+        let relative = super::super::vector::subtract(&earth.read().unwrap().position(), &sun.read().unwrap().position());
+        assert_eq!(trajectory.sample[3].position[0], [0.0; 3]);
+        assert_eq!(trajectory.sample[3].position[1], relative);
+        Ok(())
+    }
+}
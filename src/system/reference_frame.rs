@@ -0,0 +1,175 @@
+use std::f64::consts::PI;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use super::vector;
+use crate::timescale::J2000;
+pub const OBLIQUITY_J2000: f64 = 84381.448 / 3600.0 * PI / 180.0;
+pub const INVARIABLE_INCLINATION: f64 = 1.578694 * PI / 180.0;
+pub const INVARIABLE_NODE: f64 = 107.582222 * PI / 180.0;
+type Matrix = [[f64; 3]; 3];
+fn rotate_x(angle: f64) -> Matrix {
+    let (sin, cos) = angle.sin_cos();
+    [[1.0, 0.0, 0.0], [0.0, cos, -sin], [0.0, sin, cos]]
+}
+fn rotate_z(angle: f64) -> Matrix {
+    let (sin, cos) = angle.sin_cos();
+    [[cos, -sin, 0.0], [sin, cos, 0.0], [0.0, 0.0, 1.0]]
+}
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[0.0; 3]; 3];
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    product
+}
+fn apply(a: &Matrix, b: &[f64; 3]) -> [f64; 3] {
+    [vector::dot(&a[0], b), vector::dot(&a[1], b), vector::dot(&a[2], b)]
+}
+fn apply_transposed(a: &Matrix, b: &[f64; 3]) -> [f64; 3] {
+    (0..3).fold([0.0; 3], |sum, i| vector::add(&sum, &vector::scale(&a[i], b[i])))
+}
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BodyRotation {
+    pub pole_right_ascension: f64,
+    pub pole_declination: f64,
+    pub prime_meridian: f64,
+    pub rotation_rate: f64
+}
+impl BodyRotation {
+    pub fn new(pole_right_ascension: f64, pole_declination: f64, prime_meridian: f64, rotation_rate: f64) -> BodyRotation {
+        BodyRotation {
+            pole_right_ascension,
+            pole_declination,
+            prime_meridian,
+            rotation_rate
+        }
+    }
+    pub fn earth() -> BodyRotation {
+        BodyRotation::new(0.0, 90.0, 190.147, 360.9856235)
+    }
+    pub fn pole(&self) -> [f64; 3] {
+        let (sin_ra, cos_ra) = self.pole_right_ascension.to_radians().sin_cos();
+        let (sin_dec, cos_dec) = self.pole_declination.to_radians().sin_cos();
+        [cos_dec * cos_ra, cos_dec * sin_ra, sin_dec]
+    }
+    pub fn prime_meridian_at(&self, epoch: f64) -> f64 {
+        (self.prime_meridian + self.rotation_rate * (epoch - J2000)).rem_euclid(360.0).to_radians()
+    }
+    fn rotation_to_icrf(&self, epoch: f64) -> Matrix {
+        multiply(
+            &multiply(
+                &rotate_z(PI / 2.0 + self.pole_right_ascension.to_radians()),
+                &rotate_x(PI / 2.0 - self.pole_declination.to_radians())),
+            &rotate_z(self.prime_meridian_at(epoch)))
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ReferenceFrame {
+    #[default]
+    EclipticJ2000,
+    Icrf,
+    InvariablePlane,
+    BodyFixed(BodyRotation)
+}
+impl ReferenceFrame {
+    pub fn inertial() -> Vec<ReferenceFrame> {
+        vec!(ReferenceFrame::EclipticJ2000, ReferenceFrame::Icrf, ReferenceFrame::InvariablePlane)
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReferenceFrame::EclipticJ2000 => "ecliptic",
+            ReferenceFrame::Icrf => "icrf",
+            ReferenceFrame::InvariablePlane => "invariable",
+            ReferenceFrame::BodyFixed(_) => "body-fixed"
+        }
+    }
+    pub fn from_name(name: &str) -> Result<ReferenceFrame, String> {
+        match ReferenceFrame::inertial().into_iter().find(|a| a.name() == name.to_lowercase()) {
+            Some(x) => Ok(x),
+            None => Err(format!("Failed to find reference frame {}! Expected one of: {}",
+                name,
+                ReferenceFrame::inertial().iter().map(|a| a.name()).collect::<Vec<_>>().join(", ")))
+        }
+    }
+    pub fn is_inertial(&self) -> bool {
+        !matches!(self, ReferenceFrame::BodyFixed(_))
+    }
+    fn rotation_to_icrf(&self, epoch: f64) -> Matrix {
+        match self {
+            ReferenceFrame::EclipticJ2000 => rotate_x(OBLIQUITY_J2000),
+            ReferenceFrame::Icrf => rotate_z(0.0),
+            ReferenceFrame::InvariablePlane => multiply(
+                &multiply(&rotate_x(OBLIQUITY_J2000), &rotate_z(INVARIABLE_NODE)),
+                &rotate_x(INVARIABLE_INCLINATION)),
+            ReferenceFrame::BodyFixed(x) => x.rotation_to_icrf(epoch)
+        }
+    }
+    fn angular_velocity(&self) -> [f64; 3] {
+        match self {
+            ReferenceFrame::BodyFixed(x) => vector::scale(&x.pole(), x.rotation_rate.to_radians()),
+            _ => [0.0; 3]
+        }
+    }
+    pub fn convert(&self, to: &ReferenceFrame, epoch: f64, position: &[f64; 3], velocity: &[f64; 3]) -> ([f64; 3], [f64; 3]) {
+        if self == to && self.is_inertial() {
+            return (*position, *velocity)
+        }
+        let position = apply(&self.rotation_to_icrf(epoch), position);
+        let velocity = vector::add(
+            &apply(&self.rotation_to_icrf(epoch), velocity),
+            &vector::cross(&self.angular_velocity(), &position));
+        let rotation = to.rotation_to_icrf(epoch);
+        (
+            apply_transposed(&rotation, &position),
+            apply_transposed(&rotation, &vector::subtract(&velocity, &vector::cross(&to.angular_velocity(), &position)))
+        )
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    #[test]
+    fn convert_between_frames() {
+        let position = [0.4133060075292528, -0.9296817278172866, -0.0001236944559827514];
+        let velocity = [0.01547590112466981, 0.006866255831713478, 9.70628931268737e-7];
+        let (equatorial, _) = ReferenceFrame::EclipticJ2000.convert(&ReferenceFrame::Icrf, J2000, &[0.0, 1.0, 0.0], &[0.0; 3]);
+        assert_float_eq!(equatorial, [0.0, OBLIQUITY_J2000.cos(), OBLIQUITY_J2000.sin()], abs <= [1.0E-15; 3]);
+        let (pole, _) = ReferenceFrame::InvariablePlane.convert(&ReferenceFrame::Icrf, J2000, &[0.0, 0.0, 1.0], &[0.0; 3]);
+        assert_float_eq!(pole[1].atan2(pole[0]).to_degrees().rem_euclid(360.0), 273.85, abs <= 0.01);
+        assert_float_eq!(pole[2].asin().to_degrees(), 66.99, abs <= 0.01);
+        let frames = [
+            ReferenceFrame::EclipticJ2000,
+            ReferenceFrame::Icrf,
+            ReferenceFrame::InvariablePlane,
+            ReferenceFrame::BodyFixed(BodyRotation::new(40.589, 83.537, 38.9, 810.7939024))
+        ];
+        for from in frames.iter() {
+            for to in frames.iter() {
+                let (a, b) = ReferenceFrame::EclipticJ2000.convert(from, 2440418.5, &position, &velocity);
+                let (a, b) = from.convert(to, 2440418.5, &a, &b);
+                let (a, b) = to.convert(&ReferenceFrame::EclipticJ2000, 2440418.5, &a, &b);
+                assert_float_eq!(a, position, abs <= [1.0E-15; 3]);
+                assert_float_eq!(b, velocity, abs <= [1.0E-14; 3]);
+            }
+        }
+    }
+    #[test]
+    fn rotate_with_the_body() {
+        let earth = ReferenceFrame::BodyFixed(BodyRotation::earth());
+        let meridian = PI / 2.0 + BodyRotation::earth().prime_meridian_at(J2000);
+        let (position, velocity) = ReferenceFrame::Icrf.convert(&earth, J2000,
+            &[meridian.cos(), meridian.sin(), 0.0], &[0.0; 3]);
+        assert_float_eq!(position, [1.0, 0.0, 0.0], abs <= [1.0E-15; 3]);
+        let rate = 360.9856235f64.to_radians();
+        assert_float_eq!(velocity, [0.0, -rate, 0.0], abs <= [1.0E-12; 3]);
+        let (_, velocity) = earth.convert(&ReferenceFrame::Icrf, J2000, &[1.0, 0.0, 0.0], &[0.0; 3]);
+        assert_float_eq!(vector::norm(&velocity), rate, abs <= 1.0E-12);
+        assert!(ReferenceFrame::from_name("body-fixed").is_err());
+        assert_eq!(ReferenceFrame::from_name("ICRF"), Ok(ReferenceFrame::Icrf));
+    }
+}
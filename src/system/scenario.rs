@@ -0,0 +1,803 @@
+use std::{
+    collections::BTreeMap,
+    fs
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crate::{
+    runner::{
+        self,
+        CheckpointOutput,
+        DiagnosticsOutput,
+        RunConfig,
+        Stop,
+        TrajectoryOutput
+    },
+    timescale::{
+        Epoch,
+        TimeScale
+    },
+    units::{
+        GravitationalMass,
+        Length,
+        Units
+    }
+};
+use super::{
+    body::Body,
+    frame::Frame,
+    integrator::Integrator,
+    orbital_elements::OrbitalElements,
+    outgassing::{
+        Outgassing,
+        Sublimation
+    },
+    radiation::Radiation,
+    recorder::TrajectoryFormat,
+    reference_frame::ReferenceFrame,
+    spacecraft::{
+        Maneuver,
+        ManeuverFrame,
+        Spacecraft,
+        Steering,
+        Thruster
+    },
+    tide::Tide,
+    vector,
+    System
+};
+fn default_reflectivity() -> f64 {
+    1.0
+}
+fn default_moment_of_inertia() -> f64 {
+    0.4
+}
+fn default_pole() -> [f64; 3] {
+    [0.0, 0.0, 1.0]
+}
+fn default_cadence() -> usize {
+    1
+}
+fn default_diagnostics_cadence() -> usize {
+    24
+}
+fn parse_epoch(date: &str) -> Result<Epoch, String> {
+    Epoch::parse(date, TimeScale::Tdb)
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ElementOptions {
+    pub semi_major_axis: f64,
+    #[serde(default)]
+    pub eccentricity: f64,
+    #[serde(default)]
+    pub inclination: f64,
+    #[serde(default)]
+    pub longitude_of_ascending_node: f64,
+    #[serde(default)]
+    pub argument_of_periapsis: f64,
+    #[serde(default)]
+    pub mean_anomaly: f64
+}
+impl ElementOptions {
+    pub fn to_elements(&self) -> OrbitalElements {
+        OrbitalElements {
+            semi_major_axis: self.semi_major_axis,
+            eccentricity: self.eccentricity,
+            inclination: self.inclination.to_radians(),
+            longitude_of_ascending_node: self.longitude_of_ascending_node.to_radians(),
+            argument_of_periapsis: self.argument_of_periapsis.to_radians(),
+            mean_anomaly: self.mean_anomaly.to_radians()
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManeuverOptions {
+    pub epoch: String,
+    pub delta_v: [f64; 3],
+    pub frame: Option<String>,
+    pub central: Option<String>
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThrusterOptions {
+    pub thrust: f64,
+    pub specific_impulse: f64,
+    pub steering: String,
+    pub reference: Option<String>,
+    pub direction: Option<[f64; 3]>
+}
+impl ThrusterOptions {
+    pub fn to_thruster(&self) -> Result<Thruster, String> {
+        let steering = Steering::new(&self.steering, self.reference.as_deref(), self.direction)?;
+        Ok(Thruster::new(self.thrust, self.specific_impulse, steering))
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpacecraftOptions {
+    pub dry_mass: f64,
+    #[serde(default)]
+    pub fuel_mass: f64,
+    pub specific_impulse: f64,
+    #[serde(default)]
+    pub maneuver: Vec<ManeuverOptions>,
+    pub thruster: Option<ThrusterOptions>
+}
+impl SpacecraftOptions {
+    pub fn to_spacecraft(&self) -> Result<Spacecraft, String> {
+        let mut spacecraft = Spacecraft::new(self.dry_mass, self.fuel_mass, self.specific_impulse);
+        if let Some(x) = &self.thruster {
+            spacecraft = spacecraft.with_thruster(x.to_thruster()?);
+        }
+        for a in self.maneuver.iter() {
+            let frame = match &a.frame {
+                Some(x) => ManeuverFrame::from_name(x)?,
+                None => ManeuverFrame::Inertial
+            };
+            spacecraft.plan(Maneuver::new(parse_epoch(&a.epoch)?.to(TimeScale::Tdb).julian_date(), a.delta_v, frame,
+                a.central.clone()));
+        }
+        spacecraft.check()?;
+        Ok(spacecraft)
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioBody {
+    pub name: String,
+    #[serde(default)]
+    pub gravitational_mass: f64,
+    #[serde(default)]
+    pub radius: f64,
+    pub position: Option<[f64; 3]>,
+    pub velocity: Option<[f64; 3]>,
+    pub central: Option<String>,
+    pub elements: Option<ElementOptions>,
+    pub frame: Option<String>,
+    pub spacecraft: Option<SpacecraftOptions>
+}
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IntegratorOptions {
+    pub kind: Option<String>,
+    pub delta_time: Option<f64>
+}
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForceOptions {
+    #[serde(default)]
+    pub massless: Vec<String>,
+    pub luminous: Option<String>,
+    pub luminosity: Option<f64>,
+    #[serde(default)]
+    pub radiation: Vec<RadiationOptions>,
+    #[serde(default)]
+    pub outgassing: Vec<OutgassingOptions>,
+    #[serde(default)]
+    pub tides: Vec<TideOptions>
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RadiationOptions {
+    pub body: String,
+    #[serde(default)]
+    pub area_to_mass: f64,
+    #[serde(default = "default_reflectivity")]
+    pub reflectivity: f64,
+    #[serde(default)]
+    pub yarkovsky: f64
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutgassingOptions {
+    pub body: String,
+    #[serde(default)]
+    pub a1: f64,
+    #[serde(default)]
+    pub a2: f64,
+    #[serde(default)]
+    pub a3: f64,
+    #[serde(default)]
+    pub delay: f64,
+    pub alpha: Option<f64>,
+    pub r0: Option<f64>,
+    pub m: Option<f64>,
+    pub n: Option<f64>,
+    pub k: Option<f64>
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TideOptions {
+    pub body: String,
+    pub love_number: f64,
+    #[serde(default)]
+    pub time_lag: f64,
+    #[serde(default = "default_moment_of_inertia")]
+    pub moment_of_inertia: f64,
+    pub spin: Option<[f64; 3]>,
+    pub rotation_period: Option<f64>,
+    #[serde(default = "default_pole")]
+    pub pole: [f64; 3]
+}
+impl TideOptions {
+    pub fn to_tide(&self) -> Result<Tide, String> {
+        let spin = match (self.spin, self.rotation_period) {
+            (Some(x), None) => x,
+            (None, Some(x)) if x != 0.0 && vector::norm(&self.pole) > 0.0 =>
+                vector::scale(&vector::normalize(&self.pole), 2.0 * std::f64::consts::PI / x),
+            (None, Some(_)) => return Err("Failed to set spin! The rotation period and pole must not be zero".to_string()),
+            _ => return Err("Failed to set spin! Give either spin or rotation_period".to_string())
+        };
+        let tide = Tide::new(self.love_number, self.time_lag, self.moment_of_inertia, spin);
+        tide.check()?;
+        Ok(tide)
+    }
+}
+impl OutgassingOptions {
+    pub fn to_outgassing(&self) -> Outgassing {
+        let law = Sublimation::default();
+        Outgassing::new(self.a1, self.a2, self.a3).with_delay(self.delay).with_sublimation(Sublimation {
+            alpha: self.alpha.unwrap_or(law.alpha),
+            r0: self.r0.unwrap_or(law.r0),
+            m: self.m.unwrap_or(law.m),
+            n: self.n.unwrap_or(law.n),
+            k: self.k.unwrap_or(law.k)
+        })
+    }
+}
+impl RadiationOptions {
+    pub fn to_radiation(&self) -> Radiation {
+        Radiation::new(self.area_to_mass, self.reflectivity, self.yarkovsky)
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrajectoryOptions {
+    pub file: String,
+    #[serde(default = "default_cadence")]
+    pub cadence: usize,
+    #[serde(default)]
+    pub bodies: Vec<String>,
+    pub frame: Option<String>
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiagnosticsOptions {
+    pub file: String,
+    #[serde(default = "default_diagnostics_cadence")]
+    pub cadence: usize
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckpointOptions {
+    pub file: String,
+    #[serde(default)]
+    pub every: u64
+}
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputOptions {
+    pub until: Option<String>,
+    pub days: Option<f64>,
+    pub snapshot: Option<String>,
+    pub trajectory: Option<TrajectoryOptions>,
+    pub diagnostics: Option<DiagnosticsOptions>,
+    pub checkpoint: Option<CheckpointOptions>,
+    pub burns: Option<String>
+}
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub epoch: String,
+    #[serde(default)]
+    pub targets: Vec<String>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub body: Vec<ScenarioBody>,
+    pub frame: Option<String>,
+    #[serde(default)]
+    pub units: Units,
+    #[serde(default)]
+    pub integrator: IntegratorOptions,
+    #[serde(default)]
+    pub forces: ForceOptions,
+    #[serde(default)]
+    pub output: OutputOptions
+}
+impl Scenario {
+    pub fn from_toml(contents: &str) -> Result<Scenario, String> {
+        let scenario: Scenario = match toml::from_str(contents) {
+            Err(x) => return Err(format!("Failed to parse scenario!\n{}", x)),
+            Ok(x) => x
+        };
+        scenario.validate()?;
+        Ok(scenario)
+    }
+    pub fn load_toml(file_path: &str) -> Result<Scenario, String> {
+        let buffer = match fs::read_to_string(file_path) {
+            Err(x) => return Err(format!("Failed reading {}!\n{}", file_path, x)),
+            Ok(x) => x
+        };
+        match Scenario::from_toml(&buffer) {
+            Err(x) => Err(format!("Failed loading {}!\n{}", file_path, x)),
+            x => x
+        }
+    }
+    pub fn target_list(&self) -> Vec<String> {
+        let mut list: Vec<String> = Vec::new();
+        self.targets.iter()
+            .chain(self.include.iter().flat_map(|a| self.groups.get(a).into_iter().flatten()))
+            .for_each(|a| {
+                if !list.contains(a) {
+                    list.push(a.clone())
+                }
+            });
+        list
+    }
+    fn is_declared(&self, name: &str, before: usize) -> bool {
+        self.target_list().iter().any(|a| a == name) ||
+            self.body.iter().take(before).any(|a| a.name == name)
+    }
+    pub fn validate(&self) -> Result<(), String> {
+        parse_epoch(&self.epoch)?;
+        for group in self.include.iter() {
+            if !self.groups.contains_key(group) {
+                return Err(format!("Failed to find group {}! Declare it under [groups]", group))
+            }
+        }
+        for (i, body) in self.body.iter().enumerate() {
+            if self.is_declared(&body.name, i) {
+                return Err(format!("Failed to add body {}! The name is already used", body.name))
+            }
+            match (&body.position, &body.velocity, &body.elements, &body.central) {
+                (Some(_), Some(_), None, None) => (),
+                (None, None, Some(x), Some(y)) => {
+                    if !self.is_declared(y, i) {
+                        return Err(format!("Failed to find central body {} of {}! It must be a target or a body declared before it",
+                            y, body.name))
+                    }
+                    if x.eccentricity < 0.0 || (x.eccentricity < 1.0) != (x.semi_major_axis > 0.0) {
+                        return Err(format!("Failed to add body {}! Semi-major axis {} does not match eccentricity {}",
+                            body.name, x.semi_major_axis, x.eccentricity))
+                    }
+                },
+                _ => return Err(format!("Failed to add body {}! Give either position and velocity or elements and central",
+                    body.name))
+            }
+            if let Some(x) = &body.frame {
+                if body.elements.is_some() {
+                    return Err(format!("Failed to add body {}! Elements are always relative to the frame of the system",
+                        body.name))
+                }
+                ReferenceFrame::from_name(x)?;
+            }
+            if let Some(x) = &body.spacecraft {
+                if let Err(y) = x.to_spacecraft() {
+                    return Err(format!("Failed to add body {}!\n{}", body.name, y))
+                }
+                if let Some(y) = x.maneuver.iter().filter_map(|a| a.central.as_ref()).find(|a| !self.is_declared(a, self.body.len())) {
+                    return Err(format!("Failed to find central body {} of the maneuvers of {}!", y, body.name))
+                }
+                if let Some(y) = x.thruster.as_ref().and_then(|a| a.reference.as_ref()).filter(|a| !self.is_declared(a, self.body.len())) {
+                    return Err(format!("Failed to find reference body {} of the thruster of {}!", y, body.name))
+                }
+            }
+        }
+        if let Some(x) = &self.frame {
+            ReferenceFrame::from_name(x)?;
+        }
+        if let Some(x) = &self.integrator.kind {
+            Integrator::from_name(x)?;
+        }
+        if let Some(x) = self.integrator.delta_time {
+            if x <= 0.0 || !x.is_finite() {
+                return Err(format!("Failed to set time step {}! It must be positive", x))
+            }
+        }
+        for name in self.forces.massless.iter() {
+            if !self.is_declared(name, self.body.len()) {
+                return Err(format!("Failed to find massless body {}!", name))
+            }
+        }
+        for radiation in self.forces.radiation.iter() {
+            if !self.is_declared(&radiation.body, self.body.len()) {
+                return Err(format!("Failed to find body {} under radiation forces!", radiation.body))
+            }
+            if let Err(x) = radiation.to_radiation().check() {
+                return Err(format!("Failed to add radiation forces on {}!\n{}", radiation.body, x))
+            }
+        }
+        for outgassing in self.forces.outgassing.iter() {
+            if !self.is_declared(&outgassing.body, self.body.len()) {
+                return Err(format!("Failed to find body {} under outgassing!", outgassing.body))
+            }
+            if let Err(x) = outgassing.to_outgassing().check() {
+                return Err(format!("Failed to add outgassing of {}!\n{}", outgassing.body, x))
+            }
+        }
+        for tide in self.forces.tides.iter() {
+            if !self.is_declared(&tide.body, self.body.len()) {
+                return Err(format!("Failed to find body {} under tides!", tide.body))
+            }
+            if let Err(x) = tide.to_tide() {
+                return Err(format!("Failed to add tides of {}!\n{}", tide.body, x))
+            }
+        }
+        if let Some(x) = self.forces.luminous.as_ref().filter(|a| !self.is_declared(a, self.body.len())) {
+            return Err(format!("Failed to find luminous body {}!", x))
+        }
+        if let Some(x) = self.forces.luminosity.filter(|a| !(*a >= 0.0 && a.is_finite())) {
+            return Err(format!("Failed to set luminosity {}! It must not be negative", x))
+        }
+        match (&self.output.until, self.output.days) {
+            (Some(_), Some(_)) => return Err("Failed to read output! Give only one of until and days".to_string()),
+            (Some(x), None) => {
+                parse_epoch(x)?;
+            },
+            (None, Some(x)) if x < 0.0 => return Err(format!("Failed to read output! Days {} must not be negative", x)),
+            _ => ()
+        }
+        Ok(())
+    }
+    fn find(system: &System, name: &str) -> Result<usize, String> {
+        match system.index_of(name) {
+            Some(x) => Ok(x),
+            None => Err(format!("Failed to find body {} in system!", name))
+        }
+    }
+    pub fn build(&self) -> Result<System, String> {
+        let date = parse_epoch(&self.epoch)?;
+        let list = self.target_list();
+        let mut system = if list.is_empty() {
+            let mut system = System::new();
+            system.set_date(&date);
+            system
+        } else {
+            System::fetch_from_horizons(&list, &date)?
+        };
+        if system.size() < list.len() {
+            return Err(format!("Failed to fetch {} of {} targets!", list.len() - system.size(), list.len()))
+        }
+        for body in self.body.iter() {
+            let gravitational_mass = self.units.gravitational_mass(body.gravitational_mass);
+            let (position, velocity) = match (&body.position, &body.velocity, &body.elements, &body.central) {
+                (Some(x), Some(y), _, _) => (
+                    self.units.position(x).map(|a| a.au()),
+                    self.units.velocity(y).map(|a| a.au_per_day())
+                ),
+                (_, _, Some(x), Some(y)) => {
+                    let central = system.object_from_index(Scenario::find(&system, y)?).unwrap();
+                    let central = central.read().unwrap();
+                    let mut elements = x.to_elements();
+                    elements.semi_major_axis = Length::new(elements.semi_major_axis, self.units.length).au();
+                    let (position, velocity) = elements.to_state(
+                        (GravitationalMass::from_km3_per_s2(central.mass()) + gravitational_mass).au3_per_day2());
+                    (
+                        vector::add(&central.position(), &position),
+                        vector::add(&central.velocity(), &velocity)
+                    )
+                },
+                _ => return Err(format!("Failed to add body {}! Give either position and velocity or elements and central",
+                    body.name))
+            };
+            let mut object = Body::new(body.name.clone(), gravitational_mass.km3_per_s2(), self.units.radius(body.radius).km(),
+                position, velocity);
+            if let Some(x) = &body.frame {
+                object = object.in_frame(ReferenceFrame::from_name(x)?);
+                object.convert_frame(system.frame()?, system.epoch());
+            }
+            if let Some(x) = &body.spacecraft {
+                object = object.with_spacecraft(x.to_spacecraft()?);
+            }
+            system.add(object);
+        }
+        for name in self.forces.massless.iter() {
+            let index = Scenario::find(&system, name)?;
+            system.object_from_index(index).unwrap().write().unwrap().set_mass(0.0);
+        }
+        for radiation in self.forces.radiation.iter() {
+            let index = Scenario::find(&system, &radiation.body)?;
+            system.object_from_index(index).unwrap().write().unwrap().set_radiation(Some(radiation.to_radiation()));
+        }
+        for outgassing in self.forces.outgassing.iter() {
+            let index = Scenario::find(&system, &outgassing.body)?;
+            system.object_from_index(index).unwrap().write().unwrap().set_outgassing(Some(outgassing.to_outgassing()));
+        }
+        for tide in self.forces.tides.iter() {
+            let index = Scenario::find(&system, &tide.body)?;
+            system.object_from_index(index).unwrap().write().unwrap().set_tide(Some(tide.to_tide()?));
+        }
+        let luminous = self.forces.luminous.clone().unwrap_or_else(|| system.luminous().to_string());
+        let luminosity = self.forces.luminosity.unwrap_or_else(|| system.luminosity());
+        system.set_luminous(&luminous, luminosity);
+        system.check_spacecraft()?;
+        system.check_sunlight()?;
+        system.check_tides()?;
+        if let Some(x) = &self.frame {
+            system.convert_frame(ReferenceFrame::from_name(x)?)?;
+        }
+        if let Some(x) = &self.integrator.kind {
+            system.set_integrator(Integrator::from_name(x)?);
+        }
+        if let Some(x) = self.integrator.delta_time {
+            system.set_delta_time(x);
+        }
+        Ok(system)
+    }
+    pub fn run_config(&self, system: &System) -> Result<Option<RunConfig>, String> {
+        let stop = match (&self.output.until, self.output.days) {
+            (Some(x), _) => Stop::Epoch(parse_epoch(x)?.to(TimeScale::Tdb).julian_date()),
+            (None, Some(x)) => Stop::Duration(x),
+            (None, None) => return Ok(None)
+        };
+        let mut config = RunConfig::new(stop);
+        config.snapshot = self.output.snapshot.clone();
+        if let Some(x) = &self.output.trajectory {
+            runner::select_bodies(system, &x.bodies)?;
+            if let Some(y) = &x.frame {
+                Frame::parse(system, y)?;
+            }
+            config.trajectory = Some(TrajectoryOutput {
+                file_path: x.file.clone(),
+                format: TrajectoryFormat::from_path(&x.file),
+                cadence: x.cadence,
+                bodies: x.bodies.clone(),
+                frame: x.frame.clone()
+            });
+        }
+        config.diagnostics = self.output.diagnostics.as_ref().map(|x| {
+            DiagnosticsOutput {
+                file_path: x.file.clone(),
+                cadence: x.cadence
+            }
+        });
+        config.checkpoint = self.output.checkpoint.as_ref().map(|x| {
+            CheckpointOutput {
+                file_path: x.file.clone(),
+                interval: x.every
+            }
+        });
+        config.burns = self.output.burns.clone();
+        Ok(Some(config))
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    const INNER_SYSTEM: &str = r#"
+epoch = "1969-07-16"
+
+[integrator]
+kind = "yoshida4"
+delta_time = 0.5
+
+[forces]
+massless = ["Moon"]
+
+[[body]]
+name = "Sun"
+gravitational_mass = 132712440041.93938
+radius = 695700.0
+position = [0.0, 0.0, 0.0]
+velocity = [0.0, 0.0, 0.0]
+
+[[body]]
+name = "Earth"
+gravitational_mass = 398600.435436
+radius = 6371.01
+central = "Sun"
+[body.elements]
+semi_major_axis = 1.0
+eccentricity = 0.0167
+inclination = 0.0
+mean_anomaly = 90.0
+
+[[body]]
+name = "Moon"
+gravitational_mass = 4902.800066
+central = "Earth"
+[body.elements]
+semi_major_axis = 0.00257
+eccentricity = 0.0549
+inclination = 5.145
+
+[output]
+days = 30
+[output.trajectory]
+file = "moon.csv"
+bodies = ["Earth", "Moon"]
+"#;
+    #[test]
+    fn build_manual_system() -> Result<(), String> {
+        let scenario = Scenario::from_toml(INNER_SYSTEM)?;
+        let system = scenario.build()?;
+        assert_eq!(system.size(), 3);
+        assert_eq!(system.epoch(), 2440418.5);
+        assert_eq!(system.integrator(), Integrator::Yoshida4);
+        assert_eq!(system.delta_time(), 0.5);
+        let moon = system.object_from_index(2).unwrap();
+        assert_eq!(moon.read().unwrap().mass(), 0.0);
+        let elements = system.osculating_elements(1, 0)?;
+        assert_float_eq!(elements.semi_major_axis, 1.0, abs <= 1.0E-9);
+        assert_float_eq!(elements.eccentricity, 0.0167, abs <= 1.0E-9);
+        let config = scenario.run_config(&system)?.unwrap();
+        assert_eq!(config.stop, Stop::Duration(30.0));
+        assert_eq!(config.trajectory.unwrap().format, TrajectoryFormat::Csv);
+        Ok(())
+    }
+    #[test]
+    fn convert_body_frames() -> Result<(), String> {
+        let equatorial = INNER_SYSTEM.replace("position = [0.0, 0.0, 0.0]", "position = [0.0, 0.9174820620691818, 0.3977771559319137]\nframe = \"icrf\"");
+        let system = Scenario::from_toml(&equatorial)?.build()?;
+        assert_eq!(system.frame(), Ok(ReferenceFrame::EclipticJ2000));
+        let sun = system.object_from_index(0).unwrap();
+        assert_float_eq!(sun.read().unwrap().position(), [0.0, 1.0, 0.0], abs <= [1.0E-15; 3]);
+        let invariable = format!("frame = \"invariable\"\n{}", INNER_SYSTEM);
+        let system = Scenario::from_toml(&invariable)?.build()?;
+        assert_eq!(system.frame(), Ok(ReferenceFrame::InvariablePlane));
+        assert_float_eq!(system.osculating_elements(1, 0)?.semi_major_axis, 1.0, abs <= 1.0E-9);
+        let elements_in_frame = INNER_SYSTEM.replace("central = \"Earth\"", "central = \"Earth\"\nframe = \"icrf\"");
+        assert!(Scenario::from_toml(&elements_in_frame).is_err());
+        assert!(Scenario::from_toml(&format!("frame = \"galactic\"\n{}", INNER_SYSTEM)).is_err());
+        Ok(())
+    }
+    #[test]
+    fn read_bodies_in_units() -> Result<(), String> {
+        let kilometres = INNER_SYSTEM
+            .replace("epoch = \"1969-07-16\"", "epoch = \"1969-07-16\"\nunits = { length = \"km\", time = \"s\" }")
+            .replace("semi_major_axis = 1.0", "semi_major_axis = 149597870.7");
+        let system = Scenario::from_toml(&kilometres)?.build()?;
+        assert_float_eq!(system.osculating_elements(1, 0)?.semi_major_axis, 1.0, abs <= 1.0E-9);
+        let radius = system.object_from_index(0).unwrap().read().unwrap().radius();
+        assert_float_eq!(radius, 695700.0, r2nd <= 1.0E-15);
+        Ok(())
+    }
+    #[test]
+    fn plan_spacecraft_maneuvers() -> Result<(), String> {
+        let probe = format!("{}{}", INNER_SYSTEM.replace("[output]\n", "[output]\nburns = \"burns.csv\"\n"), r#"
+[[body]]
+name = "Probe"
+position = [1.0, 0.0, 0.0]
+velocity = [0.0, 0.0172, 0.0]
+[body.spacecraft]
+dry_mass = 800.0
+fuel_mass = 400.0
+specific_impulse = 310.0
+[[body.spacecraft.maneuver]]
+epoch = "1969-07-18 12:00 UTC"
+delta_v = [0.0, 0.0, 0.2]
+[[body.spacecraft.maneuver]]
+epoch = "1969-07-17"
+delta_v = [0.1, 0.0, 0.0]
+frame = "vnb"
+central = "Earth"
+"#);
+        let scenario = Scenario::from_toml(&probe)?;
+        let system = scenario.build()?;
+        let spacecraft = system.object_from_index(3).unwrap().read().unwrap().spacecraft().cloned().unwrap();
+        assert_eq!(spacecraft.maneuver.len(), 2);
+        assert_eq!(spacecraft.maneuver[0].epoch, 2440419.5);
+        assert_eq!(spacecraft.maneuver[1].frame, ManeuverFrame::Inertial);
+        assert_eq!(scenario.run_config(&system)?.unwrap().burns, Some("burns.csv".to_string()));
+        let error = Scenario::from_toml(&format!("{}\"Mars\"", probe.trim_end().trim_end_matches("\"Earth\""))).unwrap_err();
+        assert!(error.contains("Mars"));
+        assert!(Scenario::from_toml(&probe.replace("frame = \"vnb\"\ncentral = \"Earth\"", "frame = \"vnb\"")).is_err());
+        Ok(())
+    }
+    #[test]
+    fn steer_spacecraft_thruster() -> Result<(), String> {
+        let probe = format!("{}{}", INNER_SYSTEM, r#"
+[[body]]
+name = "Probe"
+position = [1.0, 0.0, 0.0]
+velocity = [0.0, 0.0172, 0.0]
+[body.spacecraft]
+dry_mass = 800.0
+fuel_mass = 400.0
+specific_impulse = 310.0
+[body.spacecraft.thruster]
+thrust = 0.09
+specific_impulse = 3100.0
+steering = "tangential"
+reference = "Sun"
+"#);
+        let system = Scenario::from_toml(&probe)?.build()?;
+        let spacecraft = system.object_from_index(3).unwrap().read().unwrap().spacecraft().cloned().unwrap();
+        assert_eq!(spacecraft.thruster, Some(Thruster::new(0.09, 3100.0, Steering::Tangential("Sun".to_string()))));
+        let error = Scenario::from_toml(&probe.replace("reference = \"Sun\"", "reference = \"Mars\"")).unwrap_err();
+        assert!(error.contains("Mars"));
+        assert!(Scenario::from_toml(&probe.replace("\"tangential\"", "\"inertial\"")).is_err());
+        Ok(())
+    }
+    #[test]
+    fn push_asteroid_with_sunlight() -> Result<(), String> {
+        let asteroid = format!("{}{}", INNER_SYSTEM, r#"
+[[body]]
+name = "Asteroid"
+central = "Sun"
+[body.elements]
+semi_major_axis = 1.1
+eccentricity = 0.2
+inclination = 3.0
+
+[[forces.radiation]]
+body = "Asteroid"
+area_to_mass = 0.02
+reflectivity = 1.2
+yarkovsky = -2.9E-14
+"#);
+        let system = Scenario::from_toml(&asteroid)?.build()?;
+        assert_eq!((system.luminous(), system.luminosity()), ("Sun", 1.0));
+        let radiation = system.object_from_index(3).unwrap().read().unwrap().radiation().cloned();
+        assert_eq!(radiation, Some(Radiation::new(0.02, 1.2, -2.9E-14)));
+        let dimmer = asteroid.replace("massless = [\"Moon\"]", "massless = [\"Moon\"]\nluminous = \"Sun\"\nluminosity = 0.5");
+        assert_eq!(Scenario::from_toml(&dimmer)?.build()?.luminosity(), 0.5);
+        let error = Scenario::from_toml(&asteroid.replace("massless = [\"Moon\"]", "massless = [\"Moon\"]\nluminous = \"Vega\"")).unwrap_err();
+        assert!(error.contains("Vega"));
+        assert!(Scenario::from_toml(&asteroid.replace("reflectivity = 1.2", "reflectivity = 3.0")).is_err());
+        assert!(Scenario::from_toml(&asteroid.replace("body = \"Asteroid\"", "body = \"Ceres\"")).is_err());
+        let comet = format!("{}{}", asteroid, r#"
+[[forces.outgassing]]
+body = "Asteroid"
+a1 = 1.2E-9
+a2 = -3.4E-10
+delay = 12.5
+r0 = 5.0
+"#);
+        let system = Scenario::from_toml(&comet)?.build()?;
+        let outgassing = system.object_from_index(3).unwrap().read().unwrap().outgassing().cloned().unwrap();
+        assert_eq!((outgassing.a1, outgassing.a2, outgassing.a3, outgassing.sublimation.r0), (1.2E-9, -3.4E-10, 0.0, 5.0));
+        assert_eq!(outgassing.sublimation.k, Sublimation::default().k);
+        assert_eq!(outgassing.delay, 12.5);
+        assert!(Scenario::from_toml(&comet.replace("r0 = 5.0", "r0 = -5.0")).is_err());
+        let unlit = asteroid.replace("name = \"Sun\"", "name = \"Star\"").replace("central = \"Sun\"", "central = \"Star\"");
+        assert!(Scenario::from_toml(&unlit)?.build().unwrap_err().contains("Sun"));
+        Ok(())
+    }
+    #[test]
+    fn raise_tides_on_earth() -> Result<(), String> {
+        let tidal = format!("{}{}", INNER_SYSTEM.replace("massless = [\"Moon\"]", ""), r#"
+[[forces.tides]]
+body = "Earth"
+love_number = 0.3
+time_lag = 600.0
+moment_of_inertia = 0.33
+rotation_period = 0.9973
+pole = [0.0, 0.0, 2.0]
+"#);
+        let system = Scenario::from_toml(&tidal)?.build()?;
+        let tide = system.object_from_index(1).unwrap().read().unwrap().tide().cloned().unwrap();
+        assert_eq!((tide.love_number, tide.time_lag, tide.moment_of_inertia), (0.3, 600.0, 0.33));
+        assert_eq!(tide.spin[..2], [0.0, 0.0]);
+        assert!((tide.rotation_period() - 0.9973).abs() < 1.0E-12);
+        let spin = tidal.replace("rotation_period = 0.9973\npole = [0.0, 0.0, 2.0]", "spin = [0.0, 0.0, 6.3]");
+        assert_eq!(Scenario::from_toml(&spin)?.build()?.object_from_index(1).unwrap().read().unwrap().tide().unwrap().spin, [0.0, 0.0, 6.3]);
+        assert!(Scenario::from_toml(&tidal.replace("pole = [0.0, 0.0, 2.0]", "spin = [0.0, 0.0, 6.3]")).is_err());
+        assert!(Scenario::from_toml(&tidal.replace("rotation_period = 0.9973\n", "")).is_err());
+        assert!(Scenario::from_toml(&tidal.replace("love_number = 0.3", "love_number = -0.3")).is_err());
+        assert!(Scenario::from_toml(&tidal.replace("body = \"Earth\"", "body = \"Venus\"")).unwrap_err().contains("Venus"));
+        let shapeless = tidal.replace("body = \"Earth\"", "body = \"Moon\"");
+        assert!(Scenario::from_toml(&shapeless)?.build().unwrap_err().contains("Moon"));
+        Ok(())
+    }
+    #[test]
+    fn reject_bad_references() -> Result<(), String> {
+        let missing_central = INNER_SYSTEM.replace("central = \"Earth\"", "central = \"Mars\"");
+        let error = Scenario::from_toml(&missing_central).unwrap_err();
+        assert!(error.contains("Mars"));
+        let missing_group = format!("include = [\"jovian\"]\n{}", INNER_SYSTEM);
+        let error = Scenario::from_toml(&missing_group).unwrap_err();
+        assert!(error.contains("jovian"));
+        let unknown_field = INNER_SYSTEM.replace("radius = 6371.01", "raduis = 6371.01");
+        assert!(Scenario::from_toml(&unknown_field).is_err());
+        Ok(())
+    }
+}
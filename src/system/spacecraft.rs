@@ -0,0 +1,325 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crate::units::{
+    Speed,
+    DAY_TO_S
+};
+use super::{
+    text::{
+        csv_field,
+        write_text
+    },
+    vector
+};
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManeuverFrame {
+    #[default]
+    Inertial,
+    Vnb,
+    Rtn
+}
+impl ManeuverFrame {
+    pub fn all() -> Vec<ManeuverFrame> {
+        vec!(ManeuverFrame::Inertial, ManeuverFrame::Vnb, ManeuverFrame::Rtn)
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            ManeuverFrame::Inertial => "inertial",
+            ManeuverFrame::Vnb => "vnb",
+            ManeuverFrame::Rtn => "rtn"
+        }
+    }
+    pub fn from_name(name: &str) -> Result<ManeuverFrame, String> {
+        match ManeuverFrame::all().into_iter().find(|a| a.name() == name.to_lowercase()) {
+            Some(x) => Ok(x),
+            None => Err(format!("Failed to find maneuver frame {}! Expected one of: {}",
+                name,
+                ManeuverFrame::all().iter().map(|a| a.name()).collect::<Vec<_>>().join(", ")))
+        }
+    }
+    pub fn axes(&self, position: &[f64; 3], velocity: &[f64; 3]) -> [[f64; 3]; 3] {
+        match self {
+            ManeuverFrame::Inertial => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            ManeuverFrame::Vnb => {
+                let along = vector::normalize(velocity);
+                let normal = vector::normalize(&vector::cross(position, velocity));
+                [along, normal, vector::cross(&along, &normal)]
+            },
+            ManeuverFrame::Rtn => {
+                let radial = vector::normalize(position);
+                let normal = vector::normalize(&vector::cross(position, velocity));
+                [radial, vector::cross(&normal, &radial), normal]
+            }
+        }
+    }
+    pub fn to_inertial(&self, delta_v: &[f64; 3], position: &[f64; 3], velocity: &[f64; 3]) -> [f64; 3] {
+        let axes = self.axes(position, velocity);
+        (0..3).fold([0.0; 3], |sum, i| vector::add(&sum, &vector::scale(&axes[i], delta_v[i])))
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Maneuver {
+    pub epoch: f64,
+    pub delta_v: [f64; 3],
+    #[serde(default)]
+    pub frame: ManeuverFrame,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub central: Option<String>
+}
+impl Maneuver {
+    pub fn new(epoch: f64, delta_v: [f64; 3], frame: ManeuverFrame, central: Option<String>) -> Maneuver {
+        Maneuver {
+            epoch,
+            delta_v,
+            frame,
+            central
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Steering {
+    Tangential(String),
+    AntiSun(String),
+    Inertial([f64; 3])
+}
+impl Steering {
+    pub fn names() -> Vec<&'static str> {
+        vec!("tangential", "anti-sun", "inertial")
+    }
+    pub fn new(name: &str, reference: Option<&str>, direction: Option<[f64; 3]>) -> Result<Steering, String> {
+        match (name.to_lowercase().as_str(), reference, direction) {
+            ("tangential", Some(x), None) => Ok(Steering::Tangential(x.to_string())),
+            ("anti-sun", Some(x), None) => Ok(Steering::AntiSun(x.to_string())),
+            ("inertial", None, Some(x)) if vector::norm(&x) > 0.0 => Ok(Steering::Inertial(x)),
+            ("tangential", _, _) | ("anti-sun", _, _) => Err(format!("Failed to set {} steering! It needs a reference body and no direction", name)),
+            ("inertial", _, _) => Err("Failed to set inertial steering! It needs a direction that is not zero and no reference body".to_string()),
+            _ => Err(format!("Failed to find steering law {}! Expected one of: {}", name, Steering::names().join(", ")))
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Steering::Tangential(_) => "tangential",
+            Steering::AntiSun(_) => "anti-sun",
+            Steering::Inertial(_) => "inertial"
+        }
+    }
+    pub fn reference(&self) -> Option<&str> {
+        match self {
+            Steering::Tangential(x) | Steering::AntiSun(x) => Some(x),
+            Steering::Inertial(_) => None
+        }
+    }
+    pub fn direction(&self, position: &[f64; 3], velocity: &[f64; 3]) -> [f64; 3] {
+        match self {
+            Steering::Tangential(_) => vector::normalize(velocity),
+            Steering::AntiSun(_) => vector::normalize(position),
+            Steering::Inertial(x) => vector::normalize(x)
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Thruster {
+    pub thrust: f64,
+    pub specific_impulse: f64,
+    pub steering: Steering
+}
+impl Thruster {
+    pub fn new(thrust: f64, specific_impulse: f64, steering: Steering) -> Thruster {
+        Thruster {
+            thrust,
+            specific_impulse,
+            steering
+        }
+    }
+    pub fn mass_flow(&self) -> f64 {
+        self.thrust / (self.specific_impulse * STANDARD_GRAVITY)
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spacecraft {
+    pub dry_mass: f64,
+    pub fuel_mass: f64,
+    pub specific_impulse: f64,
+    #[serde(default)]
+    pub maneuver: Vec<Maneuver>,
+    #[serde(default)]
+    pub executed: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thruster: Option<Thruster>
+}
+impl Spacecraft {
+    pub fn new(dry_mass: f64, fuel_mass: f64, specific_impulse: f64) -> Spacecraft {
+        Spacecraft {
+            dry_mass,
+            fuel_mass,
+            specific_impulse,
+            maneuver: Vec::new(),
+            executed: 0,
+            thruster: None
+        }
+    }
+    pub fn with_thruster(mut self, thruster: Thruster) -> Spacecraft {
+        self.thruster = Some(thruster);
+        self
+    }
+    pub fn plan(&mut self, maneuver: Maneuver) {
+        let index = self.maneuver.iter().skip(self.executed).position(|a| a.epoch > maneuver.epoch)
+            .map(|a| a + self.executed)
+            .unwrap_or(self.maneuver.len());
+        self.maneuver.insert(index, maneuver)
+    }
+    pub fn check(&self) -> Result<(), String> {
+        if self.dry_mass <= 0.0 || self.fuel_mass < 0.0 || self.specific_impulse <= 0.0 {
+            return Err(format!("Failed to add spacecraft! Dry mass {} and specific impulse {} must be positive and fuel {} not negative",
+                self.dry_mass, self.specific_impulse, self.fuel_mass))
+        }
+        if self.executed > self.maneuver.len() {
+            return Err(format!("Failed to add spacecraft! {} of {} maneuvers are marked executed", self.executed, self.maneuver.len()))
+        }
+        if self.maneuver.windows(2).any(|a| a[1].epoch < a[0].epoch) {
+            return Err("Failed to add spacecraft! Maneuvers must be in order of their epochs".to_string())
+        }
+        if let Some(x) = &self.thruster {
+            if x.thrust < 0.0 || x.specific_impulse <= 0.0 {
+                return Err(format!("Failed to add thruster! Thrust {} must not be negative and specific impulse {} must be positive",
+                    x.thrust, x.specific_impulse))
+            }
+        }
+        match self.maneuver.iter().find(|a| a.frame != ManeuverFrame::Inertial && a.central.is_none()) {
+            Some(x) => Err(format!("Failed to add maneuver at {}! A {} maneuver needs a central body", x.epoch, x.frame.name())),
+            None => Ok(())
+        }
+    }
+    pub fn mass(&self) -> f64 {
+        self.dry_mass + self.fuel_mass
+    }
+    pub fn exhaust_velocity(&self) -> Speed {
+        Speed::from_km_per_s(self.specific_impulse * STANDARD_GRAVITY / 1000.0)
+    }
+    pub fn delta_v_budget(&self) -> Speed {
+        self.exhaust_velocity() * (self.mass() / self.dry_mass).ln()
+    }
+    pub fn next_maneuver(&self) -> Option<&Maneuver> {
+        self.maneuver.get(self.executed)
+    }
+    pub(super) fn thrust(&mut self, delta_time: f64) -> f64 {
+        let (exhaust, flow) = match &self.thruster {
+            Some(x) if self.fuel_mass > 0.0 && delta_time != 0.0 => (x.specific_impulse * STANDARD_GRAVITY, x.mass_flow()),
+            _ => return 0.0
+        };
+        let used = (flow * delta_time * DAY_TO_S).min(self.fuel_mass);
+        let acceleration = exhaust * (self.mass() / (self.mass() - used)).ln() / (delta_time * DAY_TO_S);
+        self.fuel_mass = (self.fuel_mass - used).max(0.0);
+        acceleration
+    }
+    pub(super) fn burn(&mut self, requested: Speed) -> (Speed, f64) {
+        let achieved = if requested > self.delta_v_budget() { self.delta_v_budget() } else { requested };
+        let fuel_used = (self.mass() * (1.0 - (-(achieved / self.exhaust_velocity())).exp())).min(self.fuel_mass);
+        self.fuel_mass -= fuel_used;
+        self.executed += 1;
+        (achieved, fuel_used)
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct BurnRecord {
+    pub epoch: f64,
+    pub body: String,
+    pub frame: ManeuverFrame,
+    pub delta_v: [f64; 3],
+    pub requested: f64,
+    pub achieved: f64,
+    pub fuel_used: f64,
+    pub fuel_remaining: f64,
+    pub skipped: Option<String>
+}
+impl BurnRecord {
+    pub fn describe(&self) -> String {
+        if let Some(x) = &self.skipped {
+            return format!("{} skipped a burn of {:.6} km/s {}, {}", self.body, self.requested, self.frame.name(), x)
+        }
+        let shortfall = if self.achieved < self.requested {
+            format!(" of {:.6} requested, out of fuel", self.requested)
+        } else {
+            String::new()
+        };
+        format!("{} burned {:.6} km/s {}{} using {:.3} kg of fuel, {:.3} kg left",
+            self.body, self.achieved, self.frame.name(), shortfall, self.fuel_used, self.fuel_remaining)
+    }
+}
+pub fn save_csv(burn: &[BurnRecord], file_path: &str) -> Result<(), String> {
+    let mut contents = "epoch,body,frame,dvx,dvy,dvz,requested,achieved,fuel_used,fuel_remaining,skipped\n".to_string();
+    for a in burn.iter() {
+        contents.push_str(&format!("{},{},{},{},{},{},{},{},{},{},{}\n", a.epoch, csv_field(&a.body), a.frame.name(),
+            a.delta_v[0], a.delta_v[1], a.delta_v[2], a.requested, a.achieved, a.fuel_used, a.fuel_remaining,
+            csv_field(a.skipped.as_deref().unwrap_or(""))));
+    }
+    write_text(file_path, &contents)
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    #[test]
+    fn burn_with_the_rocket_equation() {
+        let mut spacecraft = Spacecraft::new(1000.0, 500.0, 300.0);
+        let exhaust = 300.0 * STANDARD_GRAVITY / 1000.0;
+        assert_float_eq!(spacecraft.delta_v_budget().km_per_s(), exhaust * 1.5f64.ln(), r2nd <= 1.0E-12);
+        let (achieved, fuel_used) = spacecraft.burn(Speed::from_km_per_s(0.5));
+        assert_float_eq!(achieved.km_per_s(), 0.5, r2nd <= 1.0E-12);
+        assert_float_eq!(fuel_used, 1500.0 * (1.0 - (-0.5 / exhaust).exp()), r2nd <= 1.0E-12);
+        assert_float_eq!(spacecraft.mass(), 1500.0 * (-0.5 / exhaust).exp(), r2nd <= 1.0E-12);
+        let (achieved, _) = spacecraft.burn(Speed::from_km_per_s(10.0));
+        assert_float_eq!(achieved.km_per_s(), exhaust * 1.5f64.ln() - 0.5, r2nd <= 1.0E-9);
+        assert_float_eq!(spacecraft.fuel_mass, 0.0, abs <= 1.0E-9);
+        assert_eq!(spacecraft.executed, 2);
+    }
+    #[test]
+    fn rotate_local_frames() {
+        let position = [2.0, 0.0, 0.0];
+        let velocity = [0.0, 3.0, 0.0];
+        assert_eq!(ManeuverFrame::Vnb.to_inertial(&[1.0, 0.0, 0.0], &position, &velocity), [0.0, 1.0, 0.0]);
+        assert_eq!(ManeuverFrame::Vnb.to_inertial(&[0.0, 1.0, 0.0], &position, &velocity), [0.0, 0.0, 1.0]);
+        assert_eq!(ManeuverFrame::Vnb.to_inertial(&[0.0, 0.0, 1.0], &position, &velocity), [1.0, 0.0, 0.0]);
+        assert_eq!(ManeuverFrame::Rtn.to_inertial(&[1.0, 2.0, 3.0], &position, &velocity), [1.0, 2.0, 3.0]);
+        assert_eq!(ManeuverFrame::Inertial.to_inertial(&[1.0, 2.0, 3.0], &position, &velocity), [1.0, 2.0, 3.0]);
+        let mut spacecraft = Spacecraft::new(1.0, 1.0, 300.0);
+        spacecraft.plan(Maneuver::new(2.0, [0.0; 3], ManeuverFrame::Inertial, None));
+        spacecraft.plan(Maneuver::new(1.0, [0.0; 3], ManeuverFrame::Rtn, Some("Earth".to_string())));
+        assert_eq!(spacecraft.next_maneuver().unwrap().epoch, 1.0);
+        assert!(spacecraft.check().is_ok());
+        spacecraft.plan(Maneuver::new(3.0, [0.0; 3], ManeuverFrame::Vnb, None));
+        assert!(spacecraft.check().is_err());
+        assert_eq!(ManeuverFrame::from_name("RTN"), Ok(ManeuverFrame::Rtn));
+    }
+    #[test]
+    fn thrust_until_empty() {
+        assert_eq!(Steering::new("Tangential", Some("Sun"), None), Ok(Steering::Tangential("Sun".to_string())));
+        assert!(Steering::new("anti-sun", None, None).is_err());
+        assert!(Steering::new("inertial", None, Some([0.0; 3])).is_err());
+        assert!(Steering::new("retrograde", None, None).is_err());
+        let steering = Steering::new("inertial", None, Some([0.0, 0.0, 2.0])).unwrap();
+        assert_eq!(steering.direction(&[1.0, 0.0, 0.0], &[0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]);
+        assert_eq!(Steering::AntiSun("Sun".to_string()).direction(&[3.0, 0.0, 0.0], &[0.0, 1.0, 0.0]), [1.0, 0.0, 0.0]);
+        assert_eq!(Steering::Tangential("Sun".to_string()).direction(&[3.0, 0.0, 0.0], &[0.0, 1.0, 0.0]), [0.0, 1.0, 0.0]);
+        let thruster = Thruster::new(0.5, 3000.0, steering);
+        let flow = 0.5 / (3000.0 * STANDARD_GRAVITY);
+        let mut spacecraft = Spacecraft::new(500.0, 10.0, 300.0).with_thruster(thruster);
+        let acceleration = spacecraft.thrust(1.0);
+        assert_float_eq!(spacecraft.fuel_mass, 10.0 - flow * DAY_TO_S, r2nd <= 1.0E-12);
+        assert_float_eq!(acceleration * DAY_TO_S, 3000.0 * STANDARD_GRAVITY * (510.0 / spacecraft.mass()).ln(), r2nd <= 1.0E-12);
+        let mass = spacecraft.mass();
+        let acceleration = spacecraft.thrust(1000.0);
+        assert_float_eq!(acceleration * 1000.0 * DAY_TO_S, 3000.0 * STANDARD_GRAVITY * (mass / 500.0).ln(), r2nd <= 1.0E-12);
+        assert_eq!(spacecraft.fuel_mass, 0.0);
+        assert_eq!(spacecraft.thrust(1.0), 0.0);
+        assert!(Spacecraft::new(1.0, 1.0, 300.0).with_thruster(Thruster::new(-1.0, 300.0, Steering::Inertial([1.0; 3]))).check().is_err());
+    }
+}
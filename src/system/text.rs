@@ -0,0 +1,21 @@
+use std::{
+    fs,
+    io::Write
+};
+pub fn csv_field(value: &str) -> String {
+    if value.contains(|a| matches!(a, ',' | ';' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+pub fn write_text(file_path: &str, contents: &str) -> Result<(), String> {
+    let mut file = match fs::File::create(file_path) {
+        Err(x) => return Err(format!("Failed creating {}!\n{}", file_path, x)),
+        Ok(x) => x
+    };
+    match file.write_all(contents.as_bytes()) {
+        Err(x) => Err(format!("Failed writing to {}!\n{}", file_path, x)),
+        _ => Ok(())
+    }
+}
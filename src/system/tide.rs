@@ -0,0 +1,86 @@
+use serde::{
+    Deserialize,
+    Serialize
+};
+use crate::units::{
+    AU_TO_KM,
+    DAY_TO_S,
+    GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+};
+use super::vector;
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tide {
+    pub love_number: f64,
+    pub time_lag: f64,
+    pub moment_of_inertia: f64,
+    pub spin: [f64; 3]
+}
+impl Tide {
+    pub fn new(love_number: f64, time_lag: f64, moment_of_inertia: f64, spin: [f64; 3]) -> Tide {
+        Tide {
+            love_number,
+            time_lag,
+            moment_of_inertia,
+            spin
+        }
+    }
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.love_number >= 0.0 && self.love_number.is_finite()) {
+            return Err(format!("Love number {} must not be negative", self.love_number))
+        }
+        if !(self.time_lag >= 0.0 && self.time_lag.is_finite()) {
+            return Err(format!("Time lag {} must not be negative", self.time_lag))
+        }
+        if !(self.moment_of_inertia > 0.0 && self.moment_of_inertia.is_finite()) {
+            return Err(format!("Moment of inertia factor {} must be positive", self.moment_of_inertia))
+        }
+        if !self.spin.iter().all(|a| a.is_finite()) {
+            return Err(format!("Spin {:?} must be finite", self.spin))
+        }
+        Ok(())
+    }
+    pub fn rotation_period(&self) -> f64 {
+        2.0 * std::f64::consts::PI / vector::norm(&self.spin)
+    }
+    pub fn acceleration(&self, radius: f64, perturber_mass: f64, position: &[f64; 3], velocity: &[f64; 3]) -> [f64; 3] {
+        let radius = radius / AU_TO_KM;
+        let distance2 = vector::dot(position, position);
+        if distance2 == 0.0 || radius == 0.0 {
+            return [0.0; 3]
+        }
+        let strength = 3.0 * perturber_mass * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2 * self.love_number * radius.powi(5) / distance2.powi(5);
+        let time_lag = self.time_lag / DAY_TO_S;
+        let lag = vector::add(
+            &vector::scale(position, 2.0 * vector::dot(position, velocity)),
+            &vector::scale(&vector::subtract(velocity, &vector::cross(&self.spin, position)), distance2));
+        vector::scale(&vector::add(&vector::scale(position, distance2), &vector::scale(&lag, time_lag)), -strength)
+    }
+    pub fn spin_kick(&mut self, radius: f64, mass: f64, perturber_mass: f64, position: &[f64; 3], acceleration: &[f64; 3], delta_time: f64) {
+        let radius = radius / AU_TO_KM;
+        let inertia = self.moment_of_inertia * mass * radius * radius;
+        let torque = vector::scale(&vector::cross(position, acceleration), -perturber_mass / inertia);
+        self.spin = vector::add(&self.spin, &vector::scale(&torque, delta_time))
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    #[test]
+    fn lag_behind_the_perturber() {
+        let position = [0.002, 0.0, 0.0];
+        let rate = 0.23;
+        let synchronous = Tide::new(0.3, 600.0, 0.33, [0.0, 0.0, rate]);
+        let acceleration = synchronous.acceleration(6371.0, 4902.8, &position, &[0.0, 0.002 * rate, 0.0]);
+        let radius: f64 = 6371.0 / AU_TO_KM;
+        let expected = -3.0 * 4902.8 * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2 * 0.3 * radius.powi(5) / 0.002f64.powi(7);
+        assert_float_eq!(acceleration, [expected, 0.0, 0.0], r2nd <= [1.0E-12; 3]);
+        let faster = Tide::new(0.3, 600.0, 0.33, [0.0, 0.0, 6.3]);
+        assert!(faster.acceleration(6371.0, 4902.8, &position, &[0.0, 0.002 * rate, 0.0])[1] > 0.0);
+        let slower = Tide::new(0.3, 600.0, 0.33, [0.0, 0.0, 0.0]);
+        assert!(slower.acceleration(6371.0, 4902.8, &position, &[0.0, 0.002 * rate, 0.0])[1] < 0.0);
+        assert_float_eq!(faster.rotation_period(), 2.0 * std::f64::consts::PI / 6.3, r2nd <= 1.0E-15);
+        assert!(Tide::new(-0.3, 600.0, 0.33, [0.0; 3]).check().is_err());
+        assert!(Tide::new(0.3, 600.0, 0.0, [0.0; 3]).check().is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::units::GRAVITATIONAL_MASS_TO_AU3_PER_DAY2;
+use super::vector;
+#[derive(Clone, Debug, PartialEq)]
+pub struct TangentVector {
+    pub position: Vec<[f64; 3]>,
+    pub velocity: Vec<[f64; 3]>
+}
+impl TangentVector {
+    pub fn zero(size: usize) -> TangentVector {
+        TangentVector {
+            position: vec![[0.0; 3]; size],
+            velocity: vec![[0.0; 3]; size]
+        }
+    }
+    pub fn size(&self) -> usize {
+        self.position.len()
+    }
+    pub fn norm_squared(&self, bodies: &[usize]) -> f64 {
+        bodies.iter()
+            .map(|a| vector::dot(&self.position[*a], &self.position[*a]) + vector::dot(&self.velocity[*a], &self.velocity[*a]))
+            .sum()
+    }
+    pub fn scale(&mut self, factor: f64) {
+        self.position.iter_mut().chain(self.velocity.iter_mut()).for_each(|a| *a = vector::scale(a, factor))
+    }
+    pub(super) fn push(&mut self) {
+        self.position.push([0.0; 3]);
+        self.velocity.push([0.0; 3]);
+    }
+    pub(super) fn kick(&mut self, acceleration: &[[f64; 3]], delta_time: f64) {
+        self.velocity.iter_mut().zip(acceleration.iter()).for_each(|(a, b)| {
+            *a = vector::add(a, &vector::scale(b, delta_time))
+        })
+    }
+    pub(super) fn drift(&mut self, delta_time: f64) {
+        self.position.iter_mut().zip(self.velocity.iter()).for_each(|(a, b)| {
+            *a = vector::add(a, &vector::scale(b, delta_time))
+        })
+    }
+}
+pub(super) fn linearized_accelerations(state: &[(f64, [f64; 3])], tangent: &[[f64; 3]]) -> Vec<[f64; 3]> {
+    use rayon::prelude::*;
+    state.par_iter().enumerate().map(|(i, (_, a_position))| {
+        state.iter().enumerate()
+            .filter(|(j, (b_mass, _))| *j != i && *b_mass != 0.0)
+            .fold([0.0; 3], |acceleration, (j, (b_mass, b_position))| {
+                let separation = vector::subtract(b_position, a_position);
+                let displacement = vector::subtract(&tangent[j], &tangent[i]);
+                let inv_r2 = 1.0 / vector::dot(&separation, &separation);
+                let inv_r3 = inv_r2.sqrt() * inv_r2 * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2 * b_mass;
+                let radial = 3.0 * vector::dot(&separation, &displacement) * inv_r2;
+                vector::add(&acceleration, &vector::scale(
+                    &vector::subtract(&displacement, &vector::scale(&separation, radial)), inv_r3))
+            })
+    }).collect()
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::{
+        body::Body,
+        integrator::Integrator,
+        System
+    };
+    use float_eq::assert_float_eq;
+    fn perturbed_system(offset: &TangentVector, size: f64) -> System {
+        let mut system = System::new();
+        let bodies = [
+            ("Sun", 132712440041.93938, [0.0; 3], [0.0; 3]),
+            ("Jupiter", 126686531.9003704, [5.2, 0.0, 0.1], [0.0, 0.0075, 0.0001]),
+            ("Saturn", 37931206.15862803, [0.0, -9.5, -0.2], [0.0056, 0.0, 0.0])
+        ];
+        for (i, (name, mass, position, velocity)) in bodies.iter().enumerate() {
+            system.add(Body::new(name.to_string(), *mass, 0.0,
+                vector::add(position, &vector::scale(&offset.position[i], size)),
+                vector::add(velocity, &vector::scale(&offset.velocity[i], size))));
+        }
+        system.set_delta_time(20.0);
+        system
+    }
+    #[test]
+    fn match_finite_differences() -> Result<(), String> {
+        let mut offset = TangentVector::zero(3);
+        offset.position[1] = [0.3, -0.2, 0.1];
+        offset.velocity[2] = [0.0001, 0.0002, -0.0001];
+        for integrator in Integrator::all().iter() {
+            let mut system = perturbed_system(&offset, 0.0);
+            let mut ahead = perturbed_system(&offset, 1.0E-6);
+            let mut behind = perturbed_system(&offset, -1.0E-6);
+            let tangent = system.add_tangent(offset.clone())?;
+            for a in [&mut system, &mut ahead, &mut behind].iter_mut() {
+                a.set_integrator(*integrator);
+                (0..500).for_each(|_| a.step());
+            }
+            let tangent = system.tangent(tangent).unwrap();
+            for i in 0..3 {
+                let ahead = ahead.object_from_index(i).unwrap();
+                let behind = behind.object_from_index(i).unwrap();
+                let (ahead, behind) = (ahead.read().unwrap(), behind.read().unwrap());
+                let position = vector::scale(&vector::subtract(&ahead.position(), &behind.position()), 0.5E6);
+                let velocity = vector::scale(&vector::subtract(&ahead.velocity(), &behind.velocity()), 0.5E6);
+                assert_float_eq!(tangent.position[i], position, abs <= [1.0E-6; 3]);
+                assert_float_eq!(tangent.velocity[i], velocity, abs <= [1.0E-9; 3]);
+            }
+        }
+        assert!(System::new().add_tangent(offset).is_err());
+        Ok(())
+    }
+}
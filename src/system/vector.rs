@@ -0,0 +1,33 @@
+pub fn add(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+pub fn subtract(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+pub fn scale(a: &[f64; 3], factor: f64) -> [f64; 3] {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+pub fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+pub fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0]
+    ]
+}
+pub fn norm(a: &[f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+pub fn normalize(a: &[f64; 3]) -> [f64; 3] {
+    let length = norm(a);
+    if length == 0.0 {
+        [0.0; 3]
+    } else {
+        scale(a, 1.0 / length)
+    }
+}
+pub fn distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    norm(&subtract(a, b))
+}
@@ -0,0 +1,296 @@
+use std::fmt;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crate::units::DAY_TO_S;
+pub const J2000: f64 = 2451545.0;
+pub const MJD_OFFSET: f64 = 2400000.5;
+pub const TT_MINUS_TAI: f64 = 32.184;
+const LEAP_SECONDS: [(f64, f64, f64, f64); 41] = [
+    (37300.0, 1.4228180, 37300.0, 0.001296),
+    (37512.0, 1.3728180, 37300.0, 0.001296),
+    (37665.0, 1.8458580, 37665.0, 0.0011232),
+    (38334.0, 1.9458580, 37665.0, 0.0011232),
+    (38395.0, 3.2401300, 38761.0, 0.001296),
+    (38486.0, 3.3401300, 38761.0, 0.001296),
+    (38639.0, 3.4401300, 38761.0, 0.001296),
+    (38761.0, 3.5401300, 38761.0, 0.001296),
+    (38820.0, 3.6401300, 38761.0, 0.001296),
+    (38942.0, 3.7401300, 38761.0, 0.001296),
+    (39004.0, 3.8401300, 38761.0, 0.001296),
+    (39126.0, 4.3131700, 39126.0, 0.002592),
+    (39887.0, 4.2131700, 39126.0, 0.002592),
+    (41317.0, 10.0, 0.0, 0.0),
+    (41499.0, 11.0, 0.0, 0.0),
+    (41683.0, 12.0, 0.0, 0.0),
+    (42048.0, 13.0, 0.0, 0.0),
+    (42413.0, 14.0, 0.0, 0.0),
+    (42778.0, 15.0, 0.0, 0.0),
+    (43144.0, 16.0, 0.0, 0.0),
+    (43509.0, 17.0, 0.0, 0.0),
+    (43874.0, 18.0, 0.0, 0.0),
+    (44239.0, 19.0, 0.0, 0.0),
+    (44786.0, 20.0, 0.0, 0.0),
+    (45151.0, 21.0, 0.0, 0.0),
+    (45516.0, 22.0, 0.0, 0.0),
+    (46247.0, 23.0, 0.0, 0.0),
+    (47161.0, 24.0, 0.0, 0.0),
+    (47892.0, 25.0, 0.0, 0.0),
+    (48257.0, 26.0, 0.0, 0.0),
+    (48804.0, 27.0, 0.0, 0.0),
+    (49169.0, 28.0, 0.0, 0.0),
+    (49534.0, 29.0, 0.0, 0.0),
+    (50083.0, 30.0, 0.0, 0.0),
+    (50630.0, 31.0, 0.0, 0.0),
+    (51179.0, 32.0, 0.0, 0.0),
+    (53736.0, 33.0, 0.0, 0.0),
+    (54832.0, 34.0, 0.0, 0.0),
+    (56109.0, 35.0, 0.0, 0.0),
+    (57204.0, 36.0, 0.0, 0.0),
+    (57754.0, 37.0, 0.0, 0.0)
+];
+pub fn tai_minus_utc(modified_julian_date: f64) -> f64 {
+    let (_, offset, reference, rate) = match LEAP_SECONDS.iter().rev().find(|a| a.0 <= modified_julian_date.floor()) {
+        Some(x) => *x,
+        None => return LEAP_SECONDS[0].1
+    };
+    offset + (modified_julian_date - reference) * rate
+}
+pub fn tdb_minus_tt(julian_date: f64) -> f64 {
+    let anomaly = (357.53 + 0.98560028 * (julian_date - J2000)).to_radians();
+    0.001657 * anomaly.sin() + 0.000014 * (2.0 * anomaly).sin()
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeScale {
+    Utc,
+    Tai,
+    Tt,
+    #[default]
+    Tdb
+}
+impl TimeScale {
+    pub fn all() -> Vec<TimeScale> {
+        vec!(TimeScale::Utc, TimeScale::Tai, TimeScale::Tt, TimeScale::Tdb)
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeScale::Utc => "UTC",
+            TimeScale::Tai => "TAI",
+            TimeScale::Tt => "TT",
+            TimeScale::Tdb => "TDB"
+        }
+    }
+    pub fn from_name(name: &str) -> Result<TimeScale, String> {
+        match TimeScale::all().into_iter().find(|a| a.name() == name.to_uppercase()) {
+            Some(x) => Ok(x),
+            None => Err(format!("Failed to find time scale {}! Expected one of: {}",
+                name,
+                TimeScale::all().iter().map(|a| a.name()).collect::<Vec<_>>().join(", ")))
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Epoch {
+    day: f64,
+    fraction: f64,
+    scale: TimeScale
+}
+impl Epoch {
+    fn normalized(day: f64, fraction: f64, scale: TimeScale) -> Epoch {
+        let (day, fraction) = (day.floor(), fraction + (day - day.floor()));
+        let carry = fraction.floor();
+        Epoch {
+            day: day + carry,
+            fraction: fraction - carry,
+            scale
+        }
+    }
+    pub fn from_julian_date(julian_date: f64, scale: TimeScale) -> Epoch {
+        Epoch::normalized(julian_date, 0.0, scale)
+    }
+    pub fn from_modified_julian_date(modified_julian_date: f64, scale: TimeScale) -> Epoch {
+        Epoch::normalized(modified_julian_date.floor() + MJD_OFFSET, modified_julian_date - modified_julian_date.floor(), scale)
+    }
+    pub fn from_calendar(
+        year: i32,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: f64,
+        scale: TimeScale
+    ) -> Result<Epoch, String> {
+        let date = match time::Date::try_from_ymd(year, month, day) {
+            Ok(x) => x,
+            Err(x) => return Err(format!("Failed to read date {}-{:02}-{:02}!\n{}", year, month, day, x))
+        };
+        if hour > 23 || minute > 59 || !(0.0..61.0).contains(&second) {
+            return Err(format!("Failed to read time {:02}:{:02}:{}!", hour, minute, second))
+        }
+        let seconds = hour as f64 * 3600.0 + minute as f64 * 60.0 + second;
+        Ok(Epoch::normalized(date.julian_day() as f64 - 0.5, seconds / DAY_TO_S, scale))
+    }
+    pub fn from_date(date: &time::Date, scale: TimeScale) -> Epoch {
+        Epoch::normalized(date.julian_day() as f64 - 0.5, 0.0, scale)
+    }
+    pub fn parse(text: &str, default: TimeScale) -> Result<Epoch, String> {
+        let error = |x: &str| format!("Failed to parse epoch {}! Expected yyyy-mm-dd, yyyy-mm-dd hh:mm[:ss], \
+            JD <julian date> or MJD <date>, optionally followed by UTC, TAI, TT or TDB\n{}", text, x);
+        let mut words: Vec<&str> = text.split_whitespace().collect();
+        if let Some(x) = words.first().copied().filter(|a| a.contains('-') && a.contains('T')) {
+            words.splice(0..1, x.splitn(2, 'T'));
+        }
+        let scale = match words.last().map(|a| TimeScale::from_name(a)) {
+            Some(Ok(x)) if words.len() > 1 => {
+                words.pop();
+                x
+            },
+            _ => default
+        };
+        let number = |a: &str| a.parse::<f64>().map_err(|x| error(&x.to_string()));
+        match words.as_slice() {
+            [kind, value] if kind.eq_ignore_ascii_case("JD") => Ok(Epoch::from_julian_date(number(value)?, scale)),
+            [kind, value] if kind.eq_ignore_ascii_case("MJD") => Ok(Epoch::from_modified_julian_date(number(value)?, scale)),
+            [date] | [date, _] => {
+                let date = match time::Date::parse(date, "%F") {
+                    Ok(x) => x,
+                    Err(x) => return Err(error(&x.to_string()))
+                };
+                let clock: Vec<f64> = match words.get(1) {
+                    Some(x) => x.split(':').map(number).collect::<Result<_, _>>()?,
+                    None => Vec::new()
+                };
+                if clock.len() > 3 || clock.len() == 1 || clock.iter().any(|a| *a < 0.0) ||
+                    clock.iter().take(2).any(|a| a.fract() != 0.0) {
+                    return Err(error("The time of day must be hh:mm or hh:mm:ss"))
+                }
+                let part = |i: usize| clock.get(i).copied().unwrap_or(0.0);
+                Epoch::from_calendar(date.year(), date.month(), date.day(), part(0) as u8, part(1) as u8, part(2), scale)
+                    .map_err(|x| error(&x))
+            },
+            _ => Err(error("Too many fields"))
+        }
+    }
+    pub fn scale(&self) -> TimeScale {
+        self.scale
+    }
+    pub fn julian_date(&self) -> f64 {
+        self.day + self.fraction
+    }
+    pub fn modified_julian_date(&self) -> f64 {
+        (self.day - MJD_OFFSET) + self.fraction
+    }
+    pub fn add_days(&self, days: f64) -> Epoch {
+        Epoch::normalized(self.day + days.trunc(), self.fraction + days.fract(), self.scale)
+    }
+    pub fn add_seconds(&self, seconds: f64) -> Epoch {
+        self.add_days(seconds / DAY_TO_S)
+    }
+    pub fn days_since(&self, other: &Epoch) -> f64 {
+        let other = other.to(self.scale);
+        (self.day - other.day) + (self.fraction - other.fraction)
+    }
+    fn step(&self, scale: TimeScale) -> Epoch {
+        let seconds = match (self.scale, scale) {
+            (TimeScale::Utc, TimeScale::Tai) => tai_minus_utc(self.modified_julian_date()),
+            (TimeScale::Tai, TimeScale::Utc) => {
+                let guess = self.add_seconds(-tai_minus_utc(self.modified_julian_date()));
+                -tai_minus_utc(guess.modified_julian_date())
+            },
+            (TimeScale::Tai, TimeScale::Tt) => TT_MINUS_TAI,
+            (TimeScale::Tt, TimeScale::Tai) => -TT_MINUS_TAI,
+            (TimeScale::Tt, TimeScale::Tdb) => tdb_minus_tt(self.julian_date()),
+            (TimeScale::Tdb, TimeScale::Tt) => -tdb_minus_tt(self.julian_date()),
+            _ => 0.0
+        };
+        Epoch {
+            scale,
+            ..self.add_seconds(seconds)
+        }
+    }
+    pub fn to(&self, scale: TimeScale) -> Epoch {
+        let order = TimeScale::all();
+        let position = |a: TimeScale| order.iter().position(|b| *b == a).unwrap();
+        let (mut epoch, target) = (*self, position(scale));
+        while position(epoch.scale) != target {
+            let next = if position(epoch.scale) < target { position(epoch.scale) + 1 } else { position(epoch.scale) - 1 };
+            epoch = epoch.step(order[next]);
+        }
+        epoch
+    }
+    pub fn calendar(&self) -> (time::Date, f64) {
+        let day = (self.day + 0.5).floor();
+        let fraction = self.fraction + (self.day + 0.5 - day);
+        let carry = fraction.floor();
+        (time::Date::from_julian_day((day + carry) as i64), (fraction - carry) * DAY_TO_S)
+    }
+    pub fn format(&self, date_format: &str, decimals: usize) -> String {
+        let (date, seconds) = self.calendar();
+        let scale = 10f64.powi(decimals as i32);
+        let rounded = (seconds * scale).round() / scale;
+        let (date, rounded) = if rounded >= DAY_TO_S { (date.next_day(), 0.0) } else { (date, rounded) };
+        let whole = rounded.floor() as i64;
+        let second = if decimals == 0 {
+            format!("{:02}", whole % 60)
+        } else {
+            format!("{:0width$.decimals$}", rounded - (whole - whole % 60) as f64, width = decimals + 3, decimals = decimals)
+        };
+        format!("{} {:02}:{:02}:{}", date.format(date_format), whole / 3600, whole / 60 % 60, second)
+    }
+}
+impl fmt::Display for Epoch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.format("%F", 0), self.scale.name())
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    #[test]
+    fn convert_between_scales() -> Result<(), String> {
+        let offset = |a: &Epoch, b: &Epoch| ((a.day - b.day) + (a.fraction - b.fraction)) * DAY_TO_S;
+        let utc = Epoch::from_calendar(2017, 1, 1, 0, 0, 0.0, TimeScale::Utc)?;
+        let tt = utc.to(TimeScale::Tt);
+        assert_float_eq!(tt.days_since(&Epoch::from_calendar(2017, 1, 1, 0, 1, 9.184, TimeScale::Tt)?) * DAY_TO_S, 0.0,
+            abs <= 1.0E-6);
+        let before = Epoch::from_calendar(2016, 12, 31, 23, 59, 59.0, TimeScale::Utc)?;
+        assert_float_eq!(utc.to(TimeScale::Tai).days_since(&before.to(TimeScale::Tai)) * DAY_TO_S, 2.0, abs <= 1.0E-6);
+        let apollo = Epoch::from_calendar(1969, 7, 16, 13, 32, 0.0, TimeScale::Utc)?;
+        assert_float_eq!(offset(&apollo.to(TimeScale::Tai), &apollo), 4.2131700 + (40418.564 - 39126.0) * 0.002592, abs <= 1.0E-4);
+        let j2000 = Epoch::from_julian_date(J2000, TimeScale::Tt);
+        assert_float_eq!(offset(&j2000.to(TimeScale::Tdb), &j2000), tdb_minus_tt(J2000), abs <= 1.0E-9);
+        assert_float_eq!(tdb_minus_tt(J2000), -0.0000726, abs <= 1.0E-7);
+        assert_float_eq!(offset(&utc.to(TimeScale::Tdb), &utc), 69.184 + tdb_minus_tt(2457754.5), abs <= 1.0E-6);
+        let trinity = Epoch::from_calendar(1945, 7, 16, 0, 0, 0.0, TimeScale::Utc)?;
+        assert_eq!(tai_minus_utc(trinity.modified_julian_date()), 1.4228180);
+        assert_eq!(tai_minus_utc(37299.9), tai_minus_utc(37300.0));
+        assert_float_eq!(offset(&trinity.to(TimeScale::Tai), &trinity), 1.4228180, abs <= 1.0E-6);
+        assert_float_eq!(trinity.to(TimeScale::Tdb).to(TimeScale::Utc).days_since(&trinity) * DAY_TO_S, 0.0, abs <= 1.0E-6);
+        for scale in TimeScale::all() {
+            let there = apollo.to(scale);
+            assert_eq!(there.scale(), scale);
+            assert_float_eq!(there.to(TimeScale::Utc).days_since(&apollo) * DAY_TO_S, 0.0, abs <= 1.0E-6);
+        }
+        Ok(())
+    }
+    #[test]
+    fn parse_and_format_epochs() -> Result<(), String> {
+        let epoch = Epoch::parse("1969-07-16", TimeScale::Tdb)?;
+        assert_eq!(epoch.julian_date(), 2440418.5);
+        assert_eq!(epoch.modified_julian_date(), 40418.0);
+        assert_eq!(epoch.to_string(), "1969-07-16 00:00:00 TDB");
+        let epoch = Epoch::parse("1969-07-16T13:32:00.5 UTC", TimeScale::Tdb)?;
+        assert_eq!(epoch.scale(), TimeScale::Utc);
+        assert_float_eq!(epoch.modified_julian_date(), 40418.0 + (13.0 * 3600.0 + 32.0 * 60.0 + 0.5) / DAY_TO_S, abs <= 1.0E-10);
+        assert_eq!(epoch.format("%Y-%b-%d", 3), "1969-Jul-16 13:32:00.500");
+        assert_eq!(Epoch::parse("JD 2451545.0 tt", TimeScale::Tdb)?, Epoch::from_julian_date(J2000, TimeScale::Tt));
+        assert_eq!(Epoch::parse("MJD 51544.5", TimeScale::Tt)?, Epoch::from_julian_date(J2000, TimeScale::Tt));
+        assert_eq!(Epoch::from_julian_date(2440419.5 - 1.0E-10, TimeScale::Tdb).to_string(), "1969-07-17 00:00:00 TDB");
+        assert!(Epoch::parse("1969-07-16 25:00", TimeScale::Tdb).is_err());
+        assert!(Epoch::parse("1969-07-16 12", TimeScale::Tdb).is_err());
+        assert!(Epoch::parse("16/07/1969", TimeScale::Tdb).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use std::ops::{
+    Add,
+    Div,
+    Mul
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+pub const AU_TO_KM: f64 = 149597870.7;
+pub const DAY_TO_S: f64 = 86400.0;
+pub const AU_PER_DAY_TO_KM_PER_S: f64 = AU_TO_KM / DAY_TO_S;
+pub const AU_PER_DAY_TO_M_PER_S: f64 = AU_TO_KM * 1000.0 / DAY_TO_S;
+pub const M_PER_S2_TO_AU_PER_DAY2: f64 = DAY_TO_S * DAY_TO_S / (AU_TO_KM * 1000.0);
+pub const AU_PER_LIGHT_DAY: f64 = 173.1446326742403;
+pub const GRAVITATIONAL_MASS_TO_AU3_PER_DAY2: f64 = DAY_TO_S * DAY_TO_S / (AU_TO_KM * AU_TO_KM * AU_TO_KM);
+pub const GRAVITATIONAL_CONSTANT: f64 = 6.6743E-20;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    #[default]
+    Au,
+    Km,
+    M
+}
+impl LengthUnit {
+    fn to_au(self) -> f64 {
+        match self {
+            LengthUnit::Au => 1.0,
+            LengthUnit::Km => 1.0 / AU_TO_KM,
+            LengthUnit::M => 0.001 / AU_TO_KM
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeUnit {
+    #[default]
+    #[serde(alias = "d")]
+    Day,
+    #[serde(alias = "h")]
+    Hour,
+    S
+}
+impl TimeUnit {
+    fn to_days(self) -> f64 {
+        match self {
+            TimeUnit::Day => 1.0,
+            TimeUnit::Hour => 1.0 / 24.0,
+            TimeUnit::S => 1.0 / DAY_TO_S
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MassUnit {
+    #[default]
+    #[serde(rename = "km3/s2")]
+    Km3PerS2,
+    #[serde(rename = "m3/s2")]
+    M3PerS2,
+    #[serde(rename = "au3/d2")]
+    Au3PerDay2,
+    #[serde(rename = "kg")]
+    Kg
+}
+impl MassUnit {
+    fn to_km3_per_s2(self) -> f64 {
+        match self {
+            MassUnit::Km3PerS2 => 1.0,
+            MassUnit::M3PerS2 => 1.0E-9,
+            MassUnit::Au3PerDay2 => 1.0 / GRAVITATIONAL_MASS_TO_AU3_PER_DAY2,
+            MassUnit::Kg => GRAVITATIONAL_CONSTANT
+        }
+    }
+}
+macro_rules! quantity {
+    ($name:ident) => {
+        impl Add for $name {
+            type Output = $name;
+            fn add(self, other: $name) -> $name {
+                $name(self.0 + other.0)
+            }
+        }
+        impl Mul<f64> for $name {
+            type Output = $name;
+            fn mul(self, factor: f64) -> $name {
+                $name(self.0 * factor)
+            }
+        }
+        impl Div for $name {
+            type Output = f64;
+            fn div(self, other: $name) -> f64 {
+                self.0 / other.0
+            }
+        }
+    };
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Length(f64);
+quantity!(Length);
+impl Length {
+    pub fn new(value: f64, unit: LengthUnit) -> Length {
+        Length(value * unit.to_au())
+    }
+    pub fn from_km(value: f64) -> Length {
+        Length::new(value, LengthUnit::Km)
+    }
+    pub fn in_unit(&self, unit: LengthUnit) -> f64 {
+        self.0 / unit.to_au()
+    }
+    pub fn au(&self) -> f64 {
+        self.0
+    }
+    pub fn km(&self) -> f64 {
+        self.in_unit(LengthUnit::Km)
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Speed(f64);
+quantity!(Speed);
+impl Speed {
+    pub fn new(value: f64, length: LengthUnit, time: TimeUnit) -> Speed {
+        Speed(value * length.to_au() / time.to_days())
+    }
+    pub fn from_km_per_s(value: f64) -> Speed {
+        Speed::new(value, LengthUnit::Km, TimeUnit::S)
+    }
+    pub fn in_unit(&self, length: LengthUnit, time: TimeUnit) -> f64 {
+        self.0 * time.to_days() / length.to_au()
+    }
+    pub fn au_per_day(&self) -> f64 {
+        self.0
+    }
+    pub fn km_per_s(&self) -> f64 {
+        self.in_unit(LengthUnit::Km, TimeUnit::S)
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct GravitationalMass(f64);
+quantity!(GravitationalMass);
+impl GravitationalMass {
+    pub fn new(value: f64, unit: MassUnit) -> GravitationalMass {
+        GravitationalMass(value * unit.to_km3_per_s2())
+    }
+    pub fn from_km3_per_s2(value: f64) -> GravitationalMass {
+        GravitationalMass(value)
+    }
+    pub fn in_unit(&self, unit: MassUnit) -> f64 {
+        self.0 / unit.to_km3_per_s2()
+    }
+    pub fn km3_per_s2(&self) -> f64 {
+        self.0
+    }
+    pub fn au3_per_day2(&self) -> f64 {
+        self.0 * GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Units {
+    #[serde(default)]
+    pub length: LengthUnit,
+    #[serde(default)]
+    pub time: TimeUnit,
+    #[serde(default)]
+    pub gravitational_mass: MassUnit,
+    #[serde(default = "default_radius")]
+    pub radius: LengthUnit
+}
+fn default_radius() -> LengthUnit {
+    LengthUnit::Km
+}
+impl Default for Units {
+    fn default() -> Units {
+        Units::canonical()
+    }
+}
+impl Units {
+    pub fn canonical() -> Units {
+        Units {
+            length: LengthUnit::Au,
+            time: TimeUnit::Day,
+            gravitational_mass: MassUnit::Km3PerS2,
+            radius: LengthUnit::Km
+        }
+    }
+    pub fn si() -> Units {
+        Units {
+            length: LengthUnit::M,
+            time: TimeUnit::S,
+            gravitational_mass: MassUnit::M3PerS2,
+            radius: LengthUnit::M
+        }
+    }
+    pub fn is_canonical(&self) -> bool {
+        *self == Units::canonical()
+    }
+    pub fn position(&self, value: &[f64; 3]) -> [Length; 3] {
+        [Length::new(value[0], self.length), Length::new(value[1], self.length), Length::new(value[2], self.length)]
+    }
+    pub fn velocity(&self, value: &[f64; 3]) -> [Speed; 3] {
+        let speed = |a: f64| Speed::new(a, self.length, self.time);
+        [speed(value[0]), speed(value[1]), speed(value[2])]
+    }
+    pub fn gravitational_mass(&self, value: f64) -> GravitationalMass {
+        GravitationalMass::new(value, self.gravitational_mass)
+    }
+    pub fn radius(&self, value: f64) -> Length {
+        Length::new(value, self.radius)
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+    #[test]
+    fn convert_quantities() {
+        assert_float_eq!(Length::from_km(AU_TO_KM).au(), 1.0, ulps <= 1);
+        assert_float_eq!(Length::new(1.0, LengthUnit::M).km(), 0.001, ulps <= 1);
+        assert_float_eq!(Speed::from_km_per_s(AU_PER_DAY_TO_KM_PER_S).au_per_day(), 1.0, ulps <= 1);
+        assert_float_eq!(Speed::new(29.78, LengthUnit::Km, TimeUnit::S).km_per_s(), 29.78, ulps <= 2);
+        assert_float_eq!(Speed::new(2.0, LengthUnit::Au, TimeUnit::Hour).au_per_day(), 48.0, ulps <= 1);
+        assert_float_eq!(GravitationalMass::new(3.986004418E14, MassUnit::M3PerS2).km3_per_s2(), 398600.4418, ulps <= 1);
+        assert_float_eq!(GravitationalMass::new(5.9722E24, MassUnit::Kg).km3_per_s2(), 398602.5, abs <= 0.1);
+        let sun = GravitationalMass::from_km3_per_s2(132712440041.93938);
+        assert_float_eq!(sun.au3_per_day2(), 0.0002959122082855911, r2nd <= 1.0E-15);
+        assert_float_eq!(GravitationalMass::new(sun.au3_per_day2(), MassUnit::Au3PerDay2).km3_per_s2(), sun.km3_per_s2(),
+            r2nd <= 1.0E-15);
+        assert_float_eq!(AU_PER_LIGHT_DAY, 299792.458 * DAY_TO_S / AU_TO_KM, ulps <= 1);
+    }
+    #[test]
+    fn read_units() {
+        let units: Units = serde_json::from_str("{\"length\": \"km\", \"time\": \"s\"}").unwrap();
+        assert_eq!(units.gravitational_mass, MassUnit::Km3PerS2);
+        assert_eq!(units.radius, LengthUnit::Km);
+        let units: Units = serde_json::from_str(&serde_json::to_string(&Units::si()).unwrap()).unwrap();
+        assert_eq!(units, Units::si());
+        assert!(serde_json::from_str::<Units>("{\"length\": \"parsec\"}").is_err());
+        assert!(serde_json::from_str::<Units>("{}").unwrap().is_canonical());
+    }
+}
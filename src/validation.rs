@@ -0,0 +1,291 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    time::Instant
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use crate::{
+    runner::{
+        self,
+        RunConfig,
+        Stop
+    },
+    system::{
+        integrator::Integrator,
+        vector,
+        System
+    },
+    timescale::Epoch,
+    units::{
+        AU_PER_DAY_TO_M_PER_S,
+        AU_TO_KM
+    }
+};
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReferenceEphemeris {
+    pub source: String,
+    pub snapshot: Vec<System>
+}
+impl ReferenceEphemeris {
+    pub fn fetch_from_horizons<T: AsRef<str>>(list: &[T], dates: &[Epoch]) -> Result<ReferenceEphemeris, String> {
+        let mut snapshot = Vec::new();
+        for date in dates.iter() {
+            let system = System::fetch_from_horizons(list, date)?;
+            if system.size() < list.len() {
+                return Err(format!("Failed to fetch {} of {} targets at {}!", list.len() - system.size(), list.len(), date))
+            }
+            snapshot.push(system);
+        }
+        Ok(ReferenceEphemeris {
+            source: "JPL HORIZONS vectors".to_string(),
+            snapshot
+        })
+    }
+    pub fn check(&self) -> Result<(), String> {
+        if self.snapshot.len() < 2 {
+            return Err(format!("Failed to validate against {}! It needs at least two snapshots", self.source))
+        }
+        for pair in self.snapshot.windows(2) {
+            if pair[1].epoch() <= pair[0].epoch() {
+                return Err(format!("Failed to validate against {}! Snapshot epochs {} and {} are out of order",
+                    self.source, pair[0].epoch(), pair[1].epoch()))
+            }
+        }
+        Ok(())
+    }
+    pub fn save_json(&self, file_path: &str) -> Result<(), String> {
+        let mut file = match fs::File::create(file_path) {
+            Err(x) => return Err(format!("Error creating {}!\n{}", file_path, x)),
+            Ok(x) => x
+        };
+        let contents = match serde_json::to_string_pretty(&self) {
+            Err(x) => return Err(format!("Error serializing {}!\n{}", file_path, x)),
+            Ok(x) => x
+        };
+        match file.write_all(contents.as_bytes()) {
+            Err(x) => Err(format!("Error writing to {}!\n{}", file_path, x)),
+            _ => Ok(())
+        }
+    }
+    pub fn load_json(file_path: &str) -> Result<ReferenceEphemeris, String> {
+        let buffer = match fs::read_to_string(file_path) {
+            Err(x) => return Err(format!("Error reading {}!\n{}", file_path, x)),
+            Ok(x) => x
+        };
+        match serde_json::from_str(&buffer) {
+            Err(x) => Err(format!("Error deserializing {}!\n{}", file_path, x)),
+            Ok(x) => Ok(x)
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tolerance {
+    pub position: Option<f64>,
+    pub velocity: Option<f64>,
+    pub relative: Option<f64>
+}
+impl Tolerance {
+    fn accepts(&self, error: &BodyError) -> bool {
+        self.position.map_or(true, |a| error.position <= a) &&
+            self.velocity.map_or(true, |a| error.velocity <= a) &&
+            match (self.relative, error.relative) {
+                (Some(a), Some(b)) => b <= a,
+                _ => true
+            }
+    }
+}
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tolerances {
+    #[serde(default)]
+    pub default: Tolerance,
+    #[serde(default)]
+    pub body: BTreeMap<String, Tolerance>
+}
+impl Tolerances {
+    pub fn new(default: Tolerance) -> Tolerances {
+        Tolerances {
+            default,
+            body: BTreeMap::new()
+        }
+    }
+    pub fn load_toml(file_path: &str) -> Result<Tolerances, String> {
+        let buffer = match fs::read_to_string(file_path) {
+            Err(x) => return Err(format!("Failed reading {}!\n{}", file_path, x)),
+            Ok(x) => x
+        };
+        match toml::from_str(&buffer) {
+            Err(x) => Err(format!("Failed parsing {}!\n{}", file_path, x)),
+            Ok(x) => Ok(x)
+        }
+    }
+    pub fn for_body(&self, name: &str) -> Tolerance {
+        self.body.iter()
+            .find(|(a, _)| {
+                name == a.as_str() ||
+                    name.ends_with(&format!("({})", a)) ||
+                    name.split(" (").next() == Some(a.as_str())
+            })
+            .map_or(self.default, |(_, b)| *b)
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BodyError {
+    pub name: String,
+    pub position: f64,
+    pub velocity: f64,
+    pub relative: Option<f64>,
+    pub passed: bool
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpochReport {
+    pub epoch: f64,
+    pub body: Vec<BodyError>,
+    pub missing: Vec<String>
+}
+impl EpochReport {
+    pub fn passed(&self) -> bool {
+        self.missing.is_empty() && self.body.iter().all(|a| a.passed)
+    }
+}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub package_version: String,
+    pub reference: String,
+    pub integrator: String,
+    pub delta_time: f64,
+    pub start_epoch: f64,
+    pub tolerances: Tolerances,
+    pub wall_time: f64,
+    pub max_position: f64,
+    pub max_velocity: f64,
+    pub passed: bool,
+    pub epoch: Vec<EpochReport>
+}
+impl ValidationReport {
+    pub fn failures(&self) -> Vec<String> {
+        self.epoch.iter().flat_map(|a| {
+            a.missing.iter()
+                .map(move |b| format!("{} missing at epoch {}", b, a.epoch))
+                .chain(a.body.iter().filter(|b| !b.passed).map(move |b| {
+                    format!("{} off by {:.3} km and {:.6} m/s at epoch {}", b.name, b.position, b.velocity, a.epoch)
+                }))
+        }).collect()
+    }
+    pub fn save_json(&self, file_path: &str) -> Result<(), String> {
+        let mut file = match fs::File::create(file_path) {
+            Err(x) => return Err(format!("Error creating {}!\n{}", file_path, x)),
+            Ok(x) => x
+        };
+        let contents = match serde_json::to_string_pretty(&self) {
+            Err(x) => return Err(format!("Error serializing {}!\n{}", file_path, x)),
+            Ok(x) => x
+        };
+        match file.write_all(contents.as_bytes()) {
+            Err(x) => Err(format!("Error writing to {}!\n{}", file_path, x)),
+            _ => Ok(())
+        }
+    }
+}
+pub fn compare(reference: &System, test: &System, tolerances: &Tolerances) -> EpochReport {
+    let star = reference.object_from_index(0).map(|a| a.read().unwrap().position());
+    let mut report = EpochReport {
+        epoch: reference.epoch(),
+        body: Vec::new(),
+        missing: Vec::new()
+    };
+    for i in 0..reference.size() {
+        let control = reference.object_from_index(i).unwrap();
+        let control = control.read().unwrap();
+        let other = match test.index_of(control.name()) {
+            Some(x) => test.object_from_index(x).unwrap(),
+            None => {
+                report.missing.push(control.name().to_string());
+                continue
+            }
+        };
+        let other = other.read().unwrap();
+        let position = vector::distance(&control.position(), &other.position()) * AU_TO_KM;
+        let velocity = vector::distance(&control.velocity(), &other.velocity()) * AU_PER_DAY_TO_M_PER_S;
+        let relative = match star {
+            Some(x) if i > 0 => Some(position / (vector::distance(&x, &control.position()) * AU_TO_KM)),
+            _ => None
+        };
+        let mut error = BodyError {
+            name: control.name().to_string(),
+            position,
+            velocity,
+            relative,
+            passed: true
+        };
+        error.passed = tolerances.for_body(control.name()).accepts(&error);
+        report.body.push(error);
+    }
+    report
+}
+pub fn validate(
+    reference: &ReferenceEphemeris,
+    integrator: Integrator,
+    delta_time: f64,
+    tolerances: &Tolerances
+) -> Result<ValidationReport, String> {
+    reference.check()?;
+    let start = Instant::now();
+    let mut system = reference.snapshot[0].snapshot();
+    system.set_integrator(integrator);
+    system.set_delta_time(delta_time);
+    let mut epoch = Vec::new();
+    for control in reference.snapshot.iter().skip(1) {
+        runner::run(&mut system, &RunConfig::new(Stop::Epoch(control.epoch())))?;
+        epoch.push(compare(control, &system, tolerances));
+    }
+    let errors = || epoch.iter().flat_map(|a| a.body.iter());
+    Ok(ValidationReport {
+        package_version: env!("CARGO_PKG_VERSION").to_string(),
+        reference: reference.source.clone(),
+        integrator: integrator.name().to_string(),
+        delta_time,
+        start_epoch: reference.snapshot[0].epoch(),
+        tolerances: tolerances.clone(),
+        wall_time: start.elapsed().as_secs_f64(),
+        max_position: errors().map(|a| a.position).fold(0.0, f64::max),
+        max_velocity: errors().map(|a| a.velocity).fold(0.0, f64::max),
+        passed: epoch.iter().all(|a| a.passed()),
+        epoch
+    })
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::system::fixture::two_body_system;
+    #[test]
+    fn fail_beyond_tolerance() -> Result<(), String> {
+        let mut fine = two_body_system();
+        (0..240).for_each(|_| fine.step_by(1.0 / 240.0));
+        let reference = ReferenceEphemeris {
+            source: "fine two body integration".to_string(),
+            snapshot: vec!(two_body_system(), fine)
+        };
+        let loose = Tolerances::new(Tolerance {
+            position: Some(1.0E3),
+            ..Tolerance::default()
+        });
+        let report = validate(&reference, Integrator::KickDriftKick, 1.0 / 24.0, &loose)?;
+        assert!(report.passed, "{:?}", report.failures());
+        assert!(report.max_position > 0.0);
+        let mut tight = loose.clone();
+        tight.body.insert("Earth".to_string(), Tolerance {
+            position: Some(1.0E-6),
+            ..Tolerance::default()
+        });
+        let report = validate(&reference, Integrator::KickDriftKick, 1.0 / 24.0, &tight)?;
+        assert!(!report.passed);
+        assert_eq!(report.failures().len(), 1);
+        Ok(())
+    }
+}
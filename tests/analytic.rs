@@ -0,0 +1,163 @@
+extern crate nbodysim;
+use std::f64::consts::PI;
+use nbodysim::{
+    system::{
+        body::Body,
+        integrator::Integrator,
+        orbital_elements::OrbitalElements,
+        vector,
+        System
+    },
+    units::GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+};
+fn gravitational_mass(gravitational_parameter: f64) -> f64 {
+    gravitational_parameter / GRAVITATIONAL_MASS_TO_AU3_PER_DAY2
+}
+fn state(system: &System, index: usize) -> ([f64; 3], [f64; 3]) {
+    let body = system.object_from_index(index).unwrap();
+    let body = body.read().unwrap();
+    (body.position(), body.velocity())
+}
+fn integrate(system: &mut System, integrator: Integrator, duration: f64, steps: usize) {
+    system.set_integrator(integrator);
+    system.set_delta_time(duration / steps as f64);
+    (0..steps).for_each(|_| system.step());
+}
+fn kepler_system(eccentricity: f64) -> (System, OrbitalElements) {
+    let elements = OrbitalElements {
+        semi_major_axis: 1.0,
+        eccentricity,
+        inclination: 0.3,
+        longitude_of_ascending_node: 1.1,
+        argument_of_periapsis: 0.7,
+        mean_anomaly: PI
+    };
+    let (position, velocity) = elements.to_state(1.0);
+    let mut system = System::new();
+    system.add(Body::new("Primary".to_string(), gravitational_mass(1.0), 0.0, [0.0; 3], [0.0; 3]));
+    system.add(Body::new("Particle".to_string(), 0.0, 0.0, position, velocity));
+    (system, elements)
+}
+fn kepler_error(integrator: Integrator, eccentricity: f64, steps: usize) -> f64 {
+    let (mut system, elements) = kepler_system(eccentricity);
+    let duration = elements.period(1.0) * 1.25;
+    integrate(&mut system, integrator, duration, steps);
+    let (position, _) = elements.propagate(1.0, duration).to_state(1.0);
+    vector::distance(&position, &state(&system, 1).0)
+}
+#[test]
+fn kepler_orbits_within_bounds() -> Result<(), String> {
+    for integrator in Integrator::all().iter() {
+        let scale = if integrator.order() > 2 { 1.0E-2 } else { 1.0 };
+        for (eccentricity, steps, bound) in [(0.0, 1000, 2.0E-4), (0.3, 1000, 2.0E-4), (0.6, 2000, 2.0E-4), (0.9, 8000, 5.0E-4)].iter() {
+            let error = kepler_error(*integrator, *eccentricity, *steps);
+            let bound = bound * scale;
+            println!("{} e = {}: {:e} AU after {} steps", integrator.name(), eccentricity, error, steps);
+            assert!(error < bound, "{} e = {} off by {:e} AU", integrator.name(), eccentricity, error);
+        }
+    }
+    Ok(())
+}
+#[test]
+fn kepler_convergence_order() -> Result<(), String> {
+    for integrator in Integrator::all().iter() {
+        for (eccentricity, steps) in [(0.0, 250), (0.3, 250), (0.6, 500), (0.9, 4000)].iter() {
+            let coarse = kepler_error(*integrator, *eccentricity, *steps);
+            let fine = kepler_error(*integrator, *eccentricity, *steps * 2);
+            let order = (coarse / fine).log2();
+            println!("{} e = {}: observed order {:.3}", integrator.name(), eccentricity, order);
+            assert!((order - integrator.order() as f64).abs() < 0.3,
+                "{} e = {} converges with order {:.3} instead of {}", integrator.name(), eccentricity, order, integrator.order());
+        }
+    }
+    Ok(())
+}
+const FIGURE_EIGHT_PERIOD: f64 = 6.32591398;
+fn figure_eight_system() -> System {
+    let position = [0.97000436, -0.24308753, 0.0];
+    let velocity = [-0.93240737, -0.86473146, 0.0];
+    let mut system = System::new();
+    system.add(Body::new("A".to_string(), gravitational_mass(1.0), 0.0, position,
+        vector::scale(&velocity, -0.5)));
+    system.add(Body::new("B".to_string(), gravitational_mass(1.0), 0.0, vector::scale(&position, -1.0),
+        vector::scale(&velocity, -0.5)));
+    system.add(Body::new("C".to_string(), gravitational_mass(1.0), 0.0, [0.0; 3], velocity));
+    system
+}
+#[test]
+fn figure_eight_choreography() -> Result<(), String> {
+    for integrator in Integrator::all().iter() {
+        let initial = figure_eight_system();
+        let mut system = figure_eight_system();
+        integrate(&mut system, *integrator, FIGURE_EIGHT_PERIOD / 3.0, 1000);
+        let shifted = (0..3).map(|i| {
+            (0..3).map(|j| vector::distance(&state(&system, i).0, &state(&initial, j).0)).fold(f64::MAX, f64::min)
+        }).fold(0.0, f64::max);
+        integrate(&mut system, *integrator, FIGURE_EIGHT_PERIOD * 2.0 / 3.0, 2000);
+        let returned = (0..3).map(|i| vector::distance(&state(&system, i).0, &state(&initial, i).0))
+            .fold(0.0, f64::max);
+        println!("{}: {:e} after a third of a period, {:e} after a period", integrator.name(), shifted, returned);
+        assert!(shifted < 1.0E-4, "{} failed to trade places, off by {:e}", integrator.name(), shifted);
+        assert!(returned < 1.0E-4, "{} failed to return, off by {:e}", integrator.name(), returned);
+    }
+    Ok(())
+}
+const MASS_RATIO: f64 = 1.0E-3;
+fn restricted_system(offset: f64) -> System {
+    let mut system = System::new();
+    system.add(Body::new("Primary".to_string(), gravitational_mass(1.0 - MASS_RATIO), 0.0,
+        [-MASS_RATIO, 0.0, 0.0], [0.0, -MASS_RATIO, 0.0]));
+    system.add(Body::new("Secondary".to_string(), gravitational_mass(MASS_RATIO), 0.0,
+        [1.0 - MASS_RATIO, 0.0, 0.0], [0.0, 1.0 - MASS_RATIO, 0.0]));
+    for (name, sign) in [("L4", 1.0), ("L5", -1.0)].iter() {
+        let angle = sign * (PI / 3.0 + offset);
+        let position = [angle.cos() - MASS_RATIO, angle.sin(), 0.0];
+        system.add(Body::new(name.to_string(), 0.0, 0.0, position, [-position[1], position[0], 0.0]));
+    }
+    system
+}
+fn angle_from_secondary(system: &System, index: usize) -> f64 {
+    let (primary, _) = state(system, 0);
+    let (secondary, _) = state(system, 1);
+    let (particle, _) = state(system, index);
+    let secondary = vector::subtract(&secondary, &primary);
+    let particle = vector::subtract(&particle, &primary);
+    vector::cross(&secondary, &particle)[2].atan2(vector::dot(&secondary, &particle)).to_degrees()
+}
+#[test]
+fn lagrange_points_stay_put() -> Result<(), String> {
+    for integrator in Integrator::all().iter() {
+        let mut system = restricted_system(0.0);
+        integrate(&mut system, *integrator, 20.0 * 2.0 * PI, 20 * 100);
+        let leading = angle_from_secondary(&system, 2);
+        let trailing = angle_from_secondary(&system, 3);
+        println!("{}: L4 at {:.6} and L5 at {:.6} degrees after 20 orbits", integrator.name(), leading, trailing);
+        assert!((leading - 60.0).abs() < 1.0E-3, "{} drifted from L4 to {}", integrator.name(), leading);
+        assert!((trailing + 60.0).abs() < 1.0E-3, "{} drifted from L5 to {}", integrator.name(), trailing);
+    }
+    Ok(())
+}
+#[test]
+fn lagrange_tadpoles_librate() -> Result<(), String> {
+    for integrator in Integrator::all().iter() {
+        let mut system = restricted_system(0.2);
+        system.set_integrator(*integrator);
+        system.set_delta_time(2.0 * PI / 100.0);
+        let mut leading = (f64::MAX, f64::MIN);
+        let mut trailing = (f64::MAX, f64::MIN);
+        for _ in 0..(100 * 50) {
+            system.step();
+            let a = angle_from_secondary(&system, 2);
+            let b = angle_from_secondary(&system, 3);
+            leading = (leading.0.min(a), leading.1.max(a));
+            trailing = (trailing.0.min(b), trailing.1.max(b));
+        }
+        println!("{}: L4 tadpole from {:.2} to {:.2}, L5 tadpole from {:.2} to {:.2} degrees",
+            integrator.name(), leading.0, leading.1, trailing.0, trailing.1);
+        assert!(leading.0 > 20.0 && leading.1 < 120.0 && leading.1 - leading.0 > 10.0,
+            "{} L4 tadpole spans {:?}", integrator.name(), leading);
+        assert!(trailing.1 < -20.0 && trailing.0 > -120.0 && trailing.1 - trailing.0 > 10.0,
+            "{} L5 tadpole spans {:?}", integrator.name(), trailing);
+    }
+    Ok(())
+}
@@ -1,54 +1,109 @@
 extern crate nbodysim;
 use ansi_term::Colour;
-use nbodysim::system::{
-   body::Body,
-   System 
+use nbodysim::{
+    system::integrator::Integrator,
+    timescale::{
+        Epoch,
+        TimeScale
+    },
+    validation::{
+        self,
+        ReferenceEphemeris,
+        Tolerance,
+        Tolerances,
+        ValidationReport
+    }
 };
-use float_eq::assert_float_eq;
-fn approximate_compare(control_star: &Body, control_body: &Body, test_star: &Body, test_body: &Body) {
-    println!("  Approximately comparing test {} to HORIZONS {}...", test_body.name(), control_body.name());
-    let position_difference = control_body.position().iter()
-        .zip(test_body.position().iter()).map(|(c, t)| {(c - t).powf(2.0)})
-        .sum::<f64>().sqrt();
-    let control_difference = control_star.position().iter()
-        .zip(control_body.position().iter()).map(|(c, t)| {(c - t).powf(2.0)})
-        .sum::<f64>().sqrt();
-    let test_difference = test_star.position().iter()
-        .zip(test_body.position().iter()).map(|(c, t)| {(c - t).powf(2.0)})
-        .sum::<f64>().sqrt();
-    println!("    World position difference of {}...", position_difference);
-    match std::panic::catch_unwind(|| {assert_float_eq!(test_difference, control_difference, r2nd <= 2.0E-2f64)}) {
-        Ok(_) => println!("      {}: Distance to sun within 1% of HORIZONS data", Colour::Green.bold().paint("Passed")),
-        Err(_) => println!("      {}: Distance to sun check!", Colour::Red.bold().paint("Failed"))
+const REGRESSION_RUN: &str = "tests/data/fistful_of_satellites_1969_07_16_regression.json";
+const HORIZONS_REFERENCE: &str = "tests/data/fistful_of_satellites_1969_07_16_horizons.json";
+const FISTFUL_OF_SATELLITES: [&str; 22] = ["10", "199", "299", "399", "499", "599", "699", "799", "899", "999",
+    "A801 AA", "A807 FA", "A802 FA", "A849 GA", "A854 RA", "A910 TC", "A903 KB", "A904 HE", "A851 OA", "A804 RA",
+    "A852 FA", "A858 CA"];
+const FISTFUL_OF_SATELLITES_DATES: [f64; 5] = [2440418.5, 2440448.5, 2440509.5, 2440600.5, 2440783.5];
+fn print_report(report: &ValidationReport) {
+    for epoch in report.epoch.iter() {
+        println!("  {} epoch {}...", Colour::Yellow.bold().paint("Comparing"), epoch.epoch);
+        for body in epoch.body.iter() {
+            let status = if body.passed {
+                Colour::Green.bold().paint("Passed")
+            } else {
+                Colour::Red.bold().paint("Failed")
+            };
+            println!("    {}: {} off by {:.3} km and {:.6} m/s", status, body.name, body.position, body.velocity);
+        }
+        for name in epoch.missing.iter() {
+            println!("    {}: {} missing", Colour::Red.bold().paint("Failed"), name);
+        }
+    }
+}
+fn check(report: ValidationReport) -> Result<(), String> {
+    print_report(&report);
+    if report.passed {
+        Ok(())
+    } else {
+        Err(format!("Failed validation against {}!\n{}", report.reference, report.failures().join("\n")))
+    }
+}
+fn horizons_reference() -> Result<ReferenceEphemeris, String> {
+    let reference = match ReferenceEphemeris::load_json(HORIZONS_REFERENCE) {
+        Err(x) => return Err(format!("Failed to load {}! It holds HORIZONS vectors of FISTFUL_OF_SATELLITES at \
+            FISTFUL_OF_SATELLITES_DATES, fetch them with ReferenceEphemeris::fetch_from_horizons and commit the file\n{}",
+            HORIZONS_REFERENCE, x)),
+        Ok(x) => x
+    };
+    let epochs: Vec<f64> = reference.snapshot.iter().map(|a| a.epoch()).collect();
+    if epochs != FISTFUL_OF_SATELLITES_DATES || reference.snapshot.iter().any(|a| a.size() != FISTFUL_OF_SATELLITES.len()) {
+        return Err(format!("Failed to use {}! Expected {} bodies at epochs {:?} but found epochs {:?}",
+            HORIZONS_REFERENCE, FISTFUL_OF_SATELLITES.len(), FISTFUL_OF_SATELLITES_DATES, epochs))
     }
+    Ok(reference)
 }
-fn simulate_planets_for(days: usize) -> Result<(), String> {
+fn validate_horizons_reference(integrator: Integrator, relative: f64) -> Result<(), String> {
+    let reference = horizons_reference()?;
+    let tolerances = Tolerances::new(Tolerance {
+        relative: Some(relative),
+        ..Tolerance::default()
+    });
+    println!("{} {} against {}", Colour::Blue.bold().paint("Validating"), integrator.name(), reference.source);
+    check(validation::validate(&reference, integrator, 1.0 / 24.0, &tolerances)?)
+}
+#[test]
+fn validate_kick_drift_kick_against_stored_horizons() -> Result<(), String> {
+    validate_horizons_reference(Integrator::KickDriftKick, 2.0E-4)
+}
+#[test]
+fn validate_yoshida4_against_stored_horizons() -> Result<(), String> {
+    validate_horizons_reference(Integrator::Yoshida4, 1.0E-4)
+}
+fn regress_against_converged_run(integrator: Integrator, position: f64) -> Result<(), String> {
+    let reference = ReferenceEphemeris::load_json(REGRESSION_RUN)?;
+    let tolerances = Tolerances::new(Tolerance {
+        position: Some(position),
+        ..Tolerance::default()
+    });
+    println!("{} {} against {}", Colour::Blue.bold().paint("Regressing"), integrator.name(), reference.source);
+    check(validation::validate(&reference, integrator, 1.0 / 24.0, &tolerances)?)
+}
+#[test]
+fn regress_kick_drift_kick() -> Result<(), String> {
+    regress_against_converged_run(Integrator::KickDriftKick, 2.0E4)
+}
+#[test]
+fn regress_yoshida4() -> Result<(), String> {
+    regress_against_converged_run(Integrator::Yoshida4, 50.0)
+}
+fn simulate_planets_for(days: i64) -> Result<(), String> {
     let list = vec!(r"Sun", r"199", r"299", r"399", r"499", r"599", r"699", r"799", r"899", r"999",
         r"A801 AA", r"A807 FA", r"A802 FA", r"301");
-    let mut date = time::Date::try_from_ymd(1945,07,16).unwrap();
-    println!("{} test system...", Colour::Blue.bold().paint("Building"));
-    let mut test = System::fetch_from_horizons(&list, &date).unwrap();
+    let start = Epoch::from_calendar(1945, 7, 16, 0, 0, 0.0, TimeScale::Tdb)?;
+    println!("{} reference from HORIZONS...", Colour::Blue.bold().paint("Fetching"));
+    let reference = ReferenceEphemeris::fetch_from_horizons(&list, &[start, start.add_days(days as f64)])?;
     println!("{} simulation for {} days (this could take a while)", Colour::Blue.bold().paint("Running"), days);
-    (0..days).into_iter().for_each(|_| {
-        let hour = 1.0/24.0;
-        let remainder = 1.0 - (hour * 23.0);
-        (0..23).into_iter().for_each(|_| {
-            test.kick_drift_kick_step(hour);
-        });
-        test.kick_drift_kick_step(remainder);
-        date = date.next_day();
+    let tolerances = Tolerances::new(Tolerance {
+        relative: Some(2.0E-2),
+        ..Tolerance::default()
     });
-    println!("{} control system...", Colour::Blue.bold().paint("Building"));
-    let control = System::fetch_from_horizons(&list, &date).unwrap();
-    println!("{} system body distances to star...", Colour::Yellow.bold().paint("Comparing"));
-    (0..control.size()).into_iter().for_each(|x| {
-        approximate_compare(
-            &control.object_from_index(0).unwrap().read().unwrap(),
-            &control.object_from_index(x).unwrap().read().unwrap(),
-            &test.object_from_index(0).unwrap().read().unwrap(),
-            &test.object_from_index(x).unwrap().read().unwrap())
-    });
-    Ok(())
+    check(validation::validate(&reference, Integrator::KickDriftKick, 1.0 / 24.0, &tolerances)?)
 }
 #[test]
 fn simulate_planets_for_day() -> Result<(), String> {
@@ -97,4 +152,4 @@ fn simulate_planets_for_half_century() -> Result<(), String> {
 #[test]
 fn simulate_planets_for_century() -> Result<(), String> {
     simulate_planets_for(36525)
-}
\ No newline at end of file
+}